@@ -0,0 +1,366 @@
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedCommand {
+    /// The argv OpenCode will receive after platform normalization.
+    pub command: Vec<String>,
+    /// Absolute path of the executable that would run, if it was found.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub executable: Option<String>,
+    /// Where the executable was found: "path", or the shim or tool directory
+    /// that supplied it ("nvm", "volta", "asdf", "mise", ...).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(rename = "shellSyntax", skip_serializing_if = "Vec::is_empty")]
+    pub shell_syntax: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+// ============== Normalization ==============
+
+/// Expands a leading `~`, to find the program the way a shell would. Saved
+/// commands keep the `~`, so configs stay portable between users.
+fn expand_tilde(arg: &str) -> String {
+    let Some(home) = dirs::home_dir() else {
+        return arg.to_string();
+    };
+    if arg == "~" {
+        home.display().to_string()
+    } else if let Some(rest) = arg.strip_prefix("~/") {
+        home.join(rest).display().to_string()
+    } else {
+        arg.to_string()
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub fn normalize(command: Vec<String>) -> Vec<String> {
+    if command.is_empty() {
+        return command;
+    }
+    let first = command[0].to_lowercase();
+    let needs_shell = first == "npx"
+        || first == "npm"
+        || first == "node"
+        || first == "pnpm"
+        || first == "yarn"
+        || first == "bunx"
+        || first == "bun"
+        || first.ends_with(".cmd")
+        || first.ends_with(".bat");
+
+    if needs_shell {
+        let mut wrapped = vec!["cmd".to_string(), "/c".to_string()];
+        wrapped.extend(command);
+        wrapped
+    } else {
+        command
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn normalize(command: Vec<String>) -> Vec<String> {
+    command
+}
+
+/// The command without the `cmd /c` wrapper that `normalize` adds on
+/// Windows, and that commands saved there already carry.
+fn unwrap_cmd(command: &[String]) -> &[String] {
+    match command {
+        [shell, flag, rest @ ..]
+            if shell.eq_ignore_ascii_case("cmd") && flag.eq_ignore_ascii_case("/c") =>
+        {
+            rest
+        }
+        _ => command,
+    }
+}
+
+/// The program that actually matters, looking through a `cmd /c` wrapper.
+fn effective_program(command: &[String]) -> Option<&str> {
+    unwrap_cmd(command).first().map(String::as_str)
+}
+
+// ============== Shell syntax ==============
+
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "fish", "cmd", "powershell", "pwsh"];
+
+const OPERATORS: &[&str] = &["&&", "||", "|", ";", ">>", ">", "<", "2>&1"];
+
+/// Finds constructs that only a shell would interpret. They are passed to
+/// the server verbatim, which is almost never what was intended.
+pub fn detect_shell_syntax(command: &[String]) -> Vec<String> {
+    let command = unwrap_cmd(command);
+    let Some(first) = command.first() else {
+        return Vec::new();
+    };
+    let name = Path::new(first)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if SHELLS.contains(&name.as_str()) {
+        return Vec::new();
+    }
+
+    let mut found = Vec::new();
+    for arg in command {
+        if arg.contains("{env:") || arg.contains("{file:") {
+            continue;
+        }
+        if let Some(op) = OPERATORS.iter().find(|op| arg == *op) {
+            found.push(format!("'{op}' is a shell operator"));
+        } else if arg.contains("$(") || arg.contains('`') {
+            found.push(format!("'{arg}' uses command substitution"));
+        } else if arg.starts_with('~') || arg.contains("=~/") {
+            found.push(format!("'{arg}' relies on shell tilde expansion"));
+        } else if arg.contains("${") || has_shell_variable(arg) {
+            found.push(format!(
+                "'{arg}' references a shell variable; use {{env:NAME}} instead"
+            ));
+        } else if (arg.contains('*') || arg.contains('?')) && !arg.contains("://") {
+            found.push(format!("'{arg}' looks like a glob, which is not expanded"));
+        }
+    }
+    found
+}
+
+fn has_shell_variable(arg: &str) -> bool {
+    arg.match_indices('$').any(|(i, _)| {
+        arg[i + 1..]
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+    })
+}
+
+// ============== Executable lookup ==============
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(windows)]
+fn candidates(dir: &Path, program: &str) -> Vec<PathBuf> {
+    if Path::new(program).extension().is_some() {
+        return vec![dir.join(program)];
+    }
+    let pathext = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".into());
+    pathext
+        .split(';')
+        .filter(|ext| !ext.is_empty())
+        .map(|ext| dir.join(format!("{program}{}", ext.to_lowercase())))
+        .collect()
+}
+
+#[cfg(not(windows))]
+fn candidates(dir: &Path, program: &str) -> Vec<PathBuf> {
+    vec![dir.join(program)]
+}
+
+/// The newest Node.js installed by nvm, preferring the default alias.
+fn nvm_bin(home: &Path) -> Option<PathBuf> {
+    let versions = home.join(".nvm").join("versions").join("node");
+    if let Ok(alias) = std::fs::read_to_string(home.join(".nvm").join("alias").join("default")) {
+        let alias = alias.trim().trim_start_matches('v');
+        let mut matching: Vec<PathBuf> = std::fs::read_dir(&versions)
+            .ok()?
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.trim_start_matches('v').starts_with(alias))
+            })
+            .collect();
+        matching.sort_by_key(|p| version_key(p));
+        if let Some(best) = matching.pop() {
+            return Some(best.join("bin"));
+        }
+    }
+
+    let mut installed: Vec<PathBuf> = std::fs::read_dir(&versions)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .collect();
+    installed.sort_by_key(|p| version_key(p));
+    installed.pop().map(|p| p.join("bin"))
+}
+
+fn version_key(path: &Path) -> Vec<u64> {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default()
+        .trim_start_matches('v')
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Directories that GUI-launched apps often miss from `PATH` because they
+/// are only added by shell startup files.
+fn extra_dirs() -> Vec<(&'static str, PathBuf)> {
+    let mut found = Vec::new();
+    if let Some(home) = dirs::home_dir() {
+        let share = home.join(".local").join("share");
+        found.push(("volta", home.join(".volta").join("bin")));
+        found.push(("asdf", home.join(".asdf").join("shims")));
+        found.push(("mise", share.join("mise").join("shims")));
+        if let Some(nvm) = nvm_bin(&home) {
+            found.push(("nvm", nvm));
+        }
+        found.push(("fnm", share.join("fnm").join("aliases").join("default").join("bin")));
+        found.push(("bun", home.join(".bun").join("bin")));
+        found.push(("cargo", home.join(".cargo").join("bin")));
+        found.push(("local", home.join(".local").join("bin")));
+    }
+    if cfg!(windows) {
+        if let Some(data) = dirs::data_dir() {
+            found.push(("npm", data.join("npm")));
+        }
+    } else {
+        found.push(("homebrew", PathBuf::from("/opt/homebrew/bin")));
+        found.push(("local", PathBuf::from("/usr/local/bin")));
+    }
+    found
+}
+
+/// Searches `PATH` (the server's own override first, if it sets one), then
+/// the well-known shim directories.
+pub fn find_executable(
+    program: &str,
+    environment: Option<&IndexMap<String, String>>,
+) -> Option<(PathBuf, String)> {
+    let program = expand_tilde(program);
+    let explicit = Path::new(&program);
+    if explicit.components().count() > 1 {
+        return is_executable(explicit).then(|| (explicit.to_path_buf(), "explicit".to_string()));
+    }
+
+    let override_path = environment.and_then(|env| env.get("PATH")).cloned();
+    let path_dirs = override_path
+        .into_iter()
+        .chain(std::env::var("PATH").ok())
+        .flat_map(|value| std::env::split_paths(&value).collect::<Vec<_>>())
+        .map(|dir| ("path", dir));
+
+    path_dirs
+        .chain(extra_dirs())
+        .flat_map(|(source, dir)| {
+            candidates(&dir, &program)
+                .into_iter()
+                .map(move |candidate| (source, candidate))
+        })
+        .find(|(_, candidate)| is_executable(candidate))
+        .map(|(source, path)| (path, source.to_string()))
+}
+
+pub fn resolve(
+    command: &[String],
+    environment: Option<&IndexMap<String, String>>,
+) -> ResolvedCommand {
+    let shell_syntax = detect_shell_syntax(command);
+    let command = normalize(command.to_vec());
+    let mut resolved = ResolvedCommand {
+        shell_syntax,
+        command,
+        executable: None,
+        source: None,
+        warnings: Vec::new(),
+    };
+
+    let Some(program) = effective_program(&resolved.command) else {
+        resolved.warnings.push("The command is empty".to_string());
+        return resolved;
+    };
+
+    if program.contains(' ') && !Path::new(&expand_tilde(program)).exists() {
+        resolved.warnings.push(format!(
+            "'{program}' contains spaces; put each argument in its own entry"
+        ));
+    }
+
+    match find_executable(program, environment) {
+        Some((path, source)) => {
+            if source != "path" && source != "explicit" {
+                resolved.warnings.push(format!(
+                    "'{program}' is not on PATH; found it in {} ({source})",
+                    path.parent().unwrap_or(&path).display()
+                ));
+            }
+            resolved.executable = Some(path.display().to_string());
+            resolved.source = Some(source);
+        }
+        None => resolved
+            .warnings
+            .push(format!("'{program}' was not found on PATH or in any known shim directory")),
+    }
+
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn keeps_tilde_as_entered_and_flags_it() {
+        let resolved = resolve(&argv(&["node", "~/server.js"]), None);
+        assert_eq!(resolved.shell_syntax.len(), 1, "{:?}", resolved.shell_syntax);
+        assert!(resolved.shell_syntax[0].contains("tilde"));
+        assert_eq!(resolved.command[1], "~/server.js");
+        assert_eq!(normalize(argv(&["~/bin/server", "~/data"])), argv(&["~/bin/server", "~/data"]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn finds_programs_under_home() {
+        use std::os::unix::fs::PermissionsExt;
+
+        crate::config::tests::sandbox_home();
+        let bin = dirs::home_dir().unwrap().join("tools");
+        std::fs::create_dir_all(&bin).unwrap();
+        let program = bin.join("resolver-test-server");
+        std::fs::write(&program, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let resolved = resolve(&argv(&["~/tools/resolver-test-server"]), None);
+        assert_eq!(resolved.command, argv(&["~/tools/resolver-test-server"]));
+        assert_eq!(resolved.executable, Some(program.display().to_string()));
+        assert_eq!(resolved.source.as_deref(), Some("explicit"));
+    }
+
+    #[test]
+    fn looks_past_a_cmd_wrapper() {
+        let found = detect_shell_syntax(&argv(&["cmd", "/c", "npx", "server", "|", "tee", "log"]));
+        assert_eq!(found, vec!["'|' is a shell operator".to_string()]);
+        assert!(detect_shell_syntax(&argv(&["bash", "-c", "a && b"])).is_empty());
+        assert_eq!(effective_program(&argv(&["CMD", "/C", "npx"])), Some("npx"));
+    }
+
+    #[test]
+    fn leaves_placeholders_and_urls_alone() {
+        let found = detect_shell_syntax(&argv(&[
+            "server",
+            "--token={env:TOKEN}",
+            "https://example.com/?q=1",
+        ]));
+        assert!(found.is_empty(), "{found:?}");
+    }
+}
@@ -1,6 +1,9 @@
+use crate::command_resolver;
 use crate::error::AppError;
+use crate::permissions;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::{Map, Value};
+use indexmap::IndexMap;
 use std::fs;
 use std::path::PathBuf;
 
@@ -28,7 +31,7 @@ pub struct McpServer {
     pub command: Option<Vec<String>>,
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub environment: Option<HashMap<String, String>>,
+    pub environment: Option<IndexMap<String, String>>,
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub enabled: Option<bool>,
@@ -40,39 +43,12 @@ pub struct McpServer {
     pub url: Option<String>,
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub headers: Option<HashMap<String, String>>,
+    pub headers: Option<IndexMap<String, String>>,
 }
 
 impl McpServer {
-    #[cfg(target_os = "windows")]
     pub fn normalize_command_for_platform(command: Vec<String>) -> Vec<String> {
-        if command.is_empty() {
-            return command;
-        }
-
-        let first = command[0].to_lowercase();
-        let needs_shell = first == "npx"
-            || first == "npm"
-            || first == "node"
-            || first == "pnpm"
-            || first == "yarn"
-            || first == "bunx"
-            || first == "bun"
-            || first.ends_with(".cmd")
-            || first.ends_with(".bat");
-
-        if needs_shell {
-            let mut wrapped = vec!["cmd".to_string(), "/c".to_string()];
-            wrapped.extend(command);
-            wrapped
-        } else {
-            command
-        }
-    }
-
-    #[cfg(not(target_os = "windows"))]
-    pub fn normalize_command_for_platform(command: Vec<String>) -> Vec<String> {
-        command
+        command_resolver::normalize(command)
     }
 }
 
@@ -94,7 +70,7 @@ pub struct ProviderOptions {
     #[serde(rename = "apiKey", default, skip_serializing_if = "Option::is_none")]
     pub api_key: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub headers: Option<HashMap<String, String>>,
+    pub headers: Option<IndexMap<String, String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -102,7 +78,7 @@ pub struct ProviderConfig {
     pub npm: String,
     pub name: String,
     pub options: ProviderOptions,
-    pub models: HashMap<String, OpenCodeModel>,
+    pub models: IndexMap<String, OpenCodeModel>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -114,13 +90,182 @@ pub struct OpenCodeConfig {
     pub plugin: Option<Vec<String>>,
 
     #[serde(default)]
-    pub provider: HashMap<String, ProviderConfig>,
+    pub provider: IndexMap<String, ProviderConfig>,
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub mcp: Option<HashMap<String, McpServer>>,
+    pub mcp: Option<IndexMap<String, McpServer>>,
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub instructions: Option<Vec<String>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub small_model: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<Map<String, Value>>,
+
+    /// Keys Open Switch does not manage, kept so saving never drops them.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+// ============== Key order ==============
+
+/// Renames a key in place, keeping its position. Returns false if `old` is
+/// not present.
+pub fn rename_key<V>(map: &mut IndexMap<String, V>, old: &str, new: &str) -> bool {
+    match map.shift_remove_full(old) {
+        Some((index, _, value)) => {
+            map.shift_insert(index, new.to_string(), value);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Inserts `value` right after `after`, or at the end if it is missing.
+pub fn insert_after<V>(map: &mut IndexMap<String, V>, after: &str, key: String, value: V) {
+    match map.get_index_of(after) {
+        Some(index) => {
+            map.shift_insert(index + 1, key, value);
+        }
+        None => {
+            map.insert(key, value);
+        }
+    }
+}
+
+/// Moves the listed keys to the front in the given order; keys that are not
+/// listed keep their relative order after them.
+pub fn reorder_keys<V>(map: &mut IndexMap<String, V>, order: &[String]) -> Result<(), AppError> {
+    if let Some(unknown) = order.iter().find(|key| !map.contains_key(key.as_str())) {
+        return Err(AppError::InvalidInput(format!("Unknown key '{unknown}'")));
+    }
+    let rank = |key: &String| order.iter().position(|k| k == key).unwrap_or(order.len());
+    map.sort_by_cached_key(|key, _| rank(key));
+    Ok(())
+}
+
+/// Rewrites a `provider/model` reference; returns whether it changed.
+fn rename_model_ref(value: &mut String, old: &str, new: &str) -> bool {
+    match value.strip_prefix(old).and_then(|rest| rest.strip_prefix('/')) {
+        Some(model) => {
+            *value = format!("{new}/{model}");
+            true
+        }
+        None => false,
+    }
+}
+
+/// The server a `tools` key (`<server>_<tool>` or `<server>_*`) belongs to.
+/// The longest matching name wins, so `foo_bar_query` belongs to `foo_bar`
+/// rather than `foo` when both exist.
+fn tool_key_server<'a>(key: &str, servers: &[&'a str]) -> Option<&'a str> {
+    servers
+        .iter()
+        .filter(|server| {
+            key.strip_prefix(**server)
+                .is_some_and(|rest| rest.starts_with('_'))
+        })
+        .max_by_key(|server| server.len())
+        .copied()
+}
+
+/// Renames the `tools` keys that belong to MCP server `old` without moving
+/// them within the map.
+fn rename_tool_keys(tools: &mut Map<String, Value>, servers: &[&str], old: &str, new: &str) -> bool {
+    let belongs = |key: &str| tool_key_server(key, servers) == Some(old);
+    if !tools.keys().any(|key| belongs(key)) {
+        return false;
+    }
+    *tools = std::mem::take(tools)
+        .into_iter()
+        .map(|(key, value)| {
+            if belongs(&key) {
+                (format!("{new}{}", &key[old.len()..]), value)
+            } else {
+                (key, value)
+            }
+        })
+        .collect();
+    true
+}
+
+impl OpenCodeConfig {
+    /// Agents configured under `agent`, plus the older `mode` section.
+    fn agent_sections_mut(&mut self) -> Vec<(&'static str, &mut Map<String, Value>)> {
+        let mut sections = Vec::new();
+        if let Some(agent) = self.agent.as_mut() {
+            sections.push(("agent", agent));
+        }
+        if let Some(Value::Object(mode)) = self.extra.get_mut("mode") {
+            sections.push(("mode", mode));
+        }
+        sections
+    }
+
+    /// Points every model reference and provider list at `new`. Returns the
+    /// paths that were changed.
+    pub fn rename_provider_references(&mut self, old: &str, new: &str) -> Vec<String> {
+        let mut changed = Vec::new();
+        for (path, value) in [("model", self.model.as_mut()), ("small_model", self.small_model.as_mut())] {
+            if value.is_some_and(|v| rename_model_ref(v, old, new)) {
+                changed.push(path.to_string());
+            }
+        }
+
+        for (section, agents) in self.agent_sections_mut() {
+            for (name, agent) in agents.iter_mut() {
+                if let Some(Value::String(model)) = agent.get_mut("model") {
+                    if rename_model_ref(model, old, new) {
+                        changed.push(format!("{section}.{name}.model"));
+                    }
+                }
+            }
+        }
+
+        for list in ["enabled_providers", "disabled_providers"] {
+            if let Some(Value::Array(ids)) = self.extra.get_mut(list) {
+                for id in ids.iter_mut().filter(|id| id.as_str() == Some(old)) {
+                    *id = Value::String(new.to_string());
+                    changed.push(list.to_string());
+                }
+            }
+        }
+        changed
+    }
+
+    /// Renames the tool permissions that refer to an MCP server's tools.
+    /// Call after the server itself was renamed in `mcp`.
+    pub fn rename_mcp_references(&mut self, old: &str, new: &str) -> Vec<String> {
+        let names: Vec<String> = self
+            .mcp
+            .iter()
+            .flat_map(|mcp| mcp.keys())
+            .map(|name| if name == new { old.to_string() } else { name.clone() })
+            .collect();
+        let servers: Vec<&str> = names.iter().map(String::as_str).collect();
+
+        let mut changed = Vec::new();
+        if let Some(Value::Object(tools)) = self.extra.get_mut("tools") {
+            if rename_tool_keys(tools, &servers, old, new) {
+                changed.push("tools".to_string());
+            }
+        }
+        for (section, agents) in self.agent_sections_mut() {
+            for (name, agent) in agents.iter_mut() {
+                if let Some(Value::Object(tools)) = agent.get_mut("tools") {
+                    if rename_tool_keys(tools, &servers, old, new) {
+                        changed.push(format!("{section}.{name}.tools"));
+                    }
+                }
+            }
+        }
+        changed
+    }
 }
 
 impl Default for OpenCodeConfig {
@@ -128,9 +273,13 @@ impl Default for OpenCodeConfig {
         Self {
             schema: Some("https://opencode.ai/config.json".to_string()),
             plugin: None,
-            provider: HashMap::new(),
+            provider: IndexMap::new(),
             mcp: None,
             instructions: None,
+            model: None,
+            small_model: None,
+            agent: None,
+            extra: Map::new(),
         }
     }
 }
@@ -176,13 +325,78 @@ pub fn save_opencode_config(config: &OpenCodeConfig) -> Result<(), AppError> {
 // ============== Auth/Credentials Management ==============
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Credential {
-    #[serde(rename = "type")]
-    pub credential_type: String,
-    pub key: String,
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Credential {
+    Api {
+        key: String,
+        #[serde(flatten)]
+        extra: Map<String, Value>,
+    },
+    Oauth {
+        refresh: String,
+        access: String,
+        /// Expiry of the access token in milliseconds since the Unix epoch.
+        expires: i64,
+        #[serde(flatten)]
+        extra: Map<String, Value>,
+    },
+    Wellknown {
+        key: String,
+        token: String,
+        #[serde(flatten)]
+        extra: Map<String, Value>,
+    },
+    /// Entries written by newer OpenCode versions are kept verbatim.
+    #[serde(untagged)]
+    Unknown(Value),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CredentialStatus {
+    pub kind: String,
+    #[serde(rename = "expiresAt", skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    #[serde(rename = "expiresInSecs", skip_serializing_if = "Option::is_none")]
+    pub expires_in_secs: Option<i64>,
+    pub expired: bool,
 }
 
-pub type AuthConfig = HashMap<String, Credential>;
+impl Credential {
+    pub fn api(key: String) -> Self {
+        Credential::Api {
+            key,
+            extra: Map::new(),
+        }
+    }
+
+    pub fn kind(&self) -> String {
+        match self {
+            Credential::Api { .. } => "api".to_string(),
+            Credential::Oauth { .. } => "oauth".to_string(),
+            Credential::Wellknown { .. } => "wellknown".to_string(),
+            Credential::Unknown(value) => value
+                .get("type")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown")
+                .to_string(),
+        }
+    }
+
+    pub fn status(&self, now_ms: i64) -> CredentialStatus {
+        let expires_at = match self {
+            Credential::Oauth { expires, .. } => Some(*expires),
+            _ => None,
+        };
+        CredentialStatus {
+            kind: self.kind(),
+            expires_at,
+            expires_in_secs: expires_at.map(|at| (at - now_ms) / 1000),
+            expired: expires_at.is_some_and(|at| at <= now_ms),
+        }
+    }
+}
+
+pub type AuthConfig = IndexMap<String, Credential>;
 
 pub fn get_auth_path() -> Result<PathBuf, AppError> {
     let home = dirs::home_dir().ok_or_else(|| AppError::Config("Cannot find home directory".into()))?;
@@ -193,7 +407,7 @@ pub fn get_auth_config() -> Result<AuthConfig, AppError> {
     let path = get_auth_path()?;
 
     if !path.exists() {
-        return Ok(HashMap::new());
+        return Ok(IndexMap::new());
     }
 
     let content = fs::read_to_string(&path).map_err(|e| AppError::io(&path, e))?;
@@ -208,16 +422,142 @@ pub fn save_auth_config(config: &AuthConfig) -> Result<(), AppError> {
 
     if let Some(parent) = path.parent() {
         if !parent.exists() {
-            fs::create_dir_all(parent).map_err(|e| AppError::io(parent, e))?;
+            permissions::create_private_dir(parent)?;
         }
     }
 
     let content =
         serde_json::to_string_pretty(config).map_err(|e| AppError::JsonSerialize { source: e })?;
 
-    let temp_path = path.with_extension("json.tmp");
-    fs::write(&temp_path, &content).map_err(|e| AppError::io(&temp_path, e))?;
-    fs::rename(&temp_path, &path).map_err(|e| AppError::io(&path, e))?;
+    permissions::write_private_file(&path, &content)
+}
 
-    Ok(())
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Points HOME at a temporary directory, so the config files, database
+    /// and AGENTS.md that tests write are never the real ones.
+    #[cfg(unix)]
+    pub(crate) fn sandbox_home() {
+        static SANDBOX: std::sync::Once = std::sync::Once::new();
+        SANDBOX.call_once(|| {
+            let home = std::env::temp_dir().join(format!("open-switch-home-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&home);
+            fs::create_dir_all(&home).unwrap();
+            std::env::set_var("HOME", &home);
+        });
+    }
+
+    /// Held by tests that write opencode.json or auth.json, which they
+    /// share. Each starts without either file.
+    #[cfg(unix)]
+    pub(crate) fn config_files() -> std::sync::MutexGuard<'static, ()> {
+        static FILES: std::sync::Mutex<()> = std::sync::Mutex::new(());
+        let guard = FILES.lock().unwrap_or_else(|e| e.into_inner());
+        sandbox_home();
+        let _ = fs::remove_file(get_config_path().unwrap());
+        let _ = fs::remove_file(get_auth_path().unwrap());
+        guard
+    }
+
+    fn resave<T: Serialize + serde::de::DeserializeOwned>(content: &str) -> String {
+        let parsed: T = serde_json::from_str(content).unwrap();
+        serde_json::to_string_pretty(&parsed).unwrap()
+    }
+
+    fn assert_in_order(text: &str, keys: &[&str]) {
+        let positions: Vec<usize> = keys
+            .iter()
+            .map(|key| text.find(&format!("\"{key}\"")).unwrap_or_else(|| panic!("{key} missing")))
+            .collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]), "{keys:?} out of order in {text}");
+    }
+
+    #[test]
+    fn repeated_saves_produce_identical_output() {
+        let written = json!({
+            "zz_custom": { "b": 1, "a": 2 },
+            "provider": {
+                "zeta": {
+                    "npm": "@ai-sdk/openai-compatible",
+                    "name": "Zeta",
+                    "options": {
+                        "baseURL": "https://zeta.example/v1",
+                        "headers": { "X-Zebra": "1", "X-Alpha": "2" }
+                    },
+                    "models": { "zeta-large": { "name": "Large" }, "zeta-base": { "name": "Base" } }
+                },
+                "alpha": {
+                    "npm": "@ai-sdk/anthropic",
+                    "name": "Alpha",
+                    "options": { "baseURL": "https://alpha.example" },
+                    "models": {}
+                }
+            },
+            "mcp": {
+                "web": { "type": "remote", "url": "https://mcp.example", "headers": { "Z": "1", "A": "2" } },
+                "files": { "type": "local", "command": ["files"], "environment": { "ZED": "1", "ABC": "2" } }
+            },
+            "aa_custom": true
+        })
+        .to_string();
+
+        let first = resave::<OpenCodeConfig>(&written);
+        for _ in 0..3 {
+            assert_eq!(resave::<OpenCodeConfig>(&first), first);
+        }
+        assert_in_order(&first, &["zeta", "alpha"]);
+        assert_in_order(&first, &["zeta-large", "zeta-base"]);
+        assert_in_order(&first, &["X-Zebra", "X-Alpha"]);
+        assert_in_order(&first, &["web", "files"]);
+        assert_in_order(&first, &["ZED", "ABC"]);
+        assert_in_order(&first, &["zz_custom", "aa_custom"]);
+    }
+
+    #[test]
+    fn repeated_auth_saves_produce_identical_output() {
+        let written = json!({
+            "zeta": { "type": "api", "key": "sk-zeta" },
+            "alpha": { "type": "oauth", "refresh": "r", "access": "a", "expires": 1, "zz": 1, "aa": 2 }
+        })
+        .to_string();
+
+        let first = resave::<AuthConfig>(&written);
+        assert_eq!(resave::<AuthConfig>(&first), first);
+        assert_in_order(&first, &["zeta", "alpha", "zz", "aa"]);
+    }
+
+    #[test]
+    fn reorders_the_listed_keys_first() {
+        let mut map: IndexMap<String, u8> =
+            ["a", "b", "c", "d"].iter().map(|k| (k.to_string(), 0)).collect();
+        reorder_keys(&mut map, &["c".to_string(), "a".to_string()]).unwrap();
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["c", "a", "b", "d"]);
+        assert!(reorder_keys(&mut map, &["x".to_string()]).is_err());
+    }
+
+    #[test]
+    fn renames_only_the_tools_of_the_renamed_server() {
+        let mut config: OpenCodeConfig = serde_json::from_value(json!({
+            "mcp": {
+                "git": { "type": "local", "command": ["git-mcp"] },
+                "foo": { "type": "local", "command": ["foo"] },
+                "foo_bar": { "type": "local", "command": ["foo-bar"] }
+            },
+            "tools": { "foo_*": false, "foo_bar_query": true, "foo_read_file": true, "git_log": true },
+            "agent": { "review": { "tools": { "foo_bar_*": false } } }
+        }))
+        .unwrap();
+        let mcp = config.mcp.as_mut().unwrap();
+        let server = mcp.shift_remove("foo").unwrap();
+        mcp.insert("baz".to_string(), server);
+
+        let changed = config.rename_mcp_references("foo", "baz");
+        assert_eq!(changed, vec!["tools".to_string()]);
+        let tools: Vec<&String> = config.extra["tools"].as_object().unwrap().keys().collect();
+        assert_eq!(tools, ["baz_*", "foo_bar_query", "baz_read_file", "git_log"]);
+        assert_eq!(config.agent.unwrap()["review"]["tools"], json!({ "foo_bar_*": false }));
+    }
 }
@@ -0,0 +1,396 @@
+use crate::config::{get_auth_config, save_auth_config, Credential};
+use crate::database::Database;
+use crate::error::AppError;
+use crate::secrets::mask_credential;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+pub const DEFAULT_GRACE_DAYS: u32 = 7;
+pub const EXPIRY_WARNING_DAYS: u32 = 14;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialMetadata {
+    pub id: String,
+    #[serde(rename = "createdAt", default)]
+    pub created_at: Option<i64>,
+    #[serde(rename = "lastRotatedAt", default)]
+    pub last_rotated_at: Option<i64>,
+    #[serde(rename = "expiresAt", default)]
+    pub expires_at: Option<i64>,
+    #[serde(rename = "ownerNote", default)]
+    pub owner_note: Option<String>,
+}
+
+impl CredentialMetadata {
+    fn new(id: &str, now: i64) -> Self {
+        Self {
+            id: id.to_string(),
+            created_at: Some(now),
+            last_rotated_at: None,
+            expires_at: None,
+            owner_note: None,
+        }
+    }
+
+    /// Length of the current validity window, used to keep the same cadence
+    /// across rotations when the caller does not pass one explicitly.
+    fn rotation_period(&self) -> Option<i64> {
+        let start = self.last_rotated_at.or(self.created_at)?;
+        self.expires_at.map(|end| end - start).filter(|p| *p > 0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CredentialHistoryEntry {
+    pub history_id: i64,
+    pub credential_id: String,
+    pub credential_json: String,
+    pub rotated_at: i64,
+    pub recoverable_until: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviousCredential {
+    #[serde(rename = "historyId")]
+    pub history_id: i64,
+    #[serde(rename = "credentialId")]
+    pub credential_id: String,
+    #[serde(rename = "rotatedAt")]
+    pub rotated_at: i64,
+    #[serde(rename = "recoverableUntil")]
+    pub recoverable_until: i64,
+    pub credential: Credential,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExpiringCredential {
+    pub id: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: i64,
+    #[serde(rename = "daysLeft")]
+    pub days_left: i64,
+    pub expired: bool,
+    #[serde(rename = "ownerNote", skip_serializing_if = "Option::is_none")]
+    pub owner_note: Option<String>,
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+pub struct CredentialService;
+
+impl CredentialService {
+    pub fn get_metadata(db: &Arc<Database>) -> Result<HashMap<String, CredentialMetadata>, AppError> {
+        Ok(db
+            .get_credential_metadata()?
+            .into_iter()
+            .map(|m| (m.id.clone(), m))
+            .collect())
+    }
+
+    pub fn update_metadata(db: &Arc<Database>, metadata: CredentialMetadata) -> Result<(), AppError> {
+        let existing = Self::get_metadata(db)?.remove(&metadata.id);
+        let merged = CredentialMetadata {
+            created_at: existing.as_ref().and_then(|m| m.created_at).or(metadata.created_at),
+            last_rotated_at: existing.as_ref().and_then(|m| m.last_rotated_at),
+            ..metadata
+        };
+        db.save_credential_metadata(&merged)
+    }
+
+    /// Called whenever a key is written through `set_credential`.
+    pub fn record_set(db: &Arc<Database>, id: &str) -> Result<(), AppError> {
+        if Self::get_metadata(db)?.contains_key(id) {
+            return Ok(());
+        }
+        db.save_credential_metadata(&CredentialMetadata::new(id, now()))
+    }
+
+    /// Forgets a deleted credential, including the previous keys kept for
+    /// its grace period.
+    pub fn forget(db: &Arc<Database>, id: &str) -> Result<(), AppError> {
+        db.delete_credential_records(id)
+    }
+
+    /// Swaps in `new_key` while keeping the previous credential recoverable
+    /// for `grace_days`. The history row is written before auth.json so a
+    /// failure can never lose the old key.
+    pub fn rotate(
+        db: &Arc<Database>,
+        id: &str,
+        new_key: String,
+        grace_days: Option<u32>,
+        valid_days: Option<u32>,
+    ) -> Result<CredentialMetadata, AppError> {
+        let mut auth = get_auth_config()?;
+        let previous = auth
+            .get(id)
+            .cloned()
+            .ok_or_else(|| AppError::InvalidInput(format!("Credential '{id}' not found")))?;
+
+        let rotated = match &previous {
+            Credential::Api { key, .. } if *key == new_key => {
+                return Err(AppError::InvalidInput(
+                    "New key is identical to the current key".to_string(),
+                ));
+            }
+            Credential::Api { extra, .. } => Credential::Api {
+                key: new_key,
+                extra: extra.clone(),
+            },
+            other => {
+                return Err(AppError::InvalidInput(format!(
+                    "Only API key credentials can be rotated, '{id}' is {}",
+                    other.kind()
+                )));
+            }
+        };
+
+        let timestamp = now();
+        let grace = i64::from(grace_days.unwrap_or(DEFAULT_GRACE_DAYS)) * SECONDS_PER_DAY;
+        let previous_json = serde_json::to_string(&previous)
+            .map_err(|e| AppError::JsonSerialize { source: e })?;
+        db.add_credential_history(id, &previous_json, timestamp, timestamp + grace)?;
+
+        auth.insert(id.to_string(), rotated);
+        save_auth_config(&auth)?;
+
+        let mut metadata = Self::get_metadata(db)?
+            .remove(id)
+            .unwrap_or_else(|| CredentialMetadata {
+                created_at: None,
+                ..CredentialMetadata::new(id, timestamp)
+            });
+        let period = valid_days
+            .map(|days| i64::from(days) * SECONDS_PER_DAY)
+            .or_else(|| metadata.rotation_period());
+        metadata.last_rotated_at = Some(timestamp);
+        metadata.expires_at = period.map(|p| timestamp + p);
+        db.save_credential_metadata(&metadata)?;
+
+        log::info!("Rotated credential: {id}");
+        Ok(metadata)
+    }
+
+    pub fn get_previous(db: &Arc<Database>, id: &str) -> Result<Vec<PreviousCredential>, AppError> {
+        db.purge_credential_history(now())?;
+        db.get_credential_history(id)?
+            .into_iter()
+            .map(|entry| {
+                let mut credential: Credential = serde_json::from_str(&entry.credential_json)
+                    .map_err(|e| AppError::Database(format!("Corrupt credential history: {e}")))?;
+                mask_credential(&mut credential);
+                Ok(PreviousCredential {
+                    history_id: entry.history_id,
+                    credential_id: entry.credential_id,
+                    rotated_at: entry.rotated_at,
+                    recoverable_until: entry.recoverable_until,
+                    credential,
+                })
+            })
+            .collect()
+    }
+
+    /// Puts a previous credential back; the one it replaces becomes the
+    /// newest history entry so the restore itself can be undone.
+    pub fn restore_previous(db: &Arc<Database>, history_id: i64) -> Result<(), AppError> {
+        let timestamp = now();
+        db.purge_credential_history(timestamp)?;
+        let entry = db.get_credential_history_entry(history_id)?.ok_or_else(|| {
+            AppError::InvalidInput(format!("Previous credential {history_id} is no longer recoverable"))
+        })?;
+        let restored: Credential = serde_json::from_str(&entry.credential_json)
+            .map_err(|e| AppError::Database(format!("Corrupt credential history: {e}")))?;
+
+        let mut auth = get_auth_config()?;
+        if let Some(current) = auth.get(&entry.credential_id) {
+            let current_json = serde_json::to_string(current)
+                .map_err(|e| AppError::JsonSerialize { source: e })?;
+            db.add_credential_history(
+                &entry.credential_id,
+                &current_json,
+                timestamp,
+                entry.recoverable_until,
+            )?;
+        }
+
+        auth.insert(entry.credential_id.clone(), restored);
+        save_auth_config(&auth)?;
+        db.delete_credential_history(history_id)?;
+
+        log::info!("Restored previous credential: {}", entry.credential_id);
+        Ok(())
+    }
+
+    pub fn get_expiring(
+        db: &Arc<Database>,
+        within_days: u32,
+    ) -> Result<Vec<ExpiringCredential>, AppError> {
+        let timestamp = now();
+        let horizon = timestamp + i64::from(within_days) * SECONDS_PER_DAY;
+        let auth = get_auth_config()?;
+
+        let mut expiring: Vec<ExpiringCredential> = db
+            .get_credential_metadata()?
+            .into_iter()
+            .filter(|m| auth.contains_key(&m.id))
+            .filter_map(|m| {
+                let expires_at = m.expires_at.filter(|at| *at <= horizon)?;
+                Some(ExpiringCredential {
+                    id: m.id,
+                    expires_at,
+                    days_left: (expires_at - timestamp).div_euclid(SECONDS_PER_DAY),
+                    expired: expires_at <= timestamp,
+                    owner_note: m.owner_note,
+                })
+            })
+            .collect();
+        expiring.sort_by_key(|c| c.expires_at);
+        Ok(expiring)
+    }
+
+    pub fn check_on_startup(db: &Arc<Database>) -> Result<Vec<ExpiringCredential>, AppError> {
+        let purged = db.purge_credential_history(now())?;
+        if purged > 0 {
+            log::info!("Purged {purged} expired previous credential(s)");
+        }
+        let expiring = Self::get_expiring(db, EXPIRY_WARNING_DAYS)?;
+        for credential in &expiring {
+            if credential.expired {
+                log::warn!("Credential '{}' has expired", credential.id);
+            } else {
+                log::warn!(
+                    "Credential '{}' expires in {} day(s)",
+                    credential.id,
+                    credential.days_left
+                );
+            }
+        }
+        Ok(expiring)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::config::tests::config_files;
+    use serde_json::Map;
+
+    fn api_key(key: &str) -> Credential {
+        Credential::Api {
+            key: key.to_string(),
+            extra: Map::new(),
+        }
+    }
+
+    fn current_key(id: &str) -> String {
+        match &get_auth_config().unwrap()[id] {
+            Credential::Api { key, .. } => key.clone(),
+            other => panic!("unexpected credential {other:?}"),
+        }
+    }
+
+    /// A database and an auth.json holding `keys`.
+    fn setup(keys: &[(&str, &str)]) -> Arc<Database> {
+        let auth = keys.iter().map(|(id, key)| (id.to_string(), api_key(key))).collect();
+        save_auth_config(&auth).unwrap();
+        let db = Arc::new(Database::open_in_memory().unwrap());
+        for (id, _) in keys {
+            CredentialService::record_set(&db, id).unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn rotation_keeps_the_old_key_and_the_rotation_period() {
+        let _files = config_files();
+        let db = setup(&[("openai", "sk-old-1234567890")]);
+
+        let first = CredentialService::rotate(&db, "openai", "sk-new-1234567890".into(), Some(3), Some(30)).unwrap();
+        assert_eq!(current_key("openai"), "sk-new-1234567890");
+        let period = first.expires_at.unwrap() - first.last_rotated_at.unwrap();
+        assert_eq!(period, 30 * SECONDS_PER_DAY);
+
+        let previous = CredentialService::get_previous(&db, "openai").unwrap();
+        assert_eq!(previous.len(), 1);
+        assert_eq!(previous[0].recoverable_until - previous[0].rotated_at, 3 * SECONDS_PER_DAY);
+        // Previous keys are only shown masked.
+        assert!(matches!(&previous[0].credential, Credential::Api { key, .. } if key == "****7890"));
+        let stored = db.get_credential_history("openai").unwrap();
+        assert!(stored[0].credential_json.contains("sk-old-1234567890"));
+
+        // Without a validity, the next rotation keeps the 30-day cadence.
+        let second = CredentialService::rotate(&db, "openai", "sk-newer-1234567890".into(), None, None).unwrap();
+        assert_eq!(second.expires_at.unwrap() - second.last_rotated_at.unwrap(), period);
+        assert_eq!(CredentialService::get_previous(&db, "openai").unwrap().len(), 2);
+
+        assert!(CredentialService::rotate(&db, "openai", "sk-newer-1234567890".into(), None, None).is_err());
+        assert!(CredentialService::rotate(&db, "missing", "sk".into(), None, None).is_err());
+    }
+
+    #[test]
+    fn restores_a_previous_key_within_the_grace_period() {
+        let _files = config_files();
+        let db = setup(&[("openai", "sk-old")]);
+        CredentialService::rotate(&db, "openai", "sk-new".into(), Some(7), None).unwrap();
+        let history_id = CredentialService::get_previous(&db, "openai").unwrap()[0].history_id;
+
+        CredentialService::restore_previous(&db, history_id).unwrap();
+
+        assert_eq!(current_key("openai"), "sk-old");
+        // The replaced key can be restored in turn.
+        let previous = db.get_credential_history("openai").unwrap();
+        assert_eq!(previous.len(), 1);
+        assert!(previous[0].credential_json.contains("sk-new"));
+        assert!(CredentialService::restore_previous(&db, history_id).is_err());
+    }
+
+    #[test]
+    fn refuses_to_restore_after_the_grace_period() {
+        let _files = config_files();
+        let db = setup(&[("openai", "sk-current")]);
+        let old = serde_json::to_string(&api_key("sk-expired")).unwrap();
+        let rotated_at = now() - 10 * SECONDS_PER_DAY;
+        let history_id = db
+            .add_credential_history("openai", &old, rotated_at, rotated_at + 7 * SECONDS_PER_DAY)
+            .unwrap();
+
+        assert!(CredentialService::restore_previous(&db, history_id).is_err());
+        assert_eq!(current_key("openai"), "sk-current");
+        assert!(CredentialService::get_previous(&db, "openai").unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_credentials_expiring_within_the_warning_window() {
+        let _files = config_files();
+        let db = setup(&[("soon", "a"), ("edge", "b"), ("later", "c"), ("expired", "d")]);
+        let timestamp = now();
+        let expiring_at = |id: &str, expires_at: i64| {
+            db.save_credential_metadata(&CredentialMetadata {
+                expires_at: Some(expires_at),
+                ..CredentialMetadata::new(id, timestamp)
+            })
+            .unwrap();
+        };
+        let window = i64::from(EXPIRY_WARNING_DAYS) * SECONDS_PER_DAY;
+        expiring_at("soon", timestamp + 3 * SECONDS_PER_DAY + 60);
+        expiring_at("edge", timestamp + window - 60);
+        expiring_at("later", timestamp + window + 3600);
+        expiring_at("expired", timestamp - 60);
+        // Metadata for a credential that is no longer in auth.json.
+        expiring_at("deleted", timestamp);
+
+        let expiring = CredentialService::check_on_startup(&db).unwrap();
+
+        let ids: Vec<&str> = expiring.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, ["expired", "soon", "edge"]);
+        assert!(expiring[0].expired);
+        assert_eq!(expiring[0].days_left, -1);
+        assert_eq!(expiring[1].days_left, 3);
+        assert!(!expiring[1].expired);
+        assert_eq!(expiring[2].days_left, i64::from(EXPIRY_WARNING_DAYS) - 1);
+    }
+}
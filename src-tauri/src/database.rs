@@ -1,7 +1,16 @@
+use crate::credential_service::{CredentialHistoryEntry, CredentialMetadata};
 use crate::error::AppError;
-use crate::prompt::Prompt;
+use crate::mcp_client::Traffic;
+use crate::mcp_supervisor::McpLogEntry;
+use crate::permissions;
+use crate::prompt::{
+    highlight_parts, Project, Prompt, PromptSearchHit, PromptSource, PromptVersion,
+    HIGHLIGHT_END, HIGHLIGHT_START,
+};
+use crate::prompt_sync::SyncedPrompt;
+use crate::secrets::SecretAccessEntry;
 use indexmap::IndexMap;
-use rusqlite::{Connection, params};
+use rusqlite::{named_params, params, Connection, OptionalExtension};
 use std::path::PathBuf;
 use std::sync::Mutex;
 
@@ -15,10 +24,10 @@ impl Database {
         
         if let Some(parent) = db_path.parent() {
             if !parent.exists() {
-                std::fs::create_dir_all(parent)
-                    .map_err(|e| AppError::io(parent, e))?;
+                permissions::create_private_dir(parent)?;
             }
         }
+        permissions::ensure_private_file(&db_path)?;
 
         let conn = Connection::open(&db_path)
             .map_err(|e| AppError::Database(format!("Failed to open database: {e}")))?;
@@ -28,7 +37,16 @@ impl Database {
         Ok(db)
     }
 
-    fn get_db_path() -> Result<PathBuf, AppError> {
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self, AppError> {
+        let conn = Connection::open_in_memory()
+            .map_err(|e| AppError::Database(format!("Failed to open database: {e}")))?;
+        let db = Self { conn: Mutex::new(conn) };
+        db.init_schema()?;
+        Ok(db)
+    }
+
+    pub fn get_db_path() -> Result<PathBuf, AppError> {
         let home = dirs::home_dir()
             .ok_or_else(|| AppError::Config("Cannot find home directory".into()))?;
         Ok(home.join(".open-switch").join("open-switch.db"))
@@ -46,34 +64,264 @@ impl Database {
                 description TEXT,
                 enabled INTEGER NOT NULL DEFAULT 0,
                 created_at INTEGER,
-                updated_at INTEGER
+                updated_at INTEGER,
+                position INTEGER,
+                tags TEXT NOT NULL DEFAULT '[]',
+                folder TEXT
+            )",
+            [],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        // Databases created when only one prompt could be enabled: that
+        // prompt becomes the first fragment.
+        if Self::add_column(&conn, "prompts", "position", "INTEGER")? {
+            conn.execute("UPDATE prompts SET position = 0 WHERE enabled = 1", [])
+                .map_err(|e| AppError::Database(e.to_string()))?;
+        }
+        Self::add_column(&conn, "prompts", "tags", "TEXT NOT NULL DEFAULT '[]'")?;
+        Self::add_column(&conn, "prompts", "folder", "TEXT")?;
+
+        Self::init_prompt_search(&conn)?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS secret_access_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                target TEXT NOT NULL,
+                accessed_at INTEGER NOT NULL
+            )",
+            [],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS credential_metadata (
+                id TEXT PRIMARY KEY,
+                created_at INTEGER,
+                last_rotated_at INTEGER,
+                expires_at INTEGER,
+                owner_note TEXT
+            )",
+            [],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS credential_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                credential_id TEXT NOT NULL,
+                credential TEXT NOT NULL,
+                rotated_at INTEGER NOT NULL,
+                recoverable_until INTEGER NOT NULL
+            )",
+            [],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS mcp_logs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                server TEXT NOT NULL,
+                stream TEXT NOT NULL,
+                message TEXT NOT NULL,
+                timestamp INTEGER NOT NULL
+            )",
+            [],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_mcp_logs_server ON mcp_logs (server, id)",
+            [],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS mcp_server_tags (
+                server TEXT NOT NULL,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (server, tag)
+            )",
+            [],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS prompt_versions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                prompt_id TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                content TEXT NOT NULL,
+                description TEXT,
+                source TEXT NOT NULL,
+                author TEXT,
+                created_at INTEGER NOT NULL,
+                UNIQUE (prompt_id, version)
+            )",
+            [],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS prompt_variables (
+                name TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            )",
+            [],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        // Only a cache of what was written, so an older layout is dropped
+        // rather than migrated.
+        let output_has_target = conn
+            .prepare("SELECT 1 FROM pragma_table_info('prompt_output') WHERE name = 'target'")
+            .and_then(|mut stmt| stmt.exists([]))
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        if !output_has_target {
+            conn.execute("DROP TABLE IF EXISTS prompt_output", [])
+                .map_err(|e| AppError::Database(e.to_string()))?;
+        }
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS prompt_output (
+                target TEXT NOT NULL,
+                prompt_id TEXT NOT NULL,
+                content TEXT NOT NULL,
+                PRIMARY KEY (target, prompt_id)
+            )",
+            [],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS projects (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                path TEXT NOT NULL UNIQUE,
+                created_at INTEGER NOT NULL
+            )",
+            [],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS project_prompts (
+                project_id TEXT NOT NULL,
+                prompt_id TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                PRIMARY KEY (project_id, prompt_id)
+            )",
+            [],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            )",
+            [],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS prompt_sync_state (
+                prompt_id TEXT PRIMARY KEY,
+                file TEXT NOT NULL,
+                hash TEXT NOT NULL
             )",
             [],
         ).map_err(|e| AppError::Database(e.to_string()))?;
 
+        // Prompts saved before history existed get their current content
+        // as version 1, so the first edit can be undone.
+        conn.execute(
+            "INSERT INTO prompt_versions (prompt_id, version, name, content, description, source, created_at)
+             SELECT id, 1, name, content, description, ?1,
+                    COALESCE(updated_at, created_at, CAST(strftime('%s', 'now') AS INTEGER))
+             FROM prompts
+             WHERE id NOT IN (SELECT prompt_id FROM prompt_versions)",
+            params![PromptSource::Initial.as_str()],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Adds a column to databases created before it existed. Returns
+    /// whether it was added.
+    fn add_column(
+        conn: &Connection,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> Result<bool, AppError> {
+        let exists = conn
+            .prepare(&format!("SELECT 1 FROM pragma_table_info('{table}') WHERE name = ?1"))
+            .and_then(|mut stmt| stmt.exists(params![column]))
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        if exists {
+            return Ok(false);
+        }
+
+        conn.execute(&format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"), [])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(true)
+    }
+
+    /// Full-text index over prompt names, descriptions and content, kept in
+    /// sync by triggers. It is keyed by prompt id rather than rowid, which a
+    /// VACUUM may renumber.
+    fn init_prompt_search(conn: &Connection) -> Result<(), AppError> {
+        let exists = conn
+            .prepare("SELECT 1 FROM sqlite_master WHERE name = 'prompts_fts'")
+            .and_then(|mut stmt| stmt.exists([]))
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute_batch(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS prompts_fts USING fts5(
+                id UNINDEXED, name, description, content,
+                tokenize = 'unicode61 remove_diacritics 2'
+            );
+            CREATE TRIGGER IF NOT EXISTS prompts_fts_insert AFTER INSERT ON prompts BEGIN
+                INSERT INTO prompts_fts (id, name, description, content)
+                VALUES (new.id, new.name, new.description, new.content);
+            END;
+            CREATE TRIGGER IF NOT EXISTS prompts_fts_delete AFTER DELETE ON prompts BEGIN
+                DELETE FROM prompts_fts WHERE id = old.id;
+            END;
+            CREATE TRIGGER IF NOT EXISTS prompts_fts_update
+            AFTER UPDATE OF id, name, description, content ON prompts BEGIN
+                DELETE FROM prompts_fts WHERE id = old.id;
+                INSERT INTO prompts_fts (id, name, description, content)
+                VALUES (new.id, new.name, new.description, new.content);
+            END;"
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        if !exists {
+            conn.execute(
+                "INSERT INTO prompts_fts (id, name, description, content)
+                 SELECT id, name, description, content FROM prompts",
+                [],
+            ).map_err(|e| AppError::Database(e.to_string()))?;
+        }
         Ok(())
     }
 
+    fn map_prompt(row: &rusqlite::Row<'_>) -> rusqlite::Result<Prompt> {
+        let tags: String = row.get(8)?;
+        Ok(Prompt {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            content: row.get(2)?,
+            description: row.get(3)?,
+            enabled: row.get::<_, i32>(4)? != 0,
+            created_at: row.get(5)?,
+            updated_at: row.get(6)?,
+            position: row.get(7)?,
+            tags: serde_json::from_str(&tags).unwrap_or_default(),
+            folder: row.get(9)?,
+        })
+    }
+
     pub fn get_prompts(&self) -> Result<IndexMap<String, Prompt>, AppError> {
         let conn = self.conn.lock()
             .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
 
         let mut stmt = conn.prepare(
-            "SELECT id, name, content, description, enabled, created_at, updated_at 
+            "SELECT id, name, content, description, enabled, created_at, updated_at, position, tags, folder
              FROM prompts ORDER BY created_at DESC"
         ).map_err(|e| AppError::Database(e.to_string()))?;
 
-        let rows = stmt.query_map([], |row| {
-            Ok(Prompt {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                content: row.get(2)?,
-                description: row.get(3)?,
-                enabled: row.get::<_, i32>(4)? != 0,
-                created_at: row.get(5)?,
-                updated_at: row.get(6)?,
-            })
-        }).map_err(|e| AppError::Database(e.to_string()))?;
+        let rows = stmt.query_map([], Self::map_prompt)
+            .map_err(|e| AppError::Database(e.to_string()))?;
 
         let mut map = IndexMap::new();
         for row in rows {
@@ -84,13 +332,32 @@ impl Database {
         Ok(map)
     }
 
-    pub fn save_prompt(&self, prompt: &Prompt) -> Result<(), AppError> {
-        let conn = self.conn.lock()
+    /// Saves the prompt and, if its name, content or description changed,
+    /// records a new revision in the same transaction.
+    pub fn save_prompt(
+        &self,
+        prompt: &Prompt,
+        source: PromptSource,
+        author: Option<&str>,
+    ) -> Result<(), AppError> {
+        let mut conn = self.conn.lock()
             .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+        let tx = conn.transaction().map_err(|e| AppError::Database(e.to_string()))?;
 
-        conn.execute(
-            "INSERT OR REPLACE INTO prompts (id, name, content, description, enabled, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        tx.execute(
+            "INSERT INTO prompts
+                (id, name, content, description, enabled, created_at, updated_at, position, tags, folder)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+             ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                content = excluded.content,
+                description = excluded.description,
+                enabled = excluded.enabled,
+                created_at = excluded.created_at,
+                updated_at = excluded.updated_at,
+                position = COALESCE(excluded.position, prompts.position),
+                tags = excluded.tags,
+                folder = excluded.folder",
             params![
                 prompt.id,
                 prompt.name,
@@ -99,38 +366,810 @@ impl Database {
                 if prompt.enabled { 1 } else { 0 },
                 prompt.created_at,
                 prompt.updated_at,
+                prompt.position,
+                serde_json::to_string(&prompt.tags)
+                    .map_err(|e| AppError::Database(e.to_string()))?,
+                prompt.folder,
             ],
         ).map_err(|e| AppError::Database(e.to_string()))?;
 
+        let latest: Option<(i64, String, String, Option<String>)> = tx.query_row(
+            "SELECT version, name, content, description FROM prompt_versions
+             WHERE prompt_id = ?1 ORDER BY version DESC LIMIT 1",
+            params![prompt.id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        ).optional().map_err(|e| AppError::Database(e.to_string()))?;
+
+        let unchanged = latest.as_ref().is_some_and(|(_, name, content, description)| {
+            *name == prompt.name && *content == prompt.content && *description == prompt.description
+        });
+        if !unchanged {
+            let version = latest.map_or(1, |(version, ..)| version + 1);
+            let created_at = prompt.updated_at.unwrap_or_else(|| chrono::Utc::now().timestamp());
+            tx.execute(
+                "INSERT INTO prompt_versions
+                    (prompt_id, version, name, content, description, source, author, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    prompt.id,
+                    version,
+                    prompt.name,
+                    prompt.content,
+                    prompt.description,
+                    source.as_str(),
+                    author,
+                    created_at,
+                ],
+            ).map_err(|e| AppError::Database(e.to_string()))?;
+        }
+
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
         Ok(())
     }
 
+    /// Keeps the version history, so a deleted prompt can be restored.
     pub fn delete_prompt(&self, id: &str) -> Result<(), AppError> {
         let conn = self.conn.lock()
             .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
 
         conn.execute("DELETE FROM prompts WHERE id = ?1", params![id])
             .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    fn map_prompt_version(row: &rusqlite::Row<'_>) -> rusqlite::Result<PromptVersion> {
+        let source: String = row.get(6)?;
+        Ok(PromptVersion {
+            id: row.get(0)?,
+            prompt_id: row.get(1)?,
+            version: row.get(2)?,
+            name: row.get(3)?,
+            content: row.get(4)?,
+            description: row.get(5)?,
+            source: PromptSource::parse(&source).unwrap_or(PromptSource::Ui),
+            author: row.get(7)?,
+            created_at: row.get(8)?,
+        })
+    }
+
+    /// Newest revision first.
+    pub fn get_prompt_versions(&self, prompt_id: &str) -> Result<Vec<PromptVersion>, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, prompt_id, version, name, content, description, source, author, created_at
+             FROM prompt_versions WHERE prompt_id = ?1 ORDER BY version DESC"
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt.query_map(params![prompt_id], Self::map_prompt_version)
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub fn get_prompt_version(&self, id: i64) -> Result<Option<PromptVersion>, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        conn.query_row(
+            "SELECT id, prompt_id, version, name, content, description, source, author, created_at
+             FROM prompt_versions WHERE id = ?1",
+            params![id],
+            Self::map_prompt_version,
+        ).optional().map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// Prompts matching an FTS5 query (best first), or all prompts, newest
+    /// first, when there is none. Each given tag must be present; a folder
+    /// also matches its subfolders.
+    pub fn search_prompts(
+        &self,
+        query: Option<&str>,
+        tags: &[String],
+        folder: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<PromptSearchHit>, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let filters = "NOT EXISTS (
+                SELECT 1 FROM json_each(:tags) wanted
+                WHERE wanted.value NOT IN (SELECT value FROM json_each(p.tags))
+            )
+            AND (:folder IS NULL OR p.folder = :folder
+                OR substr(p.folder, 1, length(:folder) + 1) = :folder || '/')";
+        let columns = "p.id, p.name, p.content, p.description, p.enabled, p.created_at,
+            p.updated_at, p.position, p.tags, p.folder";
+        let tags = serde_json::to_string(tags).map_err(|e| AppError::Database(e.to_string()))?;
+        let start = HIGHLIGHT_START.to_string();
+        let end = HIGHLIGHT_END.to_string();
+
+        let map_hit = |row: &rusqlite::Row<'_>| -> rusqlite::Result<PromptSearchHit> {
+            let prompt = Self::map_prompt(row)?;
+            let name: Option<String> = row.get(10)?;
+            let snippet: Option<String> = row.get(11)?;
+            Ok(PromptSearchHit {
+                name: highlight_parts(name.as_deref().unwrap_or(&prompt.name)),
+                snippet: snippet.as_deref().map(highlight_parts).unwrap_or_default(),
+                score: row.get(12)?,
+                prompt,
+            })
+        };
+
+        let hits = match query {
+            Some(query) => {
+                // bm25 ranks lower as better; names weigh most, then descriptions.
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT {columns},
+                        highlight(prompts_fts, 1, :start, :end),
+                        snippet(prompts_fts, -1, :start, :end, '…', 24),
+                        bm25(prompts_fts, 0.0, 10.0, 5.0, 1.0) AS score
+                     FROM prompts_fts JOIN prompts p ON p.id = prompts_fts.id
+                     WHERE prompts_fts MATCH :query AND {filters}
+                     ORDER BY score LIMIT :limit"
+                )).map_err(|e| AppError::Database(e.to_string()))?;
+                let rows = stmt.query_map(
+                    named_params! {
+                        ":query": query,
+                        ":tags": tags,
+                        ":folder": folder,
+                        ":limit": limit,
+                        ":start": start,
+                        ":end": end,
+                    },
+                    map_hit,
+                ).map_err(|e| AppError::Database(e.to_string()))?;
+                rows.collect::<Result<Vec<_>, _>>()
+            }
+            None => {
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT {columns}, NULL, NULL, NULL
+                     FROM prompts p
+                     WHERE {filters}
+                     ORDER BY COALESCE(p.updated_at, p.created_at) DESC LIMIT :limit"
+                )).map_err(|e| AppError::Database(e.to_string()))?;
+                let rows = stmt.query_map(
+                    named_params! { ":tags": tags, ":folder": folder, ":limit": limit },
+                    map_hit,
+                ).map_err(|e| AppError::Database(e.to_string()))?;
+                rows.collect::<Result<Vec<_>, _>>()
+            }
+        };
+        hits.map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// Enabled prompts in composition order.
+    pub fn get_enabled_prompts(&self) -> Result<Vec<Prompt>, AppError> {
+        let mut enabled: Vec<Prompt> = self
+            .get_prompts()?
+            .into_values()
+            .filter(|p| p.enabled)
+            .collect();
+        enabled.sort_by_key(|p| (p.position.is_none(), p.position, p.created_at));
+        Ok(enabled)
+    }
+
+    /// Replaces the globally enabled prompts; `ids` is the composition order.
+    pub fn set_enabled_prompts(&self, ids: &[String]) -> Result<(), AppError> {
+        let mut conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+        let tx = conn.transaction().map_err(|e| AppError::Database(e.to_string()))?;
+
+        tx.execute("UPDATE prompts SET enabled = 0, position = NULL", [])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        for (position, id) in ids.iter().enumerate() {
+            tx.execute(
+                "UPDATE prompts SET enabled = 1, position = ?1 WHERE id = ?2",
+                params![position as i64, id],
+            ).map_err(|e| AppError::Database(e.to_string()))?;
+        }
+
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    pub fn get_prompt_variables(&self) -> Result<IndexMap<String, String>, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let mut stmt = conn.prepare("SELECT name, value FROM prompt_variables ORDER BY name")
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        rows.collect::<Result<IndexMap<_, _>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub fn set_prompt_variable(&self, name: &str, value: &str) -> Result<(), AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        conn.execute(
+            "INSERT OR REPLACE INTO prompt_variables (name, value) VALUES (?1, ?2)",
+            params![name, value],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub fn delete_prompt_variable(&self, name: &str) -> Result<bool, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        conn.execute("DELETE FROM prompt_variables WHERE name = ?1", params![name])
+            .map(|deleted| deleted > 0)
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// The rendered text of each prompt as last written to a target's
+    /// AGENTS.md. The global file is the empty target.
+    pub fn get_prompt_output(&self, target: &str) -> Result<IndexMap<String, String>, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let mut stmt = conn.prepare("SELECT prompt_id, content FROM prompt_output WHERE target = ?1")
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt.query_map(params![target], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        rows.collect::<Result<IndexMap<_, _>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub fn set_prompt_output(
+        &self,
+        target: &str,
+        output: &IndexMap<String, String>,
+    ) -> Result<(), AppError> {
+        let mut conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+        let tx = conn.transaction().map_err(|e| AppError::Database(e.to_string()))?;
+
+        tx.execute("DELETE FROM prompt_output WHERE target = ?1", params![target])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        for (prompt_id, content) in output {
+            tx.execute(
+                "INSERT INTO prompt_output (target, prompt_id, content) VALUES (?1, ?2, ?3)",
+                params![target, prompt_id, content],
+            ).map_err(|e| AppError::Database(e.to_string()))?;
+        }
 
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
         Ok(())
     }
 
-    pub fn disable_all_prompts(&self) -> Result<(), AppError> {
+    pub fn get_projects(&self) -> Result<Vec<Project>, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let mut stmt = conn.prepare("SELECT id, name, path, created_at FROM projects ORDER BY name")
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok(Project {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                path: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        }).map_err(|e| AppError::Database(e.to_string()))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub fn add_project(&self, project: &Project) -> Result<(), AppError> {
         let conn = self.conn.lock()
             .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
 
-        conn.execute("UPDATE prompts SET enabled = 0", [])
+        conn.execute(
+            "INSERT INTO projects (id, name, path, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![project.id, project.name, project.path, project.created_at],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub fn delete_project(&self, id: &str) -> Result<(), AppError> {
+        let mut conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+        let tx = conn.transaction().map_err(|e| AppError::Database(e.to_string()))?;
+
+        tx.execute("DELETE FROM projects WHERE id = ?1", params![id])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        tx.execute("DELETE FROM project_prompts WHERE project_id = ?1", params![id])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        tx.execute("DELETE FROM prompt_output WHERE target = ?1", params![id])
             .map_err(|e| AppError::Database(e.to_string()))?;
 
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
         Ok(())
     }
 
-    pub fn enable_prompt(&self, id: &str) -> Result<(), AppError> {
+    /// Prompt ids enabled in a project, in composition order.
+    pub fn get_project_prompt_ids(&self, project_id: &str) -> Result<Vec<String>, AppError> {
         let conn = self.conn.lock()
             .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
 
-        conn.execute("UPDATE prompts SET enabled = 1 WHERE id = ?1", params![id])
+        let mut stmt = conn.prepare(
+            "SELECT prompt_id FROM project_prompts WHERE project_id = ?1 ORDER BY position"
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt.query_map(params![project_id], |row| row.get(0))
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub fn set_project_prompts(&self, project_id: &str, ids: &[String]) -> Result<(), AppError> {
+        let mut conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+        let tx = conn.transaction().map_err(|e| AppError::Database(e.to_string()))?;
+
+        tx.execute("DELETE FROM project_prompts WHERE project_id = ?1", params![project_id])
             .map_err(|e| AppError::Database(e.to_string()))?;
+        for (position, id) in ids.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO project_prompts (project_id, prompt_id, position) VALUES (?1, ?2, ?3)",
+                params![project_id, id, position as i64],
+            ).map_err(|e| AppError::Database(e.to_string()))?;
+        }
+
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Projects that have `prompt_id` enabled.
+    pub fn get_prompt_project_ids(&self, prompt_id: &str) -> Result<Vec<String>, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT project_id FROM project_prompts WHERE prompt_id = ?1 ORDER BY project_id"
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt.query_map(params![prompt_id], |row| row.get(0))
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        conn.query_row("SELECT value FROM settings WHERE key = ?1", params![key], |row| row.get(0))
+            .optional()
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<(), AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        conn.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub fn delete_setting(&self, key: &str) -> Result<(), AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        conn.execute("DELETE FROM settings WHERE key = ?1", params![key])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Each prompt as of the last git sync, by id.
+    pub fn get_prompt_sync_state(&self) -> Result<IndexMap<String, SyncedPrompt>, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let mut stmt = conn.prepare("SELECT prompt_id, file, hash FROM prompt_sync_state ORDER BY file")
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get(0)?, SyncedPrompt { file: row.get(1)?, hash: row.get(2)? }))
+        }).map_err(|e| AppError::Database(e.to_string()))?;
+
+        rows.collect::<Result<IndexMap<_, _>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub fn set_prompt_sync_state(&self, state: &IndexMap<String, SyncedPrompt>) -> Result<(), AppError> {
+        let mut conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+        let tx = conn.transaction().map_err(|e| AppError::Database(e.to_string()))?;
+
+        tx.execute("DELETE FROM prompt_sync_state", [])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        for (prompt_id, synced) in state {
+            tx.execute(
+                "INSERT INTO prompt_sync_state (prompt_id, file, hash) VALUES (?1, ?2, ?3)",
+                params![prompt_id, synced.file, synced.hash],
+            ).map_err(|e| AppError::Database(e.to_string()))?;
+        }
+
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    pub fn record_secret_access(&self, target: &str) -> Result<(), AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        conn.execute(
+            "INSERT INTO secret_access_log (target, accessed_at) VALUES (?1, ?2)",
+            params![target, chrono::Utc::now().timestamp()],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub fn get_secret_access_log(&self, limit: u32) -> Result<Vec<SecretAccessEntry>, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT target, accessed_at FROM secret_access_log ORDER BY id DESC LIMIT ?1"
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt.query_map(params![limit], |row| {
+            Ok(SecretAccessEntry {
+                target: row.get(0)?,
+                accessed_at: row.get(1)?,
+            })
+        }).map_err(|e| AppError::Database(e.to_string()))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub fn get_credential_metadata(&self) -> Result<Vec<CredentialMetadata>, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, created_at, last_rotated_at, expires_at, owner_note
+             FROM credential_metadata ORDER BY id"
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok(CredentialMetadata {
+                id: row.get(0)?,
+                created_at: row.get(1)?,
+                last_rotated_at: row.get(2)?,
+                expires_at: row.get(3)?,
+                owner_note: row.get(4)?,
+            })
+        }).map_err(|e| AppError::Database(e.to_string()))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub fn save_credential_metadata(&self, metadata: &CredentialMetadata) -> Result<(), AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        conn.execute(
+            "INSERT OR REPLACE INTO credential_metadata (id, created_at, last_rotated_at, expires_at, owner_note)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                metadata.id,
+                metadata.created_at,
+                metadata.last_rotated_at,
+                metadata.expires_at,
+                metadata.owner_note,
+            ],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Removes the metadata and previous keys of a deleted credential.
+    pub fn delete_credential_records(&self, id: &str) -> Result<(), AppError> {
+        let mut conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let tx = conn.transaction().map_err(|e| AppError::Database(e.to_string()))?;
+        tx.execute("DELETE FROM credential_metadata WHERE id = ?1", params![id])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        tx.execute("DELETE FROM credential_history WHERE credential_id = ?1", params![id])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub fn add_credential_history(
+        &self,
+        credential_id: &str,
+        credential_json: &str,
+        rotated_at: i64,
+        recoverable_until: i64,
+    ) -> Result<i64, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        conn.execute(
+            "INSERT INTO credential_history (credential_id, credential, rotated_at, recoverable_until)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![credential_id, credential_json, rotated_at, recoverable_until],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(conn.last_insert_rowid())
+    }
+
+    fn map_credential_history(row: &rusqlite::Row<'_>) -> rusqlite::Result<CredentialHistoryEntry> {
+        Ok(CredentialHistoryEntry {
+            history_id: row.get(0)?,
+            credential_id: row.get(1)?,
+            credential_json: row.get(2)?,
+            rotated_at: row.get(3)?,
+            recoverable_until: row.get(4)?,
+        })
+    }
+
+    pub fn get_credential_history(&self, credential_id: &str) -> Result<Vec<CredentialHistoryEntry>, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, credential_id, credential, rotated_at, recoverable_until
+             FROM credential_history WHERE credential_id = ?1 ORDER BY id DESC"
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt.query_map(params![credential_id], Self::map_credential_history)
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub fn get_credential_history_entry(&self, history_id: i64) -> Result<Option<CredentialHistoryEntry>, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        conn.query_row(
+            "SELECT id, credential_id, credential, rotated_at, recoverable_until
+             FROM credential_history WHERE id = ?1",
+            params![history_id],
+            Self::map_credential_history,
+        )
+        .optional()
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub fn delete_credential_history(&self, history_id: i64) -> Result<(), AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        conn.execute("DELETE FROM credential_history WHERE id = ?1", params![history_id])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub fn purge_credential_history(&self, now: i64) -> Result<usize, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        conn.execute(
+            "DELETE FROM credential_history WHERE recoverable_until < ?1",
+            params![now],
+        ).map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// Inserts log lines in one transaction and returns their ids, in order.
+    pub fn add_mcp_logs(&self, entries: &[McpLogEntry]) -> Result<Vec<i64>, AppError> {
+        let mut conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let tx = conn.transaction().map_err(|e| AppError::Database(e.to_string()))?;
+        let mut ids = Vec::with_capacity(entries.len());
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO mcp_logs (server, stream, message, timestamp) VALUES (?1, ?2, ?3, ?4)",
+            ).map_err(|e| AppError::Database(e.to_string()))?;
+            for entry in entries {
+                stmt.execute(params![entry.server, entry.stream.as_str(), entry.message, entry.timestamp])
+                    .map_err(|e| AppError::Database(e.to_string()))?;
+                ids.push(tx.last_insert_rowid());
+            }
+        }
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(ids)
+    }
+
+    /// Newest entries first; `before_id` pages further back.
+    pub fn get_mcp_logs(
+        &self,
+        server: &str,
+        limit: u32,
+        before_id: Option<i64>,
+    ) -> Result<Vec<McpLogEntry>, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, server, stream, message, timestamp FROM mcp_logs
+             WHERE server = ?1 AND id < ?2 ORDER BY id DESC LIMIT ?3"
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt.query_map(params![server, before_id.unwrap_or(i64::MAX), limit], |row| {
+            let stream: String = row.get(2)?;
+            Ok(McpLogEntry {
+                id: Some(row.get(0)?),
+                server: row.get(1)?,
+                stream: Traffic::parse(&stream).unwrap_or(Traffic::Lifecycle),
+                message: row.get(3)?,
+                timestamp: row.get(4)?,
+            })
+        }).map_err(|e| AppError::Database(e.to_string()))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub fn clear_mcp_logs(&self, server: Option<&str>) -> Result<usize, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        match server {
+            Some(server) => conn.execute("DELETE FROM mcp_logs WHERE server = ?1", params![server]),
+            None => conn.execute("DELETE FROM mcp_logs", []),
+        }
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// Drops entries older than `older_than` (ms) and keeps at most
+    /// `keep_per_server` of the newest entries for each server.
+    pub fn prune_mcp_logs(&self, older_than: i64, keep_per_server: u32) -> Result<usize, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let expired = conn.execute(
+            "DELETE FROM mcp_logs WHERE timestamp < ?1",
+            params![older_than],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        let overflow = conn.execute(
+            "DELETE FROM mcp_logs WHERE id IN (
+                SELECT id FROM (
+                    SELECT id, ROW_NUMBER() OVER (PARTITION BY server ORDER BY id DESC) AS rank
+                    FROM mcp_logs
+                ) WHERE rank > ?1
+            )",
+            params![keep_per_server],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(expired + overflow)
+    }
+
+    pub fn get_mcp_server_tags(&self) -> Result<Vec<(String, String)>, AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT server, tag FROM mcp_server_tags ORDER BY server, tag"
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub fn set_mcp_server_tags(&self, server: &str, tags: &[String]) -> Result<(), AppError> {
+        let mut conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let tx = conn.transaction().map_err(|e| AppError::Database(e.to_string()))?;
+        tx.execute("DELETE FROM mcp_server_tags WHERE server = ?1", params![server])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        for tag in tags {
+            tx.execute(
+                "INSERT OR IGNORE INTO mcp_server_tags (server, tag) VALUES (?1, ?2)",
+                params![server, tag],
+            ).map_err(|e| AppError::Database(e.to_string()))?;
+        }
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub fn delete_mcp_server_tags(&self, servers: &[String]) -> Result<(), AppError> {
+        let mut conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let tx = conn.transaction().map_err(|e| AppError::Database(e.to_string()))?;
+        for server in servers {
+            tx.execute("DELETE FROM mcp_server_tags WHERE server = ?1", params![server])
+                .map_err(|e| AppError::Database(e.to_string()))?;
+        }
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// Moves credential metadata and rotation history to a new provider id.
+    /// Moves credential metadata and history to a new id. Rows already under
+    /// `new_id` are left over from a deleted provider and are replaced, so
+    /// the move cannot clash with them. Returns false when `old_id` had no
+    /// records, in which case nothing changes.
+    pub fn rename_credential_records(&self, old_id: &str, new_id: &str) -> Result<bool, AppError> {
+        let mut conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let tx = conn.transaction().map_err(|e| AppError::Database(e.to_string()))?;
+        let records: i64 = tx.query_row(
+            "SELECT (SELECT COUNT(*) FROM credential_metadata WHERE id = ?1)
+                  + (SELECT COUNT(*) FROM credential_history WHERE credential_id = ?1)",
+            params![old_id],
+            |row| row.get(0),
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+        if records == 0 {
+            return Ok(false);
+        }
+
+        tx.execute("DELETE FROM credential_metadata WHERE id = ?1", params![new_id])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        tx.execute("DELETE FROM credential_history WHERE credential_id = ?1", params![new_id])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        tx.execute(
+            "UPDATE credential_metadata SET id = ?2 WHERE id = ?1",
+            params![old_id, new_id],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+        tx.execute(
+            "UPDATE credential_history SET credential_id = ?2 WHERE credential_id = ?1",
+            params![old_id, new_id],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(true)
+    }
+
+    /// Moves tags and logs to a new MCP server name, replacing any left
+    /// over from a deleted server of that name.
+    pub fn rename_mcp_server_records(&self, old_name: &str, new_name: &str) -> Result<(), AppError> {
+        let mut conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        let tx = conn.transaction().map_err(|e| AppError::Database(e.to_string()))?;
+        tx.execute("DELETE FROM mcp_server_tags WHERE server = ?1", params![new_name])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        tx.execute("DELETE FROM mcp_logs WHERE server = ?1", params![new_name])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        tx.execute(
+            "UPDATE mcp_server_tags SET server = ?2 WHERE server = ?1",
+            params![old_name, new_name],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+        tx.execute(
+            "UPDATE mcp_logs SET server = ?2 WHERE server = ?1",
+            params![old_name, new_name],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub fn copy_mcp_server_tags(&self, from: &str, to: &str) -> Result<(), AppError> {
+        let conn = self.conn.lock()
+            .map_err(|e| AppError::Database(format!("Lock error: {e}")))?;
+
+        conn.execute(
+            "INSERT OR IGNORE INTO mcp_server_tags (server, tag)
+             SELECT ?2, tag FROM mcp_server_tags WHERE server = ?1",
+            params![from, to],
+        ).map_err(|e| AppError::Database(e.to_string()))?;
 
         Ok(())
     }
@@ -0,0 +1,178 @@
+use serde::Serialize;
+
+/// Above this many LCS cells the changed middle is reported as a plain
+/// delete-then-insert rather than aligned line by line.
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffLine {
+    pub kind: DiffKind,
+    pub text: String,
+    /// 1-based line number in the old text; absent for inserted lines.
+    #[serde(rename = "oldLine", skip_serializing_if = "Option::is_none")]
+    pub old_line: Option<usize>,
+    /// 1-based line number in the new text; absent for deleted lines.
+    #[serde(rename = "newLine", skip_serializing_if = "Option::is_none")]
+    pub new_line: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TextDiff {
+    pub lines: Vec<DiffLine>,
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// Line-by-line diff based on the longest common subsequence.
+pub fn diff_lines(old: &str, new: &str) -> TextDiff {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut ops = Vec::with_capacity(old.len().max(new.len()));
+    ops.extend(std::iter::repeat_n(DiffKind::Equal, prefix));
+    ops.extend(align(old_mid, new_mid));
+    ops.extend(std::iter::repeat_n(DiffKind::Equal, suffix));
+
+    let (mut i, mut j) = (0, 0);
+    let mut diff = TextDiff {
+        lines: Vec::with_capacity(ops.len()),
+        added: 0,
+        removed: 0,
+    };
+    for kind in ops {
+        let line = match kind {
+            DiffKind::Equal => {
+                i += 1;
+                j += 1;
+                DiffLine {
+                    kind,
+                    text: old[i - 1].to_string(),
+                    old_line: Some(i),
+                    new_line: Some(j),
+                }
+            }
+            DiffKind::Delete => {
+                i += 1;
+                diff.removed += 1;
+                DiffLine {
+                    kind,
+                    text: old[i - 1].to_string(),
+                    old_line: Some(i),
+                    new_line: None,
+                }
+            }
+            DiffKind::Insert => {
+                j += 1;
+                diff.added += 1;
+                DiffLine {
+                    kind,
+                    text: new[j - 1].to_string(),
+                    old_line: None,
+                    new_line: Some(j),
+                }
+            }
+        };
+        diff.lines.push(line);
+    }
+    diff
+}
+
+fn align(old: &[&str], new: &[&str]) -> Vec<DiffKind> {
+    let (n, m) = (old.len(), new.len());
+    if n == 0 || m == 0 || n.saturating_mul(m) > MAX_LCS_CELLS {
+        let mut ops = vec![DiffKind::Delete; n];
+        ops.extend(std::iter::repeat_n(DiffKind::Insert, m));
+        return ops;
+    }
+
+    // lcs[i][j] is the LCS length of old[i..] and new[j..].
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i] == new[j] {
+            ops.push(DiffKind::Equal);
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            ops.push(DiffKind::Delete);
+            i += 1;
+        } else {
+            ops.push(DiffKind::Insert);
+            j += 1;
+        }
+    }
+    ops.extend(std::iter::repeat_n(DiffKind::Delete, n - i));
+    ops.extend(std::iter::repeat_n(DiffKind::Insert, m - j));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(diff: &TextDiff) -> Vec<String> {
+        diff.lines
+            .iter()
+            .map(|line| {
+                let sign = match line.kind {
+                    DiffKind::Equal => ' ',
+                    DiffKind::Insert => '+',
+                    DiffKind::Delete => '-',
+                };
+                format!("{sign}{}", line.text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn aligns_changed_lines_between_common_ones() {
+        let diff = diff_lines("a\nb\nc\nd\ne", "a\nc\nx\nd\ne\nf");
+        assert_eq!(render(&diff), [" a", "-b", " c", "+x", " d", " e", "+f"]);
+        assert_eq!((diff.added, diff.removed), (2, 1));
+    }
+
+    #[test]
+    fn numbers_lines_on_each_side() {
+        let diff = diff_lines("keep\nold", "new\nkeep");
+        let numbers: Vec<_> = diff.lines.iter().map(|l| (l.old_line, l.new_line)).collect();
+        assert_eq!(numbers, [(None, Some(1)), (Some(1), Some(2)), (Some(2), None)]);
+    }
+
+    #[test]
+    fn handles_empty_and_identical_texts() {
+        assert!(diff_lines("", "").lines.is_empty());
+        let added = diff_lines("", "one\ntwo");
+        assert_eq!((added.added, added.removed), (2, 0));
+        let same = diff_lines("x\ny", "x\ny");
+        assert!(same.lines.iter().all(|l| l.kind == DiffKind::Equal));
+    }
+}
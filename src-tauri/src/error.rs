@@ -34,6 +34,12 @@ pub enum AppError {
 
     #[error("Invalid input: {0}")]
     InvalidInput(String),
+
+    #[error("MCP error: {0}")]
+    Mcp(String),
+
+    #[error("MCP server returned HTTP {status}: {message}")]
+    McpHttp { status: u16, message: String },
 }
 
 impl AppError {
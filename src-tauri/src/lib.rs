@@ -1,51 +1,101 @@
+mod command_resolver;
 mod config;
+mod credential_service;
 mod database;
+mod diff;
 mod error;
+mod mcp_client;
+mod mcp_clients;
+mod mcp_remote;
+mod mcp_service;
+mod mcp_supervisor;
+mod mcp_templates;
+mod permissions;
+mod process_tree;
 mod prompt;
+mod prompt_fragments;
+mod prompt_library;
 mod prompt_service;
+mod prompt_sync;
+mod prompt_template;
+mod provider_service;
+mod secrets;
+mod substitution;
 
+use indexmap::IndexMap;
+use std::collections::HashMap;
 use std::sync::Arc;
-use tauri::{Manager, State};
+use tauri::webview::PageLoadEvent;
+use tauri::{Emitter, Manager, State};
 
+use command_resolver::ResolvedCommand;
+use credential_service::{
+    CredentialMetadata, CredentialService, ExpiringCredential, PreviousCredential,
+};
 use database::Database;
-use prompt::Prompt;
-use prompt_service::PromptService;
+use mcp_client::{McpCheckResult, McpInventory};
+use mcp_clients::{ImportPreview, ImportSummary, McpClient, McpExport, McpSource};
+use mcp_service::{BulkAction, BulkResult, McpSelection, McpService};
+use mcp_supervisor::{LogWriter, McpLogEntry, McpSupervisor, SupervisedServer};
+use mcp_templates::McpTemplate;
+use permissions::PermissionDiagnostic;
+use prompt::{AgentsFileState, AgentsMdMode, DriftResolution, Project, Prompt, PromptSearchHit, PromptVersion};
+use prompt_library::{LibraryExport, LibraryImport};
+use prompt_service::{ProjectStatus, PromptDrift, PromptService, PromptVersionDiff};
+use prompt_sync::{PromptSyncStatus, SyncReport, SyncResolution};
+use provider_service::{ProviderService, RenameResult};
+use secrets::{SecretAccessEntry, SecretTarget};
+use substitution::{ConversionResult, ReferenceReport, ReferenceRequest};
 
 pub use config::{
     get_opencode_config, save_opencode_config, OpenCodeConfig, OpenCodeModel, ProviderConfig,
-    get_auth_config, save_auth_config, AuthConfig, Credential, McpServer, McpServerType,
+    get_auth_config, save_auth_config, AuthConfig, Credential, CredentialStatus, McpServer,
+    McpServerType,
 };
 pub use error::AppError;
 
 pub struct AppState {
     pub db: Arc<Database>,
+    pub permission_diagnostics: Vec<PermissionDiagnostic>,
+    pub supervisor: Arc<McpSupervisor>,
+    pub log_writer: Arc<LogWriter>,
+    /// Credentials found expired or expiring when the app started.
+    pub startup_expiring: Vec<ExpiringCredential>,
 }
 
 #[tauri::command]
 fn get_config() -> Result<OpenCodeConfig, String> {
-    get_opencode_config().map_err(|e| e.to_string())
+    let mut config = get_opencode_config().map_err(|e| e.to_string())?;
+    secrets::mask_config(&mut config);
+    Ok(config)
 }
 
 #[tauri::command]
-fn save_config(config: OpenCodeConfig) -> Result<bool, String> {
+fn save_config(mut config: OpenCodeConfig) -> Result<bool, String> {
+    let existing = get_opencode_config().map_err(|e| e.to_string())?;
+    secrets::restore_masked_config(&mut config, &existing).map_err(|e| e.to_string())?;
     save_opencode_config(&config).map_err(|e| e.to_string())?;
     Ok(true)
 }
 
 #[tauri::command]
-fn add_provider(id: String, provider: ProviderConfig) -> Result<bool, String> {
+fn add_provider(id: String, mut provider: ProviderConfig) -> Result<bool, String> {
     let mut config = get_opencode_config().map_err(|e| e.to_string())?;
+    secrets::restore_masked_provider(&id, &mut provider, config.provider.get(&id))
+        .map_err(|e| e.to_string())?;
     config.provider.insert(id, provider);
     save_opencode_config(&config).map_err(|e| e.to_string())?;
     Ok(true)
 }
 
 #[tauri::command]
-fn update_provider(id: String, provider: ProviderConfig) -> Result<bool, String> {
+fn update_provider(id: String, mut provider: ProviderConfig) -> Result<bool, String> {
     let mut config = get_opencode_config().map_err(|e| e.to_string())?;
     if !config.provider.contains_key(&id) {
         return Err(format!("Provider '{}' not found", id));
     }
+    secrets::restore_masked_provider(&id, &mut provider, config.provider.get(&id))
+        .map_err(|e| e.to_string())?;
     config.provider.insert(id, provider);
     save_opencode_config(&config).map_err(|e| e.to_string())?;
     Ok(true)
@@ -54,13 +104,43 @@ fn update_provider(id: String, provider: ProviderConfig) -> Result<bool, String>
 #[tauri::command]
 fn delete_provider(id: String) -> Result<bool, String> {
     let mut config = get_opencode_config().map_err(|e| e.to_string())?;
-    if config.provider.remove(&id).is_none() {
+    if config.provider.shift_remove(&id).is_none() {
         return Err(format!("Provider '{}' not found", id));
     }
     save_opencode_config(&config).map_err(|e| e.to_string())?;
     Ok(true)
 }
 
+#[tauri::command]
+fn rename_provider(
+    state: State<AppState>,
+    old_id: String,
+    new_id: String,
+) -> Result<RenameResult, String> {
+    ProviderService::rename(&state.db, &old_id, &new_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn duplicate_provider(
+    state: State<AppState>,
+    source_id: String,
+    new_id: String,
+) -> Result<bool, String> {
+    ProviderService::duplicate(&state.db, &source_id, &new_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn reorder_providers(order: Vec<String>) -> Result<bool, String> {
+    ProviderService::reorder(&order).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[tauri::command]
+fn reorder_models(provider_id: String, order: Vec<String>) -> Result<bool, String> {
+    ProviderService::reorder_models(&provider_id, &order).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
 #[tauri::command]
 fn get_config_path() -> Result<String, String> {
     config::get_config_path()
@@ -70,30 +150,89 @@ fn get_config_path() -> Result<String, String> {
 
 #[tauri::command]
 fn get_credentials() -> Result<AuthConfig, String> {
-    get_auth_config().map_err(|e| e.to_string())
+    let mut auth = get_auth_config().map_err(|e| e.to_string())?;
+    secrets::mask_auth(&mut auth);
+    Ok(auth)
 }
 
 #[tauri::command]
-fn set_credential(id: String, api_key: String) -> Result<bool, String> {
+fn set_credential(state: State<'_, AppState>, id: String, api_key: String) -> Result<bool, String> {
     let mut auth = get_auth_config().map_err(|e| e.to_string())?;
-    auth.insert(id, Credential {
-        credential_type: "api".to_string(),
-        key: api_key,
-    });
+    auth.insert(id.clone(), Credential::api(api_key));
     save_auth_config(&auth).map_err(|e| e.to_string())?;
+    CredentialService::record_set(&state.db, &id).map_err(|e| e.to_string())?;
     Ok(true)
 }
 
 #[tauri::command]
-fn delete_credential(id: String) -> Result<bool, String> {
+fn delete_credential(state: State<'_, AppState>, id: String) -> Result<bool, String> {
     let mut auth = get_auth_config().map_err(|e| e.to_string())?;
-    if auth.remove(&id).is_none() {
+    if auth.shift_remove(&id).is_none() {
         return Err(format!("Credential '{}' not found", id));
     }
     save_auth_config(&auth).map_err(|e| e.to_string())?;
+    CredentialService::forget(&state.db, &id).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[tauri::command]
+fn get_credential_metadata(
+    state: State<'_, AppState>,
+) -> Result<HashMap<String, CredentialMetadata>, String> {
+    CredentialService::get_metadata(&state.db).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn update_credential_metadata(
+    state: State<'_, AppState>,
+    metadata: CredentialMetadata,
+) -> Result<bool, String> {
+    CredentialService::update_metadata(&state.db, metadata).map_err(|e| e.to_string())?;
     Ok(true)
 }
 
+#[tauri::command]
+fn rotate_credential(
+    state: State<'_, AppState>,
+    id: String,
+    api_key: String,
+    grace_days: Option<u32>,
+    valid_days: Option<u32>,
+) -> Result<CredentialMetadata, String> {
+    CredentialService::rotate(&state.db, &id, api_key, grace_days, valid_days)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_previous_credentials(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<Vec<PreviousCredential>, String> {
+    CredentialService::get_previous(&state.db, &id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn restore_previous_credential(state: State<'_, AppState>, history_id: i64) -> Result<bool, String> {
+    CredentialService::restore_previous(&state.db, history_id).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[tauri::command]
+fn get_expiring_credentials(
+    state: State<'_, AppState>,
+    within_days: Option<u32>,
+) -> Result<Vec<ExpiringCredential>, String> {
+    let within_days = within_days.unwrap_or(credential_service::EXPIRY_WARNING_DAYS);
+    CredentialService::get_expiring(&state.db, within_days).map_err(|e| e.to_string())
+}
+
+/// What the startup check found, for a UI that started listening after the
+/// `credential-expiring` events were sent.
+#[tauri::command]
+fn get_startup_expiring_credentials(state: State<'_, AppState>) -> Vec<ExpiringCredential> {
+    state.startup_expiring.clone()
+}
+
 #[tauri::command]
 fn has_credential(id: String) -> Result<bool, String> {
     let auth = get_auth_config().map_err(|e| e.to_string())?;
@@ -101,9 +240,59 @@ fn has_credential(id: String) -> Result<bool, String> {
 }
 
 #[tauri::command]
-fn get_mcp_servers() -> Result<std::collections::HashMap<String, McpServer>, String> {
+fn get_credential_statuses() -> Result<HashMap<String, CredentialStatus>, String> {
+    let auth = get_auth_config().map_err(|e| e.to_string())?;
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    Ok(auth
+        .iter()
+        .map(|(id, credential)| (id.clone(), credential.status(now_ms)))
+        .collect())
+}
+
+#[tauri::command]
+fn reveal_secret(state: State<'_, AppState>, target: SecretTarget) -> Result<String, String> {
+    secrets::reveal_secret(&state.db, &target).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_secret_access_log(
+    state: State<'_, AppState>,
+    limit: Option<u32>,
+) -> Result<Vec<SecretAccessEntry>, String> {
+    state
+        .db
+        .get_secret_access_log(limit.unwrap_or(100))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_secret_references() -> Result<Vec<ReferenceReport>, String> {
+    substitution::get_reference_reports().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn convert_secret_to_reference(
+    target: SecretTarget,
+    reference: ReferenceRequest,
+) -> Result<ConversionResult, String> {
+    substitution::convert_to_reference(&target, &reference).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_permission_diagnostics(
+    state: State<'_, AppState>,
+) -> Result<Vec<PermissionDiagnostic>, String> {
+    permissions::current_diagnostics(&state.permission_diagnostics).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_mcp_servers() -> Result<IndexMap<String, McpServer>, String> {
     let config = get_opencode_config().map_err(|e| e.to_string())?;
-    Ok(config.mcp.unwrap_or_default())
+    let mut servers = config.mcp.unwrap_or_default();
+    for server in servers.values_mut() {
+        secrets::mask_mcp_server(server);
+    }
+    Ok(servers)
 }
 
 #[tauri::command]
@@ -112,7 +301,9 @@ fn add_mcp_server(name: String, mut server: McpServer) -> Result<bool, String> {
         server.command = Some(McpServer::normalize_command_for_platform(cmd));
     }
     let mut config = get_opencode_config().map_err(|e| e.to_string())?;
-    let mcp = config.mcp.get_or_insert_with(std::collections::HashMap::new);
+    let mcp = config.mcp.get_or_insert_with(IndexMap::new);
+    secrets::restore_masked_mcp_server(&name, &mut server, mcp.get(&name))
+        .map_err(|e| e.to_string())?;
     mcp.insert(name, server);
     save_opencode_config(&config).map_err(|e| e.to_string())?;
     Ok(true)
@@ -124,20 +315,22 @@ fn update_mcp_server(name: String, mut server: McpServer) -> Result<bool, String
         server.command = Some(McpServer::normalize_command_for_platform(cmd));
     }
     let mut config = get_opencode_config().map_err(|e| e.to_string())?;
-    let mcp = config.mcp.get_or_insert_with(std::collections::HashMap::new);
+    let mcp = config.mcp.get_or_insert_with(IndexMap::new);
     if !mcp.contains_key(&name) {
         return Err(format!("MCP server '{}' not found", name));
     }
+    secrets::restore_masked_mcp_server(&name, &mut server, mcp.get(&name))
+        .map_err(|e| e.to_string())?;
     mcp.insert(name, server);
     save_opencode_config(&config).map_err(|e| e.to_string())?;
     Ok(true)
 }
 
 #[tauri::command]
-fn delete_mcp_server(name: String) -> Result<bool, String> {
+fn delete_mcp_server(state: State<AppState>, name: String) -> Result<bool, String> {
     let mut config = get_opencode_config().map_err(|e| e.to_string())?;
     if let Some(ref mut mcp) = config.mcp {
-        if mcp.remove(&name).is_none() {
+        if mcp.shift_remove(&name).is_none() {
             return Err(format!("MCP server '{}' not found", name));
         }
         if mcp.is_empty() {
@@ -147,6 +340,7 @@ fn delete_mcp_server(name: String) -> Result<bool, String> {
         return Err(format!("MCP server '{}' not found", name));
     }
     save_opencode_config(&config).map_err(|e| e.to_string())?;
+    McpService::forget(&state.db, &[name]).map_err(|e| e.to_string())?;
     Ok(true)
 }
 
@@ -166,6 +360,239 @@ fn toggle_mcp_server(name: String, enabled: bool) -> Result<bool, String> {
     Ok(true)
 }
 
+#[tauri::command]
+fn rename_mcp_server(
+    state: State<AppState>,
+    old_name: String,
+    new_name: String,
+) -> Result<Vec<String>, String> {
+    let running = state.supervisor.list().map_err(|e| e.to_string())?;
+    if running.iter().any(|s| s.name == old_name) {
+        return Err(format!("Stop '{}' before renaming it", old_name));
+    }
+    McpService::rename(&state.db, &old_name, &new_name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn duplicate_mcp_server(
+    state: State<AppState>,
+    source_name: String,
+    new_name: String,
+) -> Result<bool, String> {
+    McpService::duplicate(&state.db, &source_name, &new_name).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[tauri::command]
+fn reorder_mcp_servers(order: Vec<String>) -> Result<bool, String> {
+    McpService::reorder(&order).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[tauri::command]
+fn get_mcp_tags(state: State<AppState>) -> Result<HashMap<String, Vec<String>>, String> {
+    McpService::get_tags(&state.db).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_mcp_server_tags(
+    state: State<AppState>,
+    name: String,
+    tags: Vec<String>,
+) -> Result<Vec<String>, String> {
+    McpService::set_tags(&state.db, &name, tags).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn bulk_update_mcp_servers(
+    state: State<AppState>,
+    selection: McpSelection,
+    action: BulkAction,
+) -> Result<BulkResult, String> {
+    McpService::bulk_update(&state.db, &selection, action).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn check_mcp_server(
+    name: String,
+    server: Option<McpServer>,
+) -> Result<McpCheckResult, String> {
+    let config = get_opencode_config().map_err(|e| e.to_string())?;
+    let stored = config.mcp.as_ref().and_then(|m| m.get(&name));
+    let server = match server {
+        Some(mut server) => {
+            secrets::restore_masked_mcp_server(&name, &mut server, stored)
+                .map_err(|e| e.to_string())?;
+            server
+        }
+        None => stored
+            .cloned()
+            .ok_or_else(|| format!("MCP server '{}' not found", name))?,
+    };
+
+    tauri::async_runtime::spawn_blocking(move || mcp_client::check_server(&server))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn inspect_mcp_server(name: String) -> Result<McpInventory, String> {
+    let config = get_opencode_config().map_err(|e| e.to_string())?;
+    let server = config
+        .mcp
+        .as_ref()
+        .and_then(|m| m.get(&name))
+        .cloned()
+        .ok_or_else(|| format!("MCP server '{}' not found", name))?;
+
+    tauri::async_runtime::spawn_blocking(move || mcp_client::inspect_server(&server))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn discover_mcp_sources() -> Vec<McpSource> {
+    mcp_clients::discover_sources()
+}
+
+#[tauri::command]
+fn preview_mcp_import(path: String, client: Option<McpClient>) -> Result<ImportPreview, String> {
+    mcp_clients::preview_import(&path, client).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn import_mcp_servers(
+    path: String,
+    client: Option<McpClient>,
+    names: Vec<String>,
+    overwrite: bool,
+) -> Result<ImportSummary, String> {
+    mcp_clients::import_servers(&path, client, &names, overwrite).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn export_mcp_servers(
+    client: McpClient,
+    names: Option<Vec<String>>,
+    target_path: Option<String>,
+) -> Result<McpExport, String> {
+    mcp_clients::export_servers(client, names, target_path.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn resolve_mcp_command(
+    command: Vec<String>,
+    environment: Option<IndexMap<String, String>>,
+) -> ResolvedCommand {
+    command_resolver::resolve(&command, environment.as_ref())
+}
+
+#[tauri::command]
+fn get_mcp_templates() -> Vec<McpTemplate> {
+    mcp_templates::templates()
+}
+
+#[tauri::command]
+fn add_mcp_from_template(
+    template_id: String,
+    name: String,
+    params: HashMap<String, serde_json::Value>,
+) -> Result<McpServer, String> {
+    let template = mcp_templates::find_template(&template_id).map_err(|e| e.to_string())?;
+    let server = mcp_templates::build_server(&template, &params).map_err(|e| e.to_string())?;
+
+    let mut config = get_opencode_config().map_err(|e| e.to_string())?;
+    let mcp = config.mcp.get_or_insert_with(IndexMap::new);
+    if mcp.contains_key(&name) {
+        return Err(format!("MCP server '{}' already exists", name));
+    }
+    mcp.insert(name, server.clone());
+    save_opencode_config(&config).map_err(|e| e.to_string())?;
+
+    let mut masked = server;
+    secrets::mask_mcp_server(&mut masked);
+    Ok(masked)
+}
+
+#[tauri::command]
+async fn start_mcp_server(
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<SupervisedServer, String> {
+    let config = get_opencode_config().map_err(|e| e.to_string())?;
+    let server = config
+        .mcp
+        .as_ref()
+        .and_then(|m| m.get(&name))
+        .cloned()
+        .ok_or_else(|| format!("MCP server '{}' not found", name))?;
+    mcp_supervisor::prune_logs(&state.db).map_err(|e| e.to_string())?;
+
+    let supervisor = Arc::clone(&state.supervisor);
+    tauri::async_runtime::spawn_blocking(move || supervisor.start(&name, &server))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn stop_mcp_server(state: State<'_, AppState>, name: String) -> Result<bool, String> {
+    let supervisor = Arc::clone(&state.supervisor);
+    tauri::async_runtime::spawn_blocking(move || supervisor.stop(&name))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[tauri::command]
+async fn send_mcp_request(
+    state: State<'_, AppState>,
+    name: String,
+    method: String,
+    params: Option<serde_json::Value>,
+    timeout_ms: Option<u64>,
+) -> Result<serde_json::Value, String> {
+    let supervisor = Arc::clone(&state.supervisor);
+    let timeout = std::time::Duration::from_millis(
+        timeout_ms.unwrap_or(mcp_client::DEFAULT_TIMEOUT_MS),
+    );
+    let params = params.unwrap_or_else(|| serde_json::json!({}));
+    tauri::async_runtime::spawn_blocking(move || {
+        supervisor.request(&name, &method, params, timeout)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_supervised_mcp_servers(state: State<AppState>) -> Result<Vec<SupervisedServer>, String> {
+    state.supervisor.list().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_mcp_logs(
+    state: State<AppState>,
+    name: String,
+    limit: Option<u32>,
+    before_id: Option<i64>,
+) -> Result<Vec<McpLogEntry>, String> {
+    state
+        .db
+        .get_mcp_logs(&name, limit.unwrap_or(500), before_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn clear_mcp_logs(state: State<AppState>, name: Option<String>) -> Result<usize, String> {
+    state
+        .db
+        .clear_mcp_logs(name.as_deref())
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 fn get_instructions() -> Result<Vec<String>, String> {
     let config = get_opencode_config().map_err(|e| e.to_string())?;
@@ -224,8 +651,104 @@ fn delete_prompt(state: State<'_, AppState>, id: String) -> Result<bool, String>
 }
 
 #[tauri::command]
-fn enable_prompt(state: State<'_, AppState>, id: String) -> Result<bool, String> {
-    PromptService::enable_prompt(&state.db, &id).map_err(|e| e.to_string())?;
+fn enable_prompt(
+    state: State<'_, AppState>,
+    id: String,
+    project_id: Option<String>,
+) -> Result<bool, String> {
+    PromptService::enable_prompt(&state.db, &id, project_id.as_deref()).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[tauri::command]
+fn set_prompt_enabled(
+    state: State<'_, AppState>,
+    id: String,
+    enabled: bool,
+    project_id: Option<String>,
+) -> Result<bool, String> {
+    PromptService::set_prompt_enabled(&state.db, &id, enabled, project_id.as_deref())
+        .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[tauri::command]
+fn reorder_enabled_prompts(
+    state: State<'_, AppState>,
+    order: Vec<String>,
+    project_id: Option<String>,
+) -> Result<bool, String> {
+    PromptService::reorder_enabled(&state.db, &order, project_id.as_deref())
+        .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[tauri::command]
+fn get_projects(state: State<'_, AppState>) -> Result<Vec<ProjectStatus>, String> {
+    PromptService::get_projects(&state.db).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn add_project(
+    state: State<'_, AppState>,
+    path: String,
+    name: Option<String>,
+) -> Result<Project, String> {
+    PromptService::add_project(&state.db, &path, name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn remove_project(state: State<'_, AppState>, id: String) -> Result<bool, String> {
+    PromptService::remove_project(&state.db, &id).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[tauri::command]
+fn get_agents_md_state(
+    state: State<'_, AppState>,
+    project_id: Option<String>,
+) -> Result<AgentsFileState, String> {
+    PromptService::get_file_state(&state.db, project_id.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_prompt_drift(
+    state: State<'_, AppState>,
+    project_id: Option<String>,
+) -> Result<Option<PromptDrift>, String> {
+    PromptService::get_drift(&state.db, project_id.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn resolve_prompt_drift(
+    state: State<'_, AppState>,
+    resolution: DriftResolution,
+    project_id: Option<String>,
+) -> Result<bool, String> {
+    PromptService::resolve_drift(&state.db, project_id.as_deref(), resolution)
+        .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[tauri::command]
+fn get_prompt_auto_backfill(state: State<'_, AppState>) -> Result<bool, String> {
+    PromptService::auto_backfill(&state.db).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_prompt_auto_backfill(state: State<'_, AppState>, enabled: bool) -> Result<bool, String> {
+    PromptService::set_auto_backfill(&state.db, enabled).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[tauri::command]
+fn get_agents_md_mode(state: State<'_, AppState>) -> Result<AgentsMdMode, String> {
+    PromptService::agents_md_mode(&state.db).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_agents_md_mode(state: State<'_, AppState>, mode: AgentsMdMode) -> Result<bool, String> {
+    PromptService::set_agents_md_mode(&state.db, mode).map_err(|e| e.to_string())?;
     Ok(true)
 }
 
@@ -235,8 +758,131 @@ fn import_prompt_from_file(state: State<'_, AppState>) -> Result<String, String>
 }
 
 #[tauri::command]
-fn get_current_prompt_file_content() -> Result<Option<String>, String> {
-    PromptService::get_current_file_content().map_err(|e| e.to_string())
+fn search_prompts(
+    state: State<'_, AppState>,
+    query: String,
+    tags: Option<Vec<String>>,
+    folder: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<PromptSearchHit>, String> {
+    PromptService::search(&state.db, &query, tags.unwrap_or_default(), folder.as_deref(), limit)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn export_prompt_library(
+    state: State<'_, AppState>,
+    directory: String,
+    ids: Option<Vec<String>>,
+) -> Result<LibraryExport, String> {
+    PromptService::export_library(&state.db, &directory, ids).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn import_prompt_library(
+    state: State<'_, AppState>,
+    directory: String,
+    dry_run: Option<bool>,
+) -> Result<LibraryImport, String> {
+    PromptService::import_library(&state.db, &directory, dry_run.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_prompt_sync_status(state: State<'_, AppState>) -> Result<Option<PromptSyncStatus>, String> {
+    PromptService::get_sync_status(&state.db).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_prompt_sync(
+    state: State<'_, AppState>,
+    path: Option<String>,
+    remote: Option<String>,
+    directory: Option<String>,
+) -> Result<Option<PromptSyncStatus>, String> {
+    let db = Arc::clone(&state.db);
+    tauri::async_runtime::spawn_blocking(move || {
+        PromptService::set_sync(&db, path.as_deref(), remote.as_deref(), directory.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn sync_prompts(state: State<'_, AppState>) -> Result<SyncReport, String> {
+    let db = Arc::clone(&state.db);
+    tauri::async_runtime::spawn_blocking(move || PromptService::sync(&db))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn resolve_prompt_sync_conflict(
+    state: State<'_, AppState>,
+    prompt_id: String,
+    resolution: SyncResolution,
+) -> Result<bool, String> {
+    PromptService::resolve_sync_conflict(&state.db, &prompt_id, resolution)
+        .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[tauri::command]
+fn get_prompt_versions(
+    state: State<'_, AppState>,
+    prompt_id: String,
+) -> Result<Vec<PromptVersion>, String> {
+    PromptService::get_versions(&state.db, &prompt_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn diff_prompt_versions(
+    state: State<'_, AppState>,
+    from_id: i64,
+    to_id: i64,
+) -> Result<PromptVersionDiff, String> {
+    PromptService::diff_versions(&state.db, from_id, to_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn restore_prompt_version(state: State<'_, AppState>, version_id: i64) -> Result<Prompt, String> {
+    PromptService::restore_version(&state.db, version_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn preview_prompt(
+    state: State<'_, AppState>,
+    id: String,
+    content: Option<String>,
+) -> Result<String, String> {
+    PromptService::preview(&state.db, &id, content).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_prompt_variables(state: State<'_, AppState>) -> Result<IndexMap<String, String>, String> {
+    PromptService::get_variables(&state.db).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_prompt_variable(state: State<'_, AppState>, name: String, value: String) -> Result<bool, String> {
+    PromptService::set_variable(&state.db, &name, &value).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[tauri::command]
+fn delete_prompt_variable(state: State<'_, AppState>, name: String) -> Result<bool, String> {
+    PromptService::delete_variable(&state.db, &name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_current_prompt_file_content(
+    state: State<'_, AppState>,
+    project_id: Option<String>,
+) -> Result<Option<String>, String> {
+    PromptService::get_current_file_content(&state.db, project_id.as_deref())
+        .map_err(|e| e.to_string())
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -270,6 +916,11 @@ pub fn run() {
                 )?;
             }
 
+            let permission_diagnostics = permissions::repair_secret_files().unwrap_or_else(|e| {
+                log::warn!("Failed to check secret file permissions: {e}");
+                Vec::new()
+            });
+
             let db = Database::new().expect("Failed to initialize database");
             let db = Arc::new(db);
 
@@ -277,26 +928,102 @@ pub fn run() {
                 log::warn!("Failed to auto-import prompts: {e}");
             }
 
-            app.manage(AppState { db });
+            let startup_expiring = CredentialService::check_on_startup(&db).unwrap_or_else(|e| {
+                log::warn!("Failed to check credential expiry: {e}");
+                Vec::new()
+            });
+
+            if let Err(e) = mcp_supervisor::prune_logs(&db) {
+                log::warn!("Failed to prune MCP logs: {e}");
+            }
+            let log_writer = {
+                let handle = app.handle().clone();
+                Arc::new(LogWriter::spawn(Arc::clone(&db), move |stored| {
+                    let _ = handle.emit("mcp-log", stored);
+                }))
+            };
+            let supervisor = {
+                let log_writer = Arc::clone(&log_writer);
+                Arc::new(McpSupervisor::new(Arc::new(move |entry: &McpLogEntry| {
+                    log_writer.write(entry);
+                })))
+            };
+
+            app.manage(AppState {
+                db,
+                permission_diagnostics,
+                supervisor,
+                log_writer,
+                startup_expiring,
+            });
 
             Ok(())
         })
+        .on_page_load(|webview, payload| {
+            // Nothing listens before the page has loaded.
+            if webview.label() != "main" || payload.event() != PageLoadEvent::Finished {
+                return;
+            }
+            if let Some(state) = webview.try_state::<AppState>() {
+                for credential in &state.startup_expiring {
+                    let _ = webview.emit("credential-expiring", credential);
+                }
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             get_config,
             save_config,
             add_provider,
             update_provider,
             delete_provider,
+            rename_provider,
+            duplicate_provider,
+            reorder_providers,
+            reorder_models,
             get_config_path,
             get_credentials,
             set_credential,
             delete_credential,
             has_credential,
+            get_credential_statuses,
+            get_credential_metadata,
+            update_credential_metadata,
+            rotate_credential,
+            get_previous_credentials,
+            restore_previous_credential,
+            get_expiring_credentials,
+            get_startup_expiring_credentials,
+            reveal_secret,
+            get_secret_access_log,
+            get_permission_diagnostics,
+            get_secret_references,
+            convert_secret_to_reference,
             get_mcp_servers,
             add_mcp_server,
             update_mcp_server,
             delete_mcp_server,
             toggle_mcp_server,
+            rename_mcp_server,
+            duplicate_mcp_server,
+            reorder_mcp_servers,
+            get_mcp_tags,
+            set_mcp_server_tags,
+            bulk_update_mcp_servers,
+            check_mcp_server,
+            inspect_mcp_server,
+            discover_mcp_sources,
+            preview_mcp_import,
+            import_mcp_servers,
+            export_mcp_servers,
+            resolve_mcp_command,
+            get_mcp_templates,
+            add_mcp_from_template,
+            start_mcp_server,
+            stop_mcp_server,
+            send_mcp_request,
+            get_supervised_mcp_servers,
+            get_mcp_logs,
+            clear_mcp_logs,
             get_instructions,
             add_instruction,
             remove_instruction,
@@ -305,9 +1032,43 @@ pub fn run() {
             upsert_prompt,
             delete_prompt,
             enable_prompt,
+            set_prompt_enabled,
+            reorder_enabled_prompts,
+            get_projects,
+            add_project,
+            remove_project,
+            get_agents_md_state,
+            get_prompt_drift,
+            resolve_prompt_drift,
+            get_prompt_auto_backfill,
+            set_prompt_auto_backfill,
+            get_agents_md_mode,
+            set_agents_md_mode,
             import_prompt_from_file,
             get_current_prompt_file_content,
+            search_prompts,
+            export_prompt_library,
+            import_prompt_library,
+            get_prompt_sync_status,
+            set_prompt_sync,
+            sync_prompts,
+            resolve_prompt_sync_conflict,
+            get_prompt_versions,
+            diff_prompt_versions,
+            restore_prompt_version,
+            preview_prompt,
+            get_prompt_variables,
+            set_prompt_variable,
+            delete_prompt_variable,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                if let Some(state) = app.try_state::<AppState>() {
+                    state.supervisor.stop_all();
+                    state.log_writer.flush();
+                }
+            }
+        });
 }
@@ -0,0 +1,808 @@
+use crate::command_resolver;
+use crate::config::{McpServer, McpServerType};
+use crate::error::AppError;
+use crate::mcp_remote;
+use crate::process_tree::{self, ProcessTree};
+use crate::substitution::{config_dir, resolve_value};
+use serde::Serialize;
+use serde_json::{json, Value};
+use indexmap::IndexMap;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+pub const MCP_PROTOCOL_VERSION: &str = "2025-06-18";
+/// Matches OpenCode's default when `timeout` is not set on a server.
+pub const DEFAULT_TIMEOUT_MS: u64 = 5000;
+const SHUTDOWN_GRACE: Duration = Duration::from_millis(500);
+const MAX_STDERR_BYTES: usize = 64 * 1024;
+
+pub fn server_timeout(server: &McpServer) -> Duration {
+    Duration::from_millis(server.timeout.unwrap_or(DEFAULT_TIMEOUT_MS))
+}
+
+fn push_capped(buffer: &Mutex<String>, text: &str) {
+    if let Ok(mut buffer) = buffer.lock() {
+        if buffer.len() < MAX_STDERR_BYTES {
+            buffer.push_str(text);
+            buffer.push('\n');
+        }
+    }
+}
+
+// ============== Transports ==============
+
+pub trait McpTransport: Send {
+    fn kind(&self) -> &'static str;
+    fn request(&mut self, method: &str, params: Value, timeout: Duration) -> Result<Value, AppError>;
+    fn notify(&mut self, method: &str, params: Value) -> Result<(), AppError>;
+    /// Ends the session and returns whatever diagnostics it captured.
+    fn close(self: Box<Self>) -> String;
+}
+
+// ============== Stdio transport ==============
+
+/// What passed over a stdio session, for callers that want to watch it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Traffic {
+    Sent,
+    Received,
+    Stdout,
+    Stderr,
+    Exited,
+    /// Emitted by whoever manages the session rather than the session itself.
+    Lifecycle,
+}
+
+impl Traffic {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Traffic::Sent => "sent",
+            Traffic::Received => "received",
+            Traffic::Stdout => "stdout",
+            Traffic::Stderr => "stderr",
+            Traffic::Exited => "exited",
+            Traffic::Lifecycle => "lifecycle",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "sent" => Some(Traffic::Sent),
+            "received" => Some(Traffic::Received),
+            "stdout" => Some(Traffic::Stdout),
+            "stderr" => Some(Traffic::Stderr),
+            "exited" => Some(Traffic::Exited),
+            "lifecycle" => Some(Traffic::Lifecycle),
+            _ => None,
+        }
+    }
+}
+
+pub type TrafficObserver = Arc<dyn Fn(Traffic, &str) + Send + Sync>;
+
+pub struct StdioSession {
+    child: Child,
+    tree: ProcessTree,
+    stdin: Option<ChildStdin>,
+    messages: Receiver<Value>,
+    stderr: Arc<Mutex<String>>,
+    stderr_reader: Option<JoinHandle<()>>,
+    /// Disconnects when the stderr reader ends.
+    stderr_done: Receiver<()>,
+    observer: Option<TrafficObserver>,
+    next_id: i64,
+    closed: bool,
+}
+
+impl StdioSession {
+    pub fn spawn(
+        command: &[String],
+        environment: Option<&IndexMap<String, String>>,
+    ) -> Result<Self, AppError> {
+        Self::spawn_observed(command, environment, None)
+    }
+
+    pub fn spawn_observed(
+        command: &[String],
+        environment: Option<&IndexMap<String, String>>,
+        observer: Option<TrafficObserver>,
+    ) -> Result<Self, AppError> {
+        let (program, args) = command
+            .split_first()
+            .ok_or_else(|| AppError::InvalidInput("MCP server command is empty".to_string()))?;
+
+        let base_dir = config_dir()?;
+        let resolved = command_resolver::find_executable(program, environment);
+        let mut cmd = match &resolved {
+            Some((path, _)) => Command::new(path),
+            None => Command::new(program),
+        };
+        cmd.args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        for (key, value) in environment.into_iter().flatten() {
+            cmd.env(key, resolve_value(value, &base_dir).unwrap_or_else(|| value.clone()));
+        }
+
+        // Shims such as npx are scripts whose interpreter lives next to them,
+        // so a directory found outside PATH has to be added to the child's.
+        if let Some((path, source)) = &resolved {
+            if source != "path" && source != "explicit" {
+                if let Some(dir) = path.parent() {
+                    let inherited = environment
+                        .and_then(|env| env.get("PATH").cloned())
+                        .or_else(|| std::env::var("PATH").ok())
+                        .unwrap_or_default();
+                    let dirs = std::iter::once(dir.to_path_buf())
+                        .chain(std::env::split_paths(&inherited));
+                    if let Ok(joined) = std::env::join_paths(dirs) {
+                        cmd.env("PATH", joined);
+                    }
+                }
+            }
+        }
+
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+            cmd.creation_flags(CREATE_NO_WINDOW);
+        }
+        process_tree::isolate(&mut cmd);
+
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| AppError::Mcp(format!("Failed to start '{program}': {e}")))?;
+        let tree = ProcessTree::attach(&child);
+
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take();
+        let stderr_pipe = child.stderr.take();
+        let stderr = Arc::new(Mutex::new(String::new()));
+
+        let (sender, messages) = mpsc::channel();
+        if let Some(stdout) = stdout {
+            let stderr = Arc::clone(&stderr);
+            let observer = observer.clone();
+            thread::spawn(move || {
+                for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    match serde_json::from_str::<Value>(&line) {
+                        Ok(message) => {
+                            if let Some(observer) = &observer {
+                                observer(Traffic::Received, &line);
+                            }
+                            if sender.send(message).is_err() {
+                                break;
+                            }
+                        }
+                        // Servers that log to stdout break the protocol; keep the
+                        // line so the user can see why.
+                        Err(_) => {
+                            if let Some(observer) = &observer {
+                                observer(Traffic::Stdout, &line);
+                            }
+                            push_capped(&stderr, &format!("[stdout] {line}"));
+                        }
+                    }
+                }
+                if let Some(observer) = &observer {
+                    observer(Traffic::Exited, "stdout closed");
+                }
+            });
+        }
+        let (stderr_sender, stderr_done) = mpsc::channel::<()>();
+        let stderr_reader = stderr_pipe.map(|pipe| {
+            let stderr = Arc::clone(&stderr);
+            let observer = observer.clone();
+            thread::spawn(move || {
+                let _done = stderr_sender;
+                let mut reader = BufReader::new(pipe);
+                let mut line = Vec::new();
+                while reader.read_until(b'\n', &mut line).unwrap_or(0) > 0 {
+                    let text = String::from_utf8_lossy(&line);
+                    if let Some(observer) = &observer {
+                        observer(Traffic::Stderr, text.trim_end());
+                    }
+                    push_capped(&stderr, text.trim_end());
+                    line.clear();
+                }
+            })
+        });
+
+        Ok(Self {
+            child,
+            tree,
+            stdin,
+            messages,
+            stderr,
+            stderr_reader,
+            stderr_done,
+            observer,
+            next_id: 1,
+            closed: false,
+        })
+    }
+
+    fn send(&mut self, message: &Value) -> Result<(), AppError> {
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or_else(|| AppError::Mcp("Server stdin is closed".to_string()))?;
+        let mut line = message.to_string();
+        if let Some(observer) = &self.observer {
+            observer(Traffic::Sent, &line);
+        }
+        line.push('\n');
+        let written = stdin.write_all(line.as_bytes()).and_then(|_| stdin.flush());
+        written.map_err(|e| {
+            AppError::Mcp(match self.exit_status(SHUTDOWN_GRACE) {
+                Some(status) => format!("Server exited ({status})"),
+                None => format!("Failed to write to server: {e}"),
+            })
+        })
+    }
+
+    /// A server that closed a pipe is usually exiting; gives it `grace` to
+    /// finish so the exit status can be reported.
+    fn exit_status(&mut self, grace: Duration) -> Option<std::process::ExitStatus> {
+        let deadline = Instant::now() + grace;
+        loop {
+            match self.child.try_wait() {
+                Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+                Ok(status) => return status,
+                Err(_) => return None,
+            }
+        }
+    }
+
+    fn wait_for(&mut self, id: i64, method: &str, timeout: Duration) -> Result<Value, AppError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let message = match self.messages.recv_timeout(remaining) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(AppError::Mcp(format!(
+                        "Timed out after {}ms waiting for '{method}'",
+                        timeout.as_millis()
+                    )));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    let status = self.exit_status(SHUTDOWN_GRACE);
+                    return Err(AppError::Mcp(match status {
+                        Some(status) => format!("Server exited ({status}) before answering '{method}'"),
+                        None => format!("Server closed stdout before answering '{method}'"),
+                    }));
+                }
+            };
+
+            if message.get("method").is_some() {
+                self.answer_server_request(&message)?;
+                continue;
+            }
+            if message.get("id").and_then(Value::as_i64) != Some(id) {
+                continue;
+            }
+            return take_result(message, method);
+        }
+    }
+
+    /// Servers may ping or ask for roots while we wait; answer so they do
+    /// not stall the handshake.
+    fn answer_server_request(&mut self, message: &Value) -> Result<(), AppError> {
+        let Some(id) = message.get("id").cloned() else {
+            return Ok(());
+        };
+        let reply = match message.get("method").and_then(Value::as_str) {
+            Some("ping") => json!({ "jsonrpc": "2.0", "id": id, "result": {} }),
+            _ => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": "Method not supported by Open Switch" }
+            }),
+        };
+        self.send(&reply)
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.child.id()
+    }
+
+    pub fn stderr(&self) -> String {
+        self.stderr.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    /// Closes stdin (the stdio shutdown signal), gives the server a moment
+    /// to exit, then kills it and anything it started. Returns everything
+    /// captured from stderr.
+    pub fn shutdown(mut self) -> String {
+        self.terminate(SHUTDOWN_GRACE);
+        self.stderr()
+    }
+
+    fn terminate(&mut self, grace: Duration) {
+        if self.closed {
+            return;
+        }
+        self.closed = true;
+        drop(self.stdin.take());
+
+        // The server exiting closes stdout, which ends the reader and with
+        // it the channel.
+        let deadline = Instant::now() + grace;
+        while Instant::now() < deadline {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if let Err(RecvTimeoutError::Disconnected) = self.messages.recv_timeout(remaining) {
+                break;
+            }
+        }
+
+        // The child is not reaped yet, so its group id is still its own.
+        self.tree.kill();
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+
+        // A process that left the group could keep stderr open; do not
+        // wait for it forever.
+        if let Err(RecvTimeoutError::Disconnected) = self.stderr_done.recv_timeout(SHUTDOWN_GRACE) {
+            if let Some(reader) = self.stderr_reader.take() {
+                let _ = reader.join();
+            }
+        }
+    }
+}
+
+impl Drop for StdioSession {
+    /// A session dropped without `shutdown` (an early return, a panic)
+    /// must not leave the server running.
+    fn drop(&mut self) {
+        self.terminate(Duration::ZERO);
+    }
+}
+
+impl McpTransport for StdioSession {
+    fn kind(&self) -> &'static str {
+        "stdio"
+    }
+
+    fn request(&mut self, method: &str, params: Value, timeout: Duration) -> Result<Value, AppError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))?;
+        self.wait_for(id, method, timeout)
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> Result<(), AppError> {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    fn close(self: Box<Self>) -> String {
+        self.shutdown()
+    }
+}
+
+pub fn take_result(message: Value, method: &str) -> Result<Value, AppError> {
+    if let Some(error) = message.get("error") {
+        let text = error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown error");
+        return Err(AppError::Mcp(format!("'{method}' failed: {text}")));
+    }
+    Ok(message.get("result").cloned().unwrap_or(Value::Null))
+}
+
+// ============== Handshake ==============
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerInfo {
+    #[serde(rename = "protocolVersion")]
+    pub protocol_version: Option<String>,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub capabilities: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+}
+
+pub fn initialize_params() -> Value {
+    json!({
+        "protocolVersion": MCP_PROTOCOL_VERSION,
+        "capabilities": {},
+        "clientInfo": { "name": "open-switch", "version": env!("CARGO_PKG_VERSION") }
+    })
+}
+
+pub fn parse_initialize_result(result: &Value) -> ServerInfo {
+    let text = |value: Option<&Value>| value.and_then(Value::as_str).map(str::to_string);
+    ServerInfo {
+        protocol_version: text(result.get("protocolVersion")),
+        name: text(result.pointer("/serverInfo/name")),
+        version: text(result.pointer("/serverInfo/version")),
+        capabilities: result.get("capabilities").cloned().unwrap_or_else(|| json!({})),
+        instructions: text(result.get("instructions")),
+    }
+}
+
+pub fn initialize(
+    session: &mut dyn McpTransport,
+    timeout: Duration,
+) -> Result<ServerInfo, AppError> {
+    let result = session.request("initialize", initialize_params(), timeout)?;
+    session.notify("notifications/initialized", json!({}))?;
+    Ok(parse_initialize_result(&result))
+}
+
+pub struct OpenFailure {
+    pub error: AppError,
+    pub stderr: String,
+}
+
+impl From<AppError> for OpenFailure {
+    fn from(error: AppError) -> Self {
+        Self {
+            error,
+            stderr: String::new(),
+        }
+    }
+}
+
+impl From<OpenFailure> for AppError {
+    fn from(failure: OpenFailure) -> Self {
+        match failure.error {
+            AppError::Mcp(message) if !failure.stderr.trim().is_empty() => {
+                AppError::Mcp(format!("{message}\n{}", failure.stderr.trim_end()))
+            }
+            error => error,
+        }
+    }
+}
+
+fn resolved_headers(server: &McpServer) -> Result<HashMap<String, String>, AppError> {
+    let base_dir = config_dir()?;
+    Ok(server
+        .headers
+        .iter()
+        .flatten()
+        .map(|(name, value)| {
+            let value = resolve_value(value, &base_dir).unwrap_or_else(|| value.clone());
+            (name.clone(), value)
+        })
+        .collect())
+}
+
+/// Starts or connects to `server` and completes the `initialize` handshake.
+pub fn open(server: &McpServer) -> Result<(Box<dyn McpTransport>, ServerInfo), OpenFailure> {
+    let timeout = server_timeout(server);
+
+    match server.server_type {
+        McpServerType::Local => {
+            let command = server.command.as_deref().ok_or_else(|| {
+                AppError::InvalidInput("Local MCP server has no command".to_string())
+            })?;
+            let mut session = StdioSession::spawn(command, server.environment.as_ref())?;
+            match initialize(&mut session, timeout) {
+                Ok(info) => Ok((Box::new(session), info)),
+                Err(error) => Err(OpenFailure {
+                    error,
+                    stderr: session.shutdown(),
+                }),
+            }
+        }
+        McpServerType::Remote => {
+            let url = server.url.as_deref().ok_or_else(|| {
+                AppError::InvalidInput("Remote MCP server has no url".to_string())
+            })?;
+            let url = resolve_value(url, &config_dir()?).unwrap_or_else(|| url.to_string());
+            Ok(mcp_remote::connect(&url, resolved_headers(server)?, timeout)?)
+        }
+    }
+}
+
+// ============== Health check ==============
+
+#[derive(Debug, Clone, Serialize)]
+pub struct McpCheckResult {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transport: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<ServerInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(rename = "authFailed")]
+    pub auth_failed: bool,
+    #[serde(rename = "httpStatus", skip_serializing_if = "Option::is_none")]
+    pub http_status: Option<u16>,
+    pub stderr: String,
+    #[serde(rename = "elapsedMs")]
+    pub elapsed_ms: u64,
+}
+
+pub fn check_server(server: &McpServer) -> McpCheckResult {
+    let started = Instant::now();
+    let outcome = open(server);
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+
+    match outcome {
+        Ok((transport, info)) => McpCheckResult {
+            ok: true,
+            transport: Some(transport.kind().to_string()),
+            server: Some(info),
+            error: None,
+            auth_failed: false,
+            http_status: None,
+            stderr: transport.close(),
+            elapsed_ms,
+        },
+        Err(failure) => McpCheckResult {
+            ok: false,
+            transport: None,
+            server: None,
+            auth_failed: mcp_remote::is_auth_failure(&failure.error),
+            http_status: match failure.error {
+                AppError::McpHttp { status, .. } => Some(status),
+                _ => None,
+            },
+            error: Some(failure.error.to_string()),
+            stderr: failure.stderr,
+            elapsed_ms,
+        },
+    }
+}
+
+// ============== Inventory ==============
+
+const MAX_LIST_PAGES: usize = 50;
+
+const RISK_RULES: &[(&str, &[&str])] = &[
+    (
+        "executes commands",
+        &["shell", "exec", "command", "terminal", "bash", "powershell", "subprocess", "spawn", "eval", "run_code"],
+    ),
+    (
+        "modifies files",
+        &["write_file", "edit_file", "delete", "remove", "move_file", "rename", "create_file", "overwrite"],
+    ),
+    ("controls a browser", &["browser", "navigate", "click", "playwright", "puppeteer"]),
+    ("sends network requests", &["fetch", "http", "request", "curl", "webhook"]),
+    ("runs database queries", &["sql", "query", "database"]),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskyTool {
+    pub name: String,
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct McpInventory {
+    pub server: ServerInfo,
+    pub transport: String,
+    pub tools: Vec<Value>,
+    pub resources: Vec<Value>,
+    pub prompts: Vec<Value>,
+    #[serde(rename = "riskyTools")]
+    pub risky_tools: Vec<RiskyTool>,
+    pub warnings: Vec<String>,
+    pub stderr: String,
+}
+
+pub fn assess_tool(tool: &Value) -> Option<RiskyTool> {
+    let name = tool.get("name").and_then(Value::as_str)?;
+    let haystack = format!(
+        "{} {}",
+        name,
+        tool.get("description").and_then(Value::as_str).unwrap_or_default()
+    )
+    .to_lowercase();
+
+    let mut reasons: Vec<String> = RISK_RULES
+        .iter()
+        .filter(|(_, keywords)| keywords.iter().any(|k| haystack.contains(k)))
+        .map(|(reason, _)| reason.to_string())
+        .collect();
+    if tool.pointer("/annotations/destructiveHint").and_then(Value::as_bool) == Some(true) {
+        reasons.push("marked destructive by the server".to_string());
+    }
+
+    (!reasons.is_empty()).then(|| RiskyTool {
+        name: name.to_string(),
+        reasons,
+    })
+}
+
+fn list_all(
+    session: &mut dyn McpTransport,
+    method: &str,
+    key: &str,
+    timeout: Duration,
+) -> Result<Vec<Value>, AppError> {
+    let mut items = Vec::new();
+    let mut cursor: Option<String> = None;
+
+    for _ in 0..MAX_LIST_PAGES {
+        let params = match &cursor {
+            Some(cursor) => json!({ "cursor": cursor }),
+            None => json!({}),
+        };
+        let result = session.request(method, params, timeout)?;
+        if let Some(Value::Array(page)) = result.get(key) {
+            items.extend(page.iter().cloned());
+        }
+        cursor = result
+            .get("nextCursor")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        if cursor.is_none() {
+            break;
+        }
+    }
+
+    Ok(items)
+}
+
+pub fn inspect_server(server: &McpServer) -> Result<McpInventory, AppError> {
+    let timeout = server_timeout(server);
+    let (mut session, info) = open(server)?;
+
+    let mut inventory = McpInventory {
+        server: info,
+        transport: session.kind().to_string(),
+        tools: Vec::new(),
+        resources: Vec::new(),
+        prompts: Vec::new(),
+        risky_tools: Vec::new(),
+        warnings: Vec::new(),
+        stderr: String::new(),
+    };
+
+    let capabilities = inventory.server.capabilities.clone();
+    for (capability, method, key) in [
+        ("tools", "tools/list", "tools"),
+        ("resources", "resources/list", "resources"),
+        ("prompts", "prompts/list", "prompts"),
+    ] {
+        if capabilities.get(capability).is_none() {
+            continue;
+        }
+        match list_all(session.as_mut(), method, key, timeout) {
+            Ok(items) => match capability {
+                "tools" => inventory.tools = items,
+                "resources" => inventory.resources = items,
+                _ => inventory.prompts = items,
+            },
+            Err(e) => inventory.warnings.push(e.to_string()),
+        }
+    }
+
+    inventory.risky_tools = inventory.tools.iter().filter_map(assess_tool).collect();
+    inventory.stderr = session.close();
+    Ok(inventory)
+}
+
+#[cfg(all(test, unix))]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A stdio MCP server in a few lines of shell: answers `initialize`,
+    /// `ping` and `tools/list`, and writes `extra` before starting.
+    pub(crate) fn fake_server(extra: &str) -> McpServer {
+        let script = format!(
+            r#"{extra}
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  case "$line" in
+    *'"method":"initialize"'*)
+      echo "starting" >&2
+      echo '{{"jsonrpc":"2.0","id":'"$id"',"result":{{"protocolVersion":"2025-06-18","serverInfo":{{"name":"fake","version":"1.2.3"}},"capabilities":{{"tools":{{}}}}}}}}' ;;
+    *'"method":"tools/list"'*)
+      echo '{{"jsonrpc":"2.0","id":'"$id"',"result":{{"tools":[{{"name":"run_shell","description":"Runs a command"}},{{"name":"add"}}]}}}}' ;;
+    *'"method":"notifications/'*) ;;
+    *) echo '{{"jsonrpc":"2.0","id":'"$id"',"error":{{"code":-32601,"message":"nope"}}}}' ;;
+  esac
+done"#
+        );
+        McpServer {
+            server_type: McpServerType::Local,
+            command: Some(vec!["sh".into(), "-c".into(), script]),
+            environment: None,
+            enabled: None,
+            timeout: Some(5000),
+            url: None,
+            headers: None,
+        }
+    }
+
+    fn is_running(pid: i32) -> bool {
+        // SAFETY: signal 0 only checks whether the process exists.
+        unsafe { libc::kill(pid, 0) == 0 }
+    }
+
+    pub(crate) fn wait_until_gone(pid: i32) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if !is_running(pid) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        false
+    }
+
+    fn temp_file(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("open-switch-{name}-{}", std::process::id()))
+    }
+
+    #[test]
+    fn check_completes_the_handshake() {
+        let result = check_server(&fake_server(""));
+        assert!(result.ok, "{:?}", result.error);
+        assert_eq!(result.transport.as_deref(), Some("stdio"));
+        let info = result.server.unwrap();
+        assert_eq!(info.name.as_deref(), Some("fake"));
+        assert_eq!(info.version.as_deref(), Some("1.2.3"));
+        assert_eq!(info.protocol_version.as_deref(), Some("2025-06-18"));
+        assert!(result.stderr.contains("starting"));
+    }
+
+    #[test]
+    fn check_reports_a_server_that_exits() {
+        let result = check_server(&fake_server("echo 'missing token' >&2; exit 3"));
+        assert!(!result.ok);
+        let error = result.error.unwrap();
+        assert!(error.contains("exited"), "{error}");
+        assert!(result.stderr.contains("missing token"));
+    }
+
+    #[test]
+    fn inspect_lists_tools_and_flags_risky_ones() {
+        let inventory = inspect_server(&fake_server("")).unwrap();
+        let names: Vec<_> = inventory.tools.iter().filter_map(|t| t["name"].as_str()).collect();
+        assert_eq!(names, ["run_shell", "add"]);
+        assert_eq!(inventory.risky_tools.len(), 1);
+        assert_eq!(inventory.risky_tools[0].name, "run_shell");
+        assert!(inventory.warnings.is_empty());
+    }
+
+    #[test]
+    fn shutdown_kills_processes_the_server_started() {
+        let pid_file = temp_file("shutdown");
+        let extra = format!("sleep 60 & echo $! > '{}'", pid_file.display());
+        let command = fake_server(&extra).command.unwrap();
+        let mut session = StdioSession::spawn(&command, None).unwrap();
+        initialize(&mut session, Duration::from_secs(5)).unwrap();
+        let grandchild: i32 = std::fs::read_to_string(&pid_file).unwrap().trim().parse().unwrap();
+        assert!(is_running(grandchild));
+
+        session.shutdown();
+        let _ = std::fs::remove_file(&pid_file);
+        assert!(wait_until_gone(grandchild));
+    }
+
+    #[test]
+    fn dropping_a_session_stops_the_server() {
+        let pid_file = temp_file("drop");
+        let extra = format!("sleep 60 & echo $! > '{}'", pid_file.display());
+        let command = fake_server(&extra).command.unwrap();
+        let mut session = StdioSession::spawn(&command, None).unwrap();
+        initialize(&mut session, Duration::from_secs(5)).unwrap();
+        let server = session.pid() as i32;
+        let grandchild: i32 = std::fs::read_to_string(&pid_file).unwrap().trim().parse().unwrap();
+
+        drop(session);
+        let _ = std::fs::remove_file(&pid_file);
+        assert!(wait_until_gone(server));
+        assert!(wait_until_gone(grandchild));
+    }
+}
@@ -0,0 +1,808 @@
+use crate::config::{get_opencode_config, save_opencode_config, McpServer, McpServerType};
+use crate::error::AppError;
+use crate::permissions;
+use crate::secrets::mask_mcp_server;
+use crate::substitution::{find_references, ReferenceKind};
+use serde::{Deserialize, Serialize};
+use indexmap::IndexMap;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum McpClient {
+    ClaudeDesktop,
+    Cursor,
+    VsCode,
+    Windsurf,
+}
+
+impl McpClient {
+    pub const ALL: [McpClient; 4] = [
+        McpClient::ClaudeDesktop,
+        McpClient::Cursor,
+        McpClient::VsCode,
+        McpClient::Windsurf,
+    ];
+
+    pub fn servers_key(self) -> &'static str {
+        match self {
+            McpClient::VsCode => "servers",
+            _ => "mcpServers",
+        }
+    }
+
+    fn url_key(self) -> &'static str {
+        match self {
+            McpClient::Windsurf => "serverUrl",
+            _ => "url",
+        }
+    }
+
+    /// VS Code and Cursor expand `${env:NAME}`; the other clients do not
+    /// substitute anything.
+    fn supports_env_placeholders(self) -> bool {
+        matches!(self, McpClient::VsCode | McpClient::Cursor)
+    }
+
+    pub fn default_paths(self) -> Vec<PathBuf> {
+        let home = dirs::home_dir();
+        let config = dirs::config_dir();
+        match self {
+            McpClient::ClaudeDesktop => config
+                .map(|c| c.join("Claude").join("claude_desktop_config.json"))
+                .into_iter()
+                .collect(),
+            McpClient::Cursor => home
+                .map(|h| h.join(".cursor").join("mcp.json"))
+                .into_iter()
+                .collect(),
+            McpClient::VsCode => config
+                .map(|c| {
+                    ["Code", "Code - Insiders", "VSCodium"]
+                        .iter()
+                        .map(|app| c.join(app).join("User").join("mcp.json"))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            McpClient::Windsurf => home
+                .map(|h| h.join(".codeium").join("windsurf").join("mcp_config.json"))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            McpClient::ClaudeDesktop => "Claude Desktop",
+            McpClient::Cursor => "Cursor",
+            McpClient::VsCode => "VS Code",
+            McpClient::Windsurf => "Windsurf",
+        }
+    }
+
+    pub fn detect(path: &Path, document: &Value) -> Self {
+        let lowered = path.to_string_lossy().replace('\\', "/").to_lowercase();
+        if lowered.ends_with("claude_desktop_config.json") {
+            McpClient::ClaudeDesktop
+        } else if lowered.contains("/.cursor/") {
+            McpClient::Cursor
+        } else if lowered.contains("windsurf") || lowered.contains("/.codeium/") {
+            McpClient::Windsurf
+        } else if lowered.contains("/.vscode/") || document.get("servers").is_some() {
+            McpClient::VsCode
+        } else {
+            McpClient::ClaudeDesktop
+        }
+    }
+}
+
+// ============== Reading client files ==============
+
+/// Strips `//` and `/* */` comments and trailing commas, which VS Code
+/// accepts in its JSON files.
+pub fn strip_jsonc(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    let mut in_string = false;
+    // Where the last comma went in `out`, until the next token shows
+    // whether it was trailing.
+    let mut comma = None;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if in_string {
+            out.push(c);
+            if c == '\\' && i + 1 < chars.len() {
+                out.push(chars[i + 1]);
+                i += 1;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') {
+                i += 1;
+            }
+            i += 2;
+            continue;
+        } else {
+            if !c.is_whitespace() {
+                if let Some(at) = comma.take() {
+                    if matches!(c, '}' | ']') {
+                        out.remove(at);
+                    }
+                }
+            }
+            match c {
+                '"' => in_string = true,
+                ',' => comma = Some(out.len()),
+                _ => {}
+            }
+            out.push(c);
+        }
+        i += 1;
+    }
+
+    out
+}
+
+pub fn read_client_file(path: &Path) -> Result<Value, AppError> {
+    let content = std::fs::read_to_string(path).map_err(|e| AppError::io(path, e))?;
+    serde_json::from_str(&strip_jsonc(&content)).map_err(|e| AppError::json(path, e))
+}
+
+fn import_placeholders(value: &str, client: McpClient, warnings: &mut Vec<String>) -> String {
+    if value.contains("${input:") {
+        warnings.push(format!("'{value}' prompts for input in the source client"));
+    }
+    if client.supports_env_placeholders() {
+        value.replace("${env:", "{env:")
+    } else {
+        value.to_string()
+    }
+}
+
+fn string_map(
+    value: Option<&Value>,
+    client: McpClient,
+    warnings: &mut Vec<String>,
+) -> Option<IndexMap<String, String>> {
+    let map: IndexMap<String, String> = value?
+        .as_object()?
+        .iter()
+        .map(|(key, value)| {
+            let text = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            (key.clone(), import_placeholders(&text, client, warnings))
+        })
+        .collect();
+    (!map.is_empty()).then_some(map)
+}
+
+/// Converts one entry of a client's `mcpServers`/`servers` map.
+pub fn convert_entry(entry: &Value, client: McpClient) -> Converted {
+    let mut warnings = Vec::new();
+    let object = entry.as_object().ok_or("entry is not an object")?;
+
+    if object.contains_key("envFile") {
+        warnings.push("envFile is not supported by OpenCode and was ignored".to_string());
+    }
+    let enabled = match object.get("disabled").and_then(Value::as_bool) {
+        Some(true) => Some(false),
+        _ => None,
+    };
+
+    let url = object
+        .get(client.url_key())
+        .or_else(|| object.get("url"))
+        .or_else(|| object.get("serverUrl"))
+        .and_then(Value::as_str);
+
+    let server = if let Some(url) = url {
+        McpServer {
+            server_type: McpServerType::Remote,
+            command: None,
+            environment: None,
+            enabled,
+            timeout: None,
+            url: Some(import_placeholders(url, client, &mut warnings)),
+            headers: string_map(object.get("headers"), client, &mut warnings),
+        }
+    } else {
+        let program = object
+            .get("command")
+            .and_then(Value::as_str)
+            .ok_or("entry has neither a command nor a url")?;
+        let mut command = vec![import_placeholders(program, client, &mut warnings)];
+        for arg in object.get("args").and_then(Value::as_array).into_iter().flatten() {
+            let arg = arg.as_str().map(str::to_string).unwrap_or_else(|| arg.to_string());
+            command.push(import_placeholders(&arg, client, &mut warnings));
+        }
+        if object.contains_key("cwd") {
+            warnings.push("cwd is not supported by OpenCode and was ignored".to_string());
+        }
+        McpServer {
+            server_type: McpServerType::Local,
+            command: Some(McpServer::normalize_command_for_platform(command)),
+            environment: string_map(object.get("env"), client, &mut warnings),
+            enabled,
+            timeout: None,
+            url: None,
+            headers: None,
+        }
+    };
+
+    Ok((server, warnings))
+}
+
+type Converted = Result<(McpServer, Vec<String>), String>;
+
+fn parse_servers(document: &Value, client: McpClient) -> Vec<(String, Converted)> {
+    let entries = document
+        .get(client.servers_key())
+        .or_else(|| document.get("mcpServers"))
+        .or_else(|| document.get("servers"))
+        .and_then(Value::as_object);
+
+    entries
+        .into_iter()
+        .flatten()
+        .map(|(name, entry)| (name.clone(), convert_entry(entry, client)))
+        .collect()
+}
+
+// ============== Discovery and preview ==============
+
+#[derive(Debug, Clone, Serialize)]
+pub struct McpSource {
+    pub client: McpClient,
+    pub path: String,
+    #[serde(rename = "serverCount")]
+    pub server_count: usize,
+}
+
+pub fn discover_sources() -> Vec<McpSource> {
+    McpClient::ALL
+        .iter()
+        .flat_map(|client| client.default_paths().into_iter().map(move |p| (*client, p)))
+        .filter(|(_, path)| path.is_file())
+        .filter_map(|(client, path)| {
+            let document = read_client_file(&path).ok()?;
+            Some(McpSource {
+                client,
+                server_count: parse_servers(&document, client).len(),
+                path: path.display().to_string(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportCandidate {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<McpServer>,
+    /// An MCP server with this name already exists in opencode.json.
+    pub conflict: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportPreview {
+    pub client: McpClient,
+    pub path: String,
+    pub candidates: Vec<ImportCandidate>,
+}
+
+fn load_source(path: &str, client: Option<McpClient>) -> Result<(McpClient, Value), AppError> {
+    let path = Path::new(path);
+    let document = read_client_file(path)?;
+    let client = client.unwrap_or_else(|| McpClient::detect(path, &document));
+    Ok((client, document))
+}
+
+pub fn preview_import(path: &str, client: Option<McpClient>) -> Result<ImportPreview, AppError> {
+    let (client, document) = load_source(path, client)?;
+    let config = get_opencode_config()?;
+    let existing = config.mcp.unwrap_or_default();
+
+    let candidates = parse_servers(&document, client)
+        .into_iter()
+        .map(|(name, converted)| {
+            let conflict = existing.contains_key(&name);
+            match converted {
+                Ok((mut server, warnings)) => {
+                    mask_mcp_server(&mut server);
+                    ImportCandidate {
+                        name,
+                        server: Some(server),
+                        conflict,
+                        warnings,
+                        error: None,
+                    }
+                }
+                Err(error) => ImportCandidate {
+                    name,
+                    server: None,
+                    conflict,
+                    warnings: Vec::new(),
+                    error: Some(error),
+                },
+            }
+        })
+        .collect();
+
+    Ok(ImportPreview {
+        client,
+        path: path.to_string(),
+        candidates,
+    })
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportSummary {
+    pub imported: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+/// Inserts the selected servers in a single write. Names that already exist
+/// are skipped unless `overwrite` is set.
+pub fn import_servers(
+    path: &str,
+    client: Option<McpClient>,
+    names: &[String],
+    overwrite: bool,
+) -> Result<ImportSummary, AppError> {
+    let (client, document) = load_source(path, client)?;
+    let mut converted: HashMap<String, McpServer> = parse_servers(&document, client)
+        .into_iter()
+        .filter_map(|(name, result)| result.ok().map(|(server, _)| (name, server)))
+        .collect();
+
+    let mut config = get_opencode_config()?;
+    let mcp = config.mcp.get_or_insert_with(IndexMap::new);
+    let mut summary = ImportSummary::default();
+
+    for name in names {
+        match converted.remove(name) {
+            Some(server) if overwrite || !mcp.contains_key(name) => {
+                mcp.insert(name.clone(), server);
+                summary.imported.push(name.clone());
+            }
+            _ => summary.skipped.push(name.clone()),
+        }
+    }
+
+    if !summary.imported.is_empty() {
+        save_opencode_config(&config)?;
+        log::info!(
+            "Imported {} MCP server(s) from {path}",
+            summary.imported.len()
+        );
+    }
+    Ok(summary)
+}
+
+// ============== Export ==============
+
+fn export_placeholders(value: &str, client: McpClient, warnings: &mut Vec<String>) -> String {
+    for reference in find_references(value) {
+        let unsupported = match reference.kind {
+            ReferenceKind::Env => !client.supports_env_placeholders(),
+            ReferenceKind::File => true,
+        };
+        if unsupported {
+            warnings.push(format!(
+                "{{{}:{}}} is copied verbatim; {} will not substitute it",
+                match reference.kind {
+                    ReferenceKind::Env => "env",
+                    ReferenceKind::File => "file",
+                },
+                reference.name,
+                client.label()
+            ));
+        }
+    }
+    if client.supports_env_placeholders() {
+        value.replace("{env:", "${env:")
+    } else {
+        value.to_string()
+    }
+}
+
+fn export_map(
+    map: &IndexMap<String, String>,
+    client: McpClient,
+    warnings: &mut Vec<String>,
+) -> Value {
+    Value::Object(
+        map.iter()
+            .map(|(k, v)| (k.clone(), Value::String(export_placeholders(v, client, warnings))))
+            .collect(),
+    )
+}
+
+/// Builds the client-side entry for one server, or explains why the client
+/// cannot represent it.
+pub fn export_entry(server: &McpServer, client: McpClient) -> Result<(Value, Vec<String>), String> {
+    let mut warnings = Vec::new();
+    let mut entry = Map::new();
+
+    match server.server_type {
+        McpServerType::Local => {
+            let command = server
+                .command
+                .as_ref()
+                .filter(|c| !c.is_empty())
+                .ok_or("local server has no command")?;
+            if client == McpClient::VsCode {
+                entry.insert("type".into(), "stdio".into());
+            }
+            entry.insert(
+                "command".into(),
+                export_placeholders(&command[0], client, &mut warnings).into(),
+            );
+            let args: Vec<Value> = command[1..]
+                .iter()
+                .map(|arg| export_placeholders(arg, client, &mut warnings).into())
+                .collect();
+            entry.insert("args".into(), Value::Array(args));
+            if let Some(env) = server.environment.as_ref().filter(|e| !e.is_empty()) {
+                entry.insert("env".into(), export_map(env, client, &mut warnings));
+            }
+        }
+        McpServerType::Remote => {
+            if client == McpClient::ClaudeDesktop {
+                return Err(format!(
+                    "{} only supports local servers in its config file",
+                    client.label()
+                ));
+            }
+            let url = server.url.as_ref().ok_or("remote server has no url")?;
+            if client == McpClient::VsCode {
+                entry.insert("type".into(), "http".into());
+            }
+            entry.insert(
+                client.url_key().into(),
+                export_placeholders(url, client, &mut warnings).into(),
+            );
+            if let Some(headers) = server.headers.as_ref().filter(|h| !h.is_empty()) {
+                entry.insert("headers".into(), export_map(headers, client, &mut warnings));
+            }
+        }
+    }
+
+    if server.enabled == Some(false) {
+        match client {
+            McpClient::Cursor | McpClient::Windsurf => {
+                entry.insert("disabled".into(), true.into());
+            }
+            _ => warnings.push(format!(
+                "{} has no disabled flag; the server will run",
+                client.label()
+            )),
+        }
+    }
+    if server.timeout.is_some() {
+        warnings.push(format!(
+            "{} has no per-server timeout; it was dropped",
+            client.label()
+        ));
+    }
+
+    Ok((Value::Object(entry), warnings))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportSkip {
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct McpExport {
+    pub client: McpClient,
+    /// The exported servers in `client`'s format, without the rest of the
+    /// target file. Secrets are masked unless they are placeholders.
+    pub document: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub exported: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<ExportSkip>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+fn build_servers(
+    servers: &IndexMap<String, McpServer>,
+    names: &[String],
+    client: McpClient,
+    export: &mut McpExport,
+) -> Map<String, Value> {
+    let mut built = Map::new();
+    for name in names {
+        let Some(server) = servers.get(name) else {
+            export.skipped.push(ExportSkip {
+                name: name.clone(),
+                reason: "not found in opencode.json".into(),
+            });
+            continue;
+        };
+        match export_entry(server, client) {
+            Ok((entry, warnings)) => {
+                export
+                    .warnings
+                    .extend(warnings.into_iter().map(|w| format!("{name}: {w}")));
+                export.exported.push(name.clone());
+                built.insert(name.clone(), entry);
+            }
+            Err(reason) => export.skipped.push(ExportSkip {
+                name: name.clone(),
+                reason,
+            }),
+        }
+    }
+    built
+}
+
+fn servers_document(client: McpClient, servers: Map<String, Value>) -> Value {
+    let mut document = Map::new();
+    document.insert(client.servers_key().into(), Value::Object(servers));
+    Value::Object(document)
+}
+
+/// Converts the selected servers (all of them when `names` is `None`) into
+/// `client`'s format. With `target`, the servers are merged into that file
+/// in place: other top-level keys and other servers are kept, entries with
+/// the same name are replaced. A target with comments or trailing commas is
+/// refused, since rewriting it would drop them.
+pub fn export_servers(
+    client: McpClient,
+    names: Option<Vec<String>>,
+    target: Option<&str>,
+) -> Result<McpExport, AppError> {
+    let servers = get_opencode_config()?.mcp.unwrap_or_default();
+    let names = names.unwrap_or_else(|| servers.keys().cloned().collect());
+
+    let mut export = McpExport {
+        client,
+        document: Value::Null,
+        path: target.map(str::to_string),
+        exported: Vec::new(),
+        skipped: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    let masked: IndexMap<String, McpServer> = servers
+        .iter()
+        .map(|(name, server)| {
+            let mut server = server.clone();
+            mask_mcp_server(&mut server);
+            (name.clone(), server)
+        })
+        .collect();
+
+    let Some(target) = target else {
+        let built = build_servers(&masked, &names, client, &mut export);
+        export.document = servers_document(client, built);
+        return Ok(export);
+    };
+
+    let path = Path::new(target);
+    let mut document = if path.exists() {
+        let raw = std::fs::read_to_string(path).map_err(|e| AppError::io(path, e))?;
+        let stripped = strip_jsonc(&raw);
+        if stripped != raw {
+            return Err(AppError::InvalidInput(format!(
+                "{target} contains comments or trailing commas, which exporting would remove"
+            )));
+        }
+        match serde_json::from_str::<Value>(&stripped).map_err(|e| AppError::json(path, e))? {
+            Value::Object(map) => map,
+            _ => {
+                return Err(AppError::InvalidInput(format!(
+                    "{target} does not contain a JSON object"
+                )))
+            }
+        }
+    } else {
+        Map::new()
+    };
+
+    let built = build_servers(&servers, &names, client, &mut export);
+    let section = document
+        .entry(client.servers_key())
+        .or_insert_with(|| Value::Object(Map::new()));
+    let Value::Object(existing) = section else {
+        return Err(AppError::InvalidInput(format!(
+            "'{}' in {target} is not an object",
+            client.servers_key()
+        )));
+    };
+    existing.extend(built);
+
+    if !export.exported.is_empty() {
+        let content = serde_json::to_string_pretty(&document)
+            .map_err(|e| AppError::JsonSerialize { source: e })?;
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            if !parent.exists() {
+                std::fs::create_dir_all(parent).map_err(|e| AppError::io(parent, e))?;
+            }
+        }
+        // The exported env and headers usually carry secrets.
+        permissions::write_private_file(path, &format!("{content}\n"))?;
+        log::info!(
+            "Exported {} MCP server(s) to {target}",
+            export.exported.len()
+        );
+    }
+
+    // The rest of the file belongs to the other client and may hold its
+    // secrets, so only the exported servers are returned.
+    let returned: Map<String, Value> = export
+        .exported
+        .iter()
+        .filter_map(|name| {
+            let (entry, _) = export_entry(masked.get(name)?, client).ok()?;
+            Some((name.clone(), entry))
+        })
+        .collect();
+    export.document = servers_document(client, returned);
+    Ok(export)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(text: &str) -> Value {
+        serde_json::from_str(&strip_jsonc(text)).unwrap()
+    }
+
+    #[test]
+    fn strips_comments_and_trailing_commas() {
+        let text = r#"{
+            // servers
+            "servers": {
+                "a": { "command": "run", "args": ["x", "y",], }, // note
+                /* block, */ "b": { "url": "https://example.com/a//b,}" },
+                /* last */
+            },
+        }"#;
+        assert_eq!(
+            parse(text),
+            json!({
+                "servers": {
+                    "a": { "command": "run", "args": ["x", "y"] },
+                    "b": { "url": "https://example.com/a//b,}" }
+                }
+            })
+        );
+        assert_eq!(strip_jsonc(r#"["a\"//", 1]"#), r#"["a\"//", 1]"#);
+        assert_eq!(strip_jsonc("[1, /* , */ 2,\n// ]\n]"), "[1,  2\n\n]");
+    }
+
+    #[test]
+    fn converts_local_and_remote_entries() {
+        let (local, warnings) = convert_entry(
+            &json!({
+                "command": "server-bin",
+                "args": ["--port", 8080, "${env:TOKEN}"],
+                "env": { "API_KEY": "${env:API_KEY}", "DEBUG": true },
+                "disabled": true
+            }),
+            McpClient::VsCode,
+        )
+        .unwrap();
+        assert!(warnings.is_empty());
+        assert!(matches!(local.server_type, McpServerType::Local));
+        let command = local.command.unwrap();
+        assert_eq!(command[command.len() - 3..], ["--port", "8080", "{env:TOKEN}"]);
+        let environment = local.environment.unwrap();
+        assert_eq!(environment["API_KEY"], "{env:API_KEY}");
+        assert_eq!(environment["DEBUG"], "true");
+        assert_eq!(local.enabled, Some(false));
+
+        let (remote, warnings) = convert_entry(
+            &json!({
+                "serverUrl": "https://mcp.example/${env:ID}",
+                "headers": { "Authorization": "Bearer ${input:token}" }
+            }),
+            McpClient::Windsurf,
+        )
+        .unwrap();
+        assert!(matches!(remote.server_type, McpServerType::Remote));
+        // Windsurf does not expand placeholders, so they stay as written.
+        assert_eq!(remote.url.as_deref(), Some("https://mcp.example/${env:ID}"));
+        assert_eq!(remote.headers.unwrap()["Authorization"], "Bearer ${input:token}");
+        assert_eq!(remote.enabled, None);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("prompts for input"));
+
+        let (_, warnings) =
+            convert_entry(&json!({ "command": "x", "cwd": "/tmp", "envFile": ".env" }), McpClient::Cursor)
+                .unwrap();
+        assert_eq!(warnings.len(), 2);
+        assert!(convert_entry(&json!({ "args": ["x"] }), McpClient::Cursor).is_err());
+        assert!(convert_entry(&json!("x"), McpClient::Cursor).is_err());
+    }
+
+    #[test]
+    fn detects_the_client_from_path_and_content() {
+        let empty = json!({});
+        let detect = |path: &str| McpClient::detect(Path::new(path), &empty);
+        assert_eq!(detect("/Users/a/Library/Application Support/Claude/claude_desktop_config.json"), McpClient::ClaudeDesktop);
+        assert_eq!(detect("/home/a/.cursor/mcp.json"), McpClient::Cursor);
+        assert_eq!(detect(r"C:\Users\a\.codeium\windsurf\mcp_config.json"), McpClient::Windsurf);
+        assert_eq!(detect("/work/project/.vscode/mcp.json"), McpClient::VsCode);
+        assert_eq!(detect("/tmp/mcp.json"), McpClient::ClaudeDesktop);
+        assert_eq!(
+            McpClient::detect(Path::new("/tmp/mcp.json"), &json!({ "servers": {} })),
+            McpClient::VsCode
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reports_and_skips_conflicting_imports() {
+        let _files = crate::config::tests::config_files();
+        let mut config = get_opencode_config().unwrap();
+        let existing: McpServer = serde_json::from_value(json!({
+            "type": "local",
+            "command": ["existing-bin"]
+        }))
+        .unwrap();
+        config.mcp = Some(IndexMap::from([("shared".to_string(), existing)]));
+        save_opencode_config(&config).unwrap();
+
+        let source = std::env::temp_dir().join(format!("open-switch-import-{}.json", std::process::id()));
+        std::fs::write(
+            &source,
+            r#"{
+                "mcpServers": {
+                    "shared": { "command": "new-bin", "env": { "API_KEY": "sk-live-1234567890" } },
+                    "fresh": { "command": "fresh-bin" }, // added
+                    "broken": { "args": [] },
+                }
+            }"#,
+        )
+        .unwrap();
+        let source = source.display().to_string();
+
+        let preview = preview_import(&source, None).unwrap();
+        assert_eq!(preview.client, McpClient::ClaudeDesktop);
+        let candidate = |name: &str| preview.candidates.iter().find(|c| c.name == name).unwrap();
+        assert!(candidate("shared").conflict);
+        assert!(!candidate("fresh").conflict);
+        assert!(candidate("broken").error.is_some());
+        let environment = candidate("shared").server.as_ref().unwrap().environment.clone().unwrap();
+        assert_ne!(environment["API_KEY"], "sk-live-1234567890");
+
+        let names = ["shared", "fresh", "broken"].map(String::from);
+        let summary = import_servers(&source, None, &names, false).unwrap();
+        assert_eq!(summary.imported, ["fresh"]);
+        assert_eq!(summary.skipped, ["shared", "broken"]);
+        let mcp = get_opencode_config().unwrap().mcp.unwrap();
+        assert_eq!(mcp["shared"].command.as_ref().unwrap()[0], "existing-bin");
+
+        let summary = import_servers(&source, None, &names[..1], true).unwrap();
+        assert_eq!(summary.imported, ["shared"]);
+        let mcp = get_opencode_config().unwrap().mcp.unwrap();
+        assert_eq!(mcp["shared"].environment.as_ref().unwrap()["API_KEY"], "sk-live-1234567890");
+    }
+}
@@ -0,0 +1,713 @@
+use crate::error::AppError;
+use crate::mcp_client::{
+    initialize_params, parse_initialize_result, take_result, McpTransport, ServerInfo,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const SESSION_HEADER: &str = "Mcp-Session-Id";
+const PROTOCOL_HEADER: &str = "MCP-Protocol-Version";
+const UNREACHABLE: &str = "Failed to reach";
+/// How often the event stream reader checks whether it should stop.
+const STREAM_POLL: Duration = Duration::from_millis(100);
+
+// ============== Server-Sent Events ==============
+
+#[derive(Debug, Default)]
+pub struct SseEvent {
+    pub event: String,
+    pub data: String,
+}
+
+/// Reads one event from an SSE stream; `None` once the stream ends.
+pub fn next_sse_event(reader: &mut impl BufRead) -> std::io::Result<Option<SseEvent>> {
+    let mut event = SseEvent::default();
+    let mut has_data = false;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(has_data.then_some(event));
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+
+        if line.is_empty() {
+            if has_data {
+                return Ok(Some(event));
+            }
+            continue;
+        }
+        if line.starts_with(':') {
+            continue;
+        }
+
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => event.event = value.to_string(),
+            "data" => {
+                if has_data {
+                    event.data.push('\n');
+                }
+                event.data.push_str(value);
+                has_data = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+// ============== HTTP helpers ==============
+
+fn http_error(error: ureq::Error, url: &str) -> AppError {
+    match error {
+        ureq::Error::Status(status, response) => {
+            let challenge = response.header("WWW-Authenticate").map(str::to_string);
+            let mut message = response.status_text().to_string();
+            if let Some(challenge) = challenge {
+                message = format!("{message} ({challenge})");
+            }
+            AppError::McpHttp { status, message }
+        }
+        ureq::Error::Transport(transport) => match transport.url() {
+            Some(_) => AppError::Mcp(format!("{UNREACHABLE} {transport}")),
+            None => AppError::Mcp(format!("{UNREACHABLE} {url}: {transport}")),
+        },
+    }
+}
+
+pub fn is_auth_failure(error: &AppError) -> bool {
+    matches!(error, AppError::McpHttp { status: 401 | 403, .. })
+}
+
+fn is_unreachable(error: &AppError) -> bool {
+    matches!(error, AppError::Mcp(message) if message.starts_with(UNREACHABLE))
+}
+
+fn describe(error: &AppError) -> String {
+    match error {
+        AppError::Mcp(message) => message.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Resolves the `endpoint` announced by a legacy SSE server against the URL
+/// the stream was opened on.
+pub fn join_url(base: &str, reference: &str) -> String {
+    if reference.starts_with("http://") || reference.starts_with("https://") {
+        return reference.to_string();
+    }
+    let scheme_end = base.find("://").map(|i| i + 3).unwrap_or(0);
+    let path_start = base[scheme_end..]
+        .find('/')
+        .map(|i| scheme_end + i)
+        .unwrap_or(base.len());
+    let origin = &base[..path_start];
+
+    if reference.starts_with('/') {
+        return format!("{origin}{reference}");
+    }
+    let path = base[path_start..].split(['?', '#']).next().unwrap_or("");
+    let dir = Path::new(path)
+        .parent()
+        .map(|p| p.to_string_lossy().trim_end_matches('/').to_string())
+        .unwrap_or_default();
+    format!("{origin}{dir}/{reference}")
+}
+
+fn find_response(message: Value, id: i64) -> Option<Value> {
+    match message {
+        Value::Array(batch) => batch.into_iter().find_map(|m| find_response(m, id)),
+        message if message.get("id").and_then(Value::as_i64) == Some(id) => Some(message),
+        _ => None,
+    }
+}
+
+// ============== Streamable HTTP ==============
+
+pub struct StreamableHttpSession {
+    agent: ureq::Agent,
+    url: String,
+    headers: HashMap<String, String>,
+    session_id: Option<String>,
+    protocol_version: Option<String>,
+    next_id: i64,
+}
+
+impl StreamableHttpSession {
+    pub fn new(url: &str, headers: HashMap<String, String>, timeout: Duration) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new().timeout_connect(timeout).build(),
+            url: url.to_string(),
+            headers,
+            session_id: None,
+            protocol_version: None,
+            next_id: 1,
+        }
+    }
+
+    fn post(&self, body: &Value, timeout: Duration) -> Result<ureq::Response, AppError> {
+        let mut request = self
+            .agent
+            .post(&self.url)
+            .timeout(timeout)
+            .set("Content-Type", "application/json")
+            .set("Accept", "application/json, text/event-stream");
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+        if let Some(ref session_id) = self.session_id {
+            request = request.set(SESSION_HEADER, session_id);
+        }
+        if let Some(ref version) = self.protocol_version {
+            request = request.set(PROTOCOL_HEADER, version);
+        }
+        request
+            .send_string(&body.to_string())
+            .map_err(|e| http_error(e, &self.url))
+    }
+
+    pub fn initialize(&mut self, timeout: Duration) -> Result<ServerInfo, AppError> {
+        let result = self.request("initialize", initialize_params(), timeout)?;
+        let info = parse_initialize_result(&result);
+        self.protocol_version = info.protocol_version.clone();
+        self.notify("notifications/initialized", json!({}))?;
+        Ok(info)
+    }
+}
+
+impl McpTransport for StreamableHttpSession {
+    fn kind(&self) -> &'static str {
+        "streamableHttp"
+    }
+
+    fn request(&mut self, method: &str, params: Value, timeout: Duration) -> Result<Value, AppError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let body = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let response = self.post(&body, timeout)?;
+
+        if let Some(session_id) = response.header(SESSION_HEADER) {
+            self.session_id = Some(session_id.to_string());
+        }
+        let is_stream = response.content_type() == "text/event-stream";
+
+        let message = if is_stream {
+            let mut reader = BufReader::new(response.into_reader());
+            loop {
+                let event = next_sse_event(&mut reader)
+                    .map_err(|e| AppError::Mcp(format!("Failed to read '{method}' stream: {e}")))?
+                    .ok_or_else(|| {
+                        AppError::Mcp(format!("Stream ended before answering '{method}'"))
+                    })?;
+                let Ok(message) = serde_json::from_str::<Value>(&event.data) else {
+                    continue;
+                };
+                if let Some(message) = find_response(message, id) {
+                    break message;
+                }
+            }
+        } else {
+            let text = response
+                .into_string()
+                .map_err(|e| AppError::Mcp(format!("Failed to read '{method}' response: {e}")))?;
+            let message: Value = serde_json::from_str(&text).map_err(|_| {
+                AppError::Mcp(format!("'{method}' response is not JSON-RPC"))
+            })?;
+            find_response(message, id)
+                .ok_or_else(|| AppError::Mcp(format!("No response to '{method}' in reply")))?
+        };
+
+        take_result(message, method)
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> Result<(), AppError> {
+        let body = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        self.post(&body, Duration::from_secs(10)).map(|_| ())
+    }
+
+    fn close(self: Box<Self>) -> String {
+        if let Some(ref session_id) = self.session_id {
+            let _ = self
+                .agent
+                .delete(&self.url)
+                .timeout(Duration::from_secs(2))
+                .set(SESSION_HEADER, session_id)
+                .call();
+        }
+        String::new()
+    }
+}
+
+// ============== Legacy HTTP+SSE ==============
+
+enum SseMessage {
+    Endpoint(String),
+    Message(Value),
+}
+
+/// Retries reads that time out, so a short read timeout only serves to
+/// notice `stop`. Once it is set the stream reads as ended, and dropping
+/// the reader closes the connection.
+struct Stoppable<R> {
+    inner: R,
+    stop: Arc<AtomicBool>,
+}
+
+impl<R: Read> Read for Stoppable<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            if self.stop.load(Ordering::Relaxed) {
+                return Ok(0);
+            }
+            match self.inner.read(buf) {
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock
+                    ) => {}
+                result => return result,
+            }
+        }
+    }
+}
+
+pub struct SseSession {
+    agent: ureq::Agent,
+    endpoint: String,
+    headers: HashMap<String, String>,
+    messages: Receiver<SseMessage>,
+    stop: Arc<AtomicBool>,
+    reader: Option<JoinHandle<()>>,
+    next_id: i64,
+}
+
+impl SseSession {
+    pub fn connect(
+        url: &str,
+        headers: HashMap<String, String>,
+        timeout: Duration,
+    ) -> Result<Self, AppError> {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(timeout)
+            .timeout_read(timeout + Duration::from_secs(1))
+            .build();
+        // The event stream idles between responses; its short read timeout
+        // only lets the reader notice when the session is closed.
+        let stream_agent = ureq::AgentBuilder::new()
+            .timeout_connect(timeout)
+            .timeout_read(STREAM_POLL)
+            .build();
+
+        let mut request = stream_agent.get(url).set("Accept", "text/event-stream");
+        for (name, value) in &headers {
+            request = request.set(name, value);
+        }
+        let response = request.call().map_err(|e| http_error(e, url))?;
+        if response.content_type() != "text/event-stream" {
+            return Err(AppError::Mcp(format!(
+                "{url} did not answer with an event stream ({})",
+                response.content_type()
+            )));
+        }
+
+        let (sender, messages) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stream = Stoppable {
+            inner: response.into_reader(),
+            stop: Arc::clone(&stop),
+        };
+        let reader = thread::spawn(move || {
+            let mut reader = BufReader::new(stream);
+            while let Ok(Some(event)) = next_sse_event(&mut reader) {
+                let message = match event.event.as_str() {
+                    "endpoint" => SseMessage::Endpoint(event.data),
+                    "" | "message" => match serde_json::from_str(&event.data) {
+                        Ok(value) => SseMessage::Message(value),
+                        Err(_) => continue,
+                    },
+                    _ => continue,
+                };
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut session = Self {
+            agent,
+            endpoint: String::new(),
+            headers,
+            messages,
+            stop,
+            reader: Some(reader),
+            next_id: 1,
+        };
+        let deadline = Instant::now() + timeout;
+        session.endpoint = loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match session.messages.recv_timeout(remaining) {
+                Ok(SseMessage::Endpoint(endpoint)) => break join_url(url, &endpoint),
+                Ok(SseMessage::Message(_)) => continue,
+                Err(_) => {
+                    return Err(AppError::Mcp(format!(
+                        "{url} did not announce an endpoint event"
+                    )));
+                }
+            }
+        };
+        Ok(session)
+    }
+
+    fn post(&self, body: &Value) -> Result<(), AppError> {
+        let mut request = self
+            .agent
+            .post(&self.endpoint)
+            .set("Content-Type", "application/json");
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+        request
+            .send_string(&body.to_string())
+            .map(|_| ())
+            .map_err(|e| http_error(e, &self.endpoint))
+    }
+
+    pub fn initialize(&mut self, timeout: Duration) -> Result<ServerInfo, AppError> {
+        let result = self.request("initialize", initialize_params(), timeout)?;
+        self.notify("notifications/initialized", json!({}))?;
+        Ok(parse_initialize_result(&result))
+    }
+}
+
+impl McpTransport for SseSession {
+    fn kind(&self) -> &'static str {
+        "sse"
+    }
+
+    fn request(&mut self, method: &str, params: Value, timeout: Duration) -> Result<Value, AppError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.post(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.messages.recv_timeout(remaining) {
+                Ok(SseMessage::Message(message)) => {
+                    if let Some(message) = find_response(message, id) {
+                        return take_result(message, method);
+                    }
+                }
+                Ok(SseMessage::Endpoint(_)) => {}
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(AppError::Mcp(format!(
+                        "Timed out after {}ms waiting for '{method}'",
+                        timeout.as_millis()
+                    )));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(AppError::Mcp(format!(
+                        "Event stream closed before answering '{method}'"
+                    )));
+                }
+            }
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> Result<(), AppError> {
+        self.post(&json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    fn close(self: Box<Self>) -> String {
+        // Dropping stops the reader and closes the stream.
+        String::new()
+    }
+}
+
+impl Drop for SseSession {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+    }
+}
+
+// ============== Transport detection ==============
+
+/// Tries Streamable HTTP first and falls back to the legacy SSE transport
+/// when the endpoint rejects a JSON-RPC POST. Auth failures and unreachable
+/// hosts never fall back.
+pub fn connect(
+    url: &str,
+    headers: HashMap<String, String>,
+    timeout: Duration,
+) -> Result<(Box<dyn McpTransport>, ServerInfo), AppError> {
+    let mut streamable = StreamableHttpSession::new(url, headers.clone(), timeout);
+    let streamable_error = match streamable.initialize(timeout) {
+        Ok(info) => return Ok((Box::new(streamable), info)),
+        Err(e) if is_auth_failure(&e) || is_unreachable(&e) => return Err(e),
+        Err(e) => e,
+    };
+
+    let mut sse = match SseSession::connect(url, headers, timeout) {
+        Ok(sse) => sse,
+        Err(e) if is_auth_failure(&e) => return Err(e),
+        Err(e) => {
+            return Err(AppError::Mcp(format!(
+                "Streamable HTTP: {}; SSE: {}",
+                describe(&streamable_error),
+                describe(&e)
+            )));
+        }
+    };
+    let info = sse.initialize(timeout)?;
+    Ok((Box::new(sse), info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Mutex;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    struct Request {
+        method: String,
+        path: String,
+        headers: HashMap<String, String>,
+        body: Value,
+    }
+
+    impl Request {
+        fn id(&self) -> Option<i64> {
+            self.body.get("id").and_then(Value::as_i64)
+        }
+
+        fn rpc_method(&self) -> &str {
+            self.body.get("method").and_then(Value::as_str).unwrap_or("")
+        }
+    }
+
+    fn read_request(stream: &TcpStream) -> Option<Request> {
+        let mut reader = BufReader::new(stream.try_clone().ok()?);
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let mut parts = line.split_whitespace();
+        let method = parts.next()?.to_string();
+        let path = parts.next()?.to_string();
+
+        let mut headers = HashMap::new();
+        loop {
+            line.clear();
+            reader.read_line(&mut line).ok()?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+            }
+        }
+        let length = headers
+            .get("content-length")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).ok()?;
+        Some(Request {
+            method,
+            path,
+            headers,
+            body: serde_json::from_slice(&body).unwrap_or(Value::Null),
+        })
+    }
+
+    fn respond(mut stream: &TcpStream, status: &str, headers: &[(&str, &str)], body: &str) {
+        let mut head = format!(
+            "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            body.len()
+        );
+        for (name, value) in headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        let _ = write!(stream, "{head}\r\n{body}");
+    }
+
+    /// Serves every connection on its own thread and returns the base URL.
+    fn serve(handler: impl Fn(Request, TcpStream) + Send + Sync + 'static) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        let handler = Arc::new(handler);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let handler = Arc::clone(&handler);
+                thread::spawn(move || {
+                    if let Some(request) = read_request(&stream) {
+                        handler(request, stream);
+                    }
+                });
+            }
+        });
+        url
+    }
+
+    fn reply(id: i64, result: Value) -> String {
+        json!({ "jsonrpc": "2.0", "id": id, "result": result }).to_string()
+    }
+
+    fn server_info() -> Value {
+        json!({
+            "protocolVersion": "2025-03-26",
+            "serverInfo": { "name": "stand-in", "version": "1.0.0" },
+            "capabilities": { "tools": {} }
+        })
+    }
+
+    #[test]
+    fn reads_events_split_across_lines() {
+        let stream = ": comment\nevent: endpoint\ndata: /messages\n\ndata: {\"a\":\ndata: 1}\n\n";
+        let mut reader = BufReader::new(stream.as_bytes());
+
+        let first = next_sse_event(&mut reader).unwrap().unwrap();
+        assert_eq!((first.event.as_str(), first.data.as_str()), ("endpoint", "/messages"));
+        let second = next_sse_event(&mut reader).unwrap().unwrap();
+        assert_eq!((second.event.as_str(), second.data.as_str()), ("", "{\"a\":\n1}"));
+        assert!(next_sse_event(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn joins_endpoints_against_the_stream_url() {
+        let base = "http://host:8080/api/sse?token=1";
+        assert_eq!(join_url(base, "/messages?s=1"), "http://host:8080/messages?s=1");
+        assert_eq!(join_url(base, "messages"), "http://host:8080/api/messages");
+        assert_eq!(join_url(base, "https://other/x"), "https://other/x");
+    }
+
+    #[test]
+    fn streamable_http_handshake_and_session_end() {
+        let deleted = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&deleted);
+        let url = serve(move |request, stream| match request.method.as_str() {
+            "DELETE" => {
+                let session = request.headers.get("mcp-session-id").cloned();
+                seen.lock().unwrap().push(session);
+                respond(&stream, "200 OK", &[], "");
+            }
+            _ => match (request.rpc_method(), request.id()) {
+                ("initialize", Some(id)) => respond(
+                    &stream,
+                    "200 OK",
+                    &[("Content-Type", "application/json"), (SESSION_HEADER, "abc")],
+                    &reply(id, server_info()),
+                ),
+                ("tools/list", Some(id)) => {
+                    let body = format!(
+                        "event: message\ndata: {}\n\n",
+                        reply(id, json!({ "tools": [{ "name": "echo" }] }))
+                    );
+                    respond(&stream, "200 OK", &[("Content-Type", "text/event-stream")], &body);
+                }
+                _ => respond(&stream, "202 Accepted", &[], ""),
+            },
+        });
+
+        let (mut session, info) = connect(&url, HashMap::new(), TIMEOUT).unwrap();
+        assert_eq!(session.kind(), "streamableHttp");
+        assert_eq!(info.name.as_deref(), Some("stand-in"));
+
+        let tools = session.request("tools/list", json!({}), TIMEOUT).unwrap();
+        assert_eq!(tools["tools"][0]["name"], "echo");
+
+        session.close();
+        assert_eq!(*deleted.lock().unwrap(), vec![Some("abc".to_string())]);
+    }
+
+    #[test]
+    fn falls_back_to_sse_and_closes_the_stream() {
+        let events: Arc<Mutex<Option<TcpStream>>> = Arc::default();
+        let (closed_tx, closed) = mpsc::channel();
+        let closed_tx = Mutex::new(closed_tx);
+        let stream_slot = Arc::clone(&events);
+        let url = serve(move |request, mut stream| match request.method.as_str() {
+            "GET" => {
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n\
+                     event: endpoint\ndata: /messages?session=1\n\n"
+                );
+                *stream_slot.lock().unwrap() = stream.try_clone().ok();
+                // Returns once the client drops the connection.
+                let _ = stream.read(&mut [0; 1]);
+                let _ = closed_tx.lock().unwrap().send(());
+            }
+            "POST" if request.path.starts_with("/messages") => {
+                respond(&stream, "202 Accepted", &[], "");
+                let Some(id) = request.id() else { return };
+                let result = match request.rpc_method() {
+                    "initialize" => server_info(),
+                    _ => json!({ "tools": [] }),
+                };
+                let event = format!("event: message\ndata: {}\n\n", reply(id, result));
+                let (head, tail) = event.split_at(event.len() / 2);
+                let mut slot = stream_slot.lock().unwrap();
+                let events = slot.as_mut().unwrap();
+                // Pause mid-event for longer than the reader's poll interval.
+                let _ = events.write_all(head.as_bytes());
+                thread::sleep(STREAM_POLL * 3);
+                let _ = events.write_all(tail.as_bytes());
+            }
+            _ => respond(&stream, "405 Method Not Allowed", &[], ""),
+        });
+
+        let (mut session, info) = connect(&url, HashMap::new(), TIMEOUT).unwrap();
+        assert_eq!(session.kind(), "sse");
+        assert_eq!(info.name.as_deref(), Some("stand-in"));
+        let tools = session.request("tools/list", json!({}), TIMEOUT).unwrap();
+        assert_eq!(tools["tools"], json!([]));
+
+        let started = Instant::now();
+        session.close();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        closed.recv_timeout(Duration::from_secs(1)).expect("event stream left open");
+        assert!(events.lock().unwrap().is_some());
+    }
+
+    #[test]
+    fn auth_failures_do_not_fall_back() {
+        for status in ["401 Unauthorized", "403 Forbidden"] {
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let seen = Arc::clone(&requests);
+            let url = serve(move |request, stream| {
+                seen.lock().unwrap().push(request.method);
+                respond(&stream, status, &[("WWW-Authenticate", "Bearer realm=\"mcp\"")], "");
+            });
+
+            let error = connect(&url, HashMap::new(), TIMEOUT).err().unwrap();
+            assert!(is_auth_failure(&error), "{status}: {error}");
+            assert!(error.to_string().contains("Bearer realm"));
+            assert_eq!(*requests.lock().unwrap(), vec!["POST".to_string()]);
+        }
+    }
+
+    #[test]
+    fn unreachable_hosts_do_not_fall_back() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        drop(listener);
+
+        let error = connect(&url, HashMap::new(), TIMEOUT).err().unwrap();
+        assert!(is_unreachable(&error), "{error}");
+    }
+}
@@ -0,0 +1,318 @@
+use crate::config::{
+    get_opencode_config, insert_after, rename_key, reorder_keys, save_opencode_config, McpServer,
+};
+use crate::database::Database;
+use crate::error::AppError;
+use crate::provider_service::validate_new_name;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+/// Servers picked by name, by tag, or both (the union is used).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct McpSelection {
+    #[serde(default)]
+    pub names: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BulkAction {
+    Enable,
+    Disable,
+    Delete,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BulkResult {
+    pub affected: Vec<String>,
+    /// Names that were selected explicitly but are not in opencode.json.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+}
+
+/// Trims, drops empties, de-duplicates and sorts.
+pub fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    tags.into_iter()
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+pub struct McpService;
+
+impl McpService {
+    /// Tags per server. Tags live in the app database so opencode.json only
+    /// ever contains what OpenCode understands.
+    pub fn get_tags(db: &Arc<Database>) -> Result<HashMap<String, Vec<String>>, AppError> {
+        let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+        for (server, tag) in db.get_mcp_server_tags()? {
+            tags.entry(server).or_default().push(tag);
+        }
+        Ok(tags)
+    }
+
+    pub fn set_tags(db: &Arc<Database>, name: &str, tags: Vec<String>) -> Result<Vec<String>, AppError> {
+        let config = get_opencode_config()?;
+        if !config.mcp.as_ref().is_some_and(|m| m.contains_key(name)) {
+            return Err(AppError::InvalidInput(format!("MCP server '{name}' not found")));
+        }
+        let tags = normalize_tags(tags);
+        db.set_mcp_server_tags(name, &tags)?;
+        Ok(tags)
+    }
+
+    pub fn forget(db: &Arc<Database>, names: &[String]) -> Result<(), AppError> {
+        db.delete_mcp_server_tags(names)
+    }
+
+    /// Applies `action` to every selected server with a single write of
+    /// opencode.json.
+    pub fn bulk_update(
+        db: &Arc<Database>,
+        selection: &McpSelection,
+        action: BulkAction,
+    ) -> Result<BulkResult, AppError> {
+        let mut config = get_opencode_config()?;
+        let mcp = config.mcp.get_or_insert_with(IndexMap::new);
+        let mut result = BulkResult::default();
+
+        let mut selected: BTreeSet<String> = BTreeSet::new();
+        for name in &selection.names {
+            if mcp.contains_key(name) {
+                selected.insert(name.clone());
+            } else {
+                result.missing.push(name.clone());
+            }
+        }
+        if !selection.tags.is_empty() {
+            selected.extend(
+                db.get_mcp_server_tags()?
+                    .into_iter()
+                    .filter(|(server, tag)| selection.tags.contains(tag) && mcp.contains_key(server))
+                    .map(|(server, _)| server),
+            );
+        }
+        if selected.is_empty() {
+            return Ok(result);
+        }
+
+        for name in &selected {
+            match action {
+                BulkAction::Enable | BulkAction::Disable => {
+                    if let Some(server) = mcp.get_mut(name) {
+                        server.enabled = Some(matches!(action, BulkAction::Enable));
+                    }
+                }
+                BulkAction::Delete => {
+                    mcp.shift_remove(name);
+                }
+            }
+        }
+        if mcp.is_empty() {
+            config.mcp = None;
+        }
+        save_opencode_config(&config)?;
+
+        result.affected = selected.into_iter().collect();
+        if matches!(action, BulkAction::Delete) {
+            db.delete_mcp_server_tags(&result.affected)?;
+        }
+        log::info!("Bulk {action:?} of {} MCP server(s)", result.affected.len());
+        Ok(result)
+    }
+
+    fn check_names(
+        mcp: &IndexMap<String, McpServer>,
+        old: &str,
+        new: &str,
+    ) -> Result<(), AppError> {
+        validate_new_name("MCP server", old, new)?;
+        if !mcp.contains_key(old) {
+            return Err(AppError::InvalidInput(format!("MCP server '{old}' not found")));
+        }
+        if mcp.contains_key(new) {
+            return Err(AppError::InvalidInput(format!("MCP server '{new}' already exists")));
+        }
+        Ok(())
+    }
+
+    /// Renames a server along with the tool permissions that name its tools,
+    /// its tags and its logs. Returns the config paths that were rewritten.
+    pub fn rename(db: &Arc<Database>, old: &str, new: &str) -> Result<Vec<String>, AppError> {
+        let mut config = get_opencode_config()?;
+        let mcp = config.mcp.get_or_insert_with(IndexMap::new);
+        Self::check_names(mcp, old, new)?;
+
+        rename_key(mcp, old, new);
+        let references = config.rename_mcp_references(old, new);
+        db.rename_mcp_server_records(old, new)?;
+        if let Err(e) = save_opencode_config(&config) {
+            if let Err(rollback) = db.rename_mcp_server_records(new, old) {
+                log::error!("Failed to move MCP server records back to {old}: {rollback}");
+            }
+            return Err(e);
+        }
+
+        log::info!("Renamed MCP server {old} -> {new}");
+        Ok(references)
+    }
+
+    pub fn duplicate(db: &Arc<Database>, source: &str, new: &str) -> Result<(), AppError> {
+        let mut config = get_opencode_config()?;
+        let mcp = config.mcp.get_or_insert_with(IndexMap::new);
+        Self::check_names(mcp, source, new)?;
+
+        if let Some(server) = mcp.get(source).cloned() {
+            insert_after(mcp, source, new.to_string(), server);
+        }
+        save_opencode_config(&config)?;
+        db.copy_mcp_server_tags(source, new)?;
+
+        log::info!("Duplicated MCP server {source} -> {new}");
+        Ok(())
+    }
+
+    pub fn reorder(order: &[String]) -> Result<(), AppError> {
+        let mut config = get_opencode_config()?;
+        let mcp = config.mcp.get_or_insert_with(IndexMap::new);
+        reorder_keys(mcp, order)?;
+        save_opencode_config(&config)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::config::get_config_path;
+    use crate::config::tests::config_files;
+    use crate::mcp_client::Traffic;
+    use crate::mcp_supervisor::McpLogEntry;
+    use serde_json::json;
+
+    /// A database and an opencode.json with a local server per name.
+    fn setup(names: &[&str]) -> Arc<Database> {
+        let mut config = get_opencode_config().unwrap();
+        config.mcp = Some(
+            names
+                .iter()
+                .map(|name| {
+                    let server = serde_json::from_value(json!({ "type": "local", "command": [name] })).unwrap();
+                    (name.to_string(), server)
+                })
+                .collect(),
+        );
+        save_opencode_config(&config).unwrap();
+        Arc::new(Database::open_in_memory().unwrap())
+    }
+
+    fn enabled(name: &str) -> Option<bool> {
+        get_opencode_config().unwrap().mcp.unwrap()[name].enabled
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn updates_every_selected_server_in_one_call() {
+        let _files = config_files();
+        let db = setup(&["a", "b", "c", "d"]);
+        McpService::set_tags(&db, "b", strings(&["web"])).unwrap();
+        McpService::set_tags(&db, "c", strings(&["web", "db"])).unwrap();
+
+        let selection = McpSelection { names: strings(&["a", "gone"]), tags: strings(&["web"]) };
+        let result = McpService::bulk_update(&db, &selection, BulkAction::Disable).unwrap();
+        assert_eq!(result.affected, ["a", "b", "c"]);
+        assert_eq!(result.missing, ["gone"]);
+        for name in ["a", "b", "c"] {
+            assert_eq!(enabled(name), Some(false), "{name}");
+        }
+        assert_eq!(enabled("d"), None);
+
+        let selection = McpSelection { names: Vec::new(), tags: strings(&["db"]) };
+        let result = McpService::bulk_update(&db, &selection, BulkAction::Enable).unwrap();
+        assert_eq!(result.affected, ["c"]);
+        assert_eq!(enabled("c"), Some(true));
+        assert_eq!(enabled("b"), Some(false));
+
+        // Nothing selected leaves the file alone.
+        let path = get_config_path().unwrap();
+        let before = std::fs::read_to_string(&path).unwrap();
+        let selection = McpSelection { names: Vec::new(), tags: strings(&["none"]) };
+        assert!(McpService::bulk_update(&db, &selection, BulkAction::Delete).unwrap().affected.is_empty());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), before);
+    }
+
+    #[test]
+    fn keeps_tags_out_of_opencode_json() {
+        let _files = config_files();
+        let db = setup(&["search"]);
+
+        let tags = McpService::set_tags(&db, "search", strings(&[" web ", "", "api", "web"])).unwrap();
+        assert_eq!(tags, ["api", "web"]);
+        assert_eq!(McpService::get_tags(&db).unwrap()["search"], ["api", "web"]);
+        let written = std::fs::read_to_string(get_config_path().unwrap()).unwrap();
+        assert!(!written.contains("tags"));
+        assert!(!written.contains("\"web\""));
+
+        assert!(McpService::set_tags(&db, "missing", strings(&["web"])).is_err());
+    }
+
+    #[test]
+    fn deleting_servers_removes_their_tags() {
+        let _files = config_files();
+        let db = setup(&["a", "b"]);
+        McpService::set_tags(&db, "a", strings(&["web"])).unwrap();
+        McpService::set_tags(&db, "b", strings(&["web"])).unwrap();
+
+        let selection = McpSelection { names: strings(&["a", "b"]), tags: Vec::new() };
+        let result = McpService::bulk_update(&db, &selection, BulkAction::Delete).unwrap();
+        assert_eq!(result.affected, ["a", "b"]);
+        assert!(get_opencode_config().unwrap().mcp.is_none());
+        assert!(McpService::get_tags(&db).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rename_moves_tool_permissions_tags_and_logs() {
+        let _files = config_files();
+        let db = setup(&["git", "foo"]);
+        let mut config = get_opencode_config().unwrap();
+        config.extra.insert("tools".to_string(), json!({ "foo_*": false, "git_log": true }));
+        save_opencode_config(&config).unwrap();
+        McpService::set_tags(&db, "foo", strings(&["web"])).unwrap();
+        let log = |server: &str, message: &str| McpLogEntry {
+            id: None,
+            server: server.to_string(),
+            stream: Traffic::Stderr,
+            message: message.to_string(),
+            timestamp: 1,
+        };
+        // Left behind by a server that was deleted outside the app.
+        db.set_mcp_server_tags("bar", &strings(&["web", "old"])).unwrap();
+        db.add_mcp_logs(&[log("foo", "started"), log("bar", "stale")]).unwrap();
+
+        let references = McpService::rename(&db, "foo", "bar").unwrap();
+        assert_eq!(references, ["tools"]);
+        let config = get_opencode_config().unwrap();
+        assert_eq!(config.mcp.unwrap().keys().collect::<Vec<_>>(), ["git", "bar"]);
+        assert_eq!(config.extra["tools"], json!({ "bar_*": false, "git_log": true }));
+
+        let tags = McpService::get_tags(&db).unwrap();
+        assert_eq!(tags["bar"], ["web"]);
+        assert!(!tags.contains_key("foo"));
+        let logs = db.get_mcp_logs("bar", 10, None).unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].message, "started");
+        assert!(db.get_mcp_logs("foo", 10, None).unwrap().is_empty());
+
+        assert!(McpService::rename(&db, "bar", "git").is_err());
+        assert!(McpService::rename(&db, "missing", "other").is_err());
+        assert!(McpService::rename(&db, "bar", " bar").is_err());
+    }
+}
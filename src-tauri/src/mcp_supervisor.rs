@@ -0,0 +1,452 @@
+use crate::config::{McpServer, McpServerType};
+use crate::database::Database;
+use crate::error::AppError;
+use crate::mcp_client::{self, McpTransport, ServerInfo, StdioSession, Traffic, TrafficObserver};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+pub const LOG_RETENTION_DAYS: i64 = 7;
+pub const MAX_LOGS_PER_SERVER: u32 = 5000;
+const MAX_LOG_MESSAGE_BYTES: usize = 16 * 1024;
+/// Most lines stored in one transaction.
+const LOG_BATCH: usize = 256;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct McpLogEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+    pub server: String,
+    pub stream: Traffic,
+    pub message: String,
+    /// Milliseconds since the epoch; lines arrive faster than once a second.
+    pub timestamp: i64,
+}
+
+/// Receives every log line as it happens. The supervisor does not know
+/// about Tauri or the database; the caller decides where lines go.
+pub type LogSink = Arc<dyn Fn(&McpLogEntry) + Send + Sync>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SupervisedServer {
+    pub name: String,
+    pub pid: u32,
+    pub command: Vec<String>,
+    #[serde(rename = "startedAt")]
+    pub started_at: i64,
+    pub running: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<ServerInfo>,
+}
+
+struct Running {
+    /// Emptied by `stop`, which may run while a request holds the lock.
+    session: Arc<Mutex<Option<StdioSession>>>,
+    exited: Arc<AtomicBool>,
+    status: SupervisedServer,
+}
+
+fn truncate(message: &str) -> String {
+    if message.len() <= MAX_LOG_MESSAGE_BYTES {
+        return message.to_string();
+    }
+    let mut end = MAX_LOG_MESSAGE_BYTES;
+    while !message.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}… ({} bytes truncated)", &message[..end], message.len() - end)
+}
+
+fn emit(sink: &LogSink, server: &str, stream: Traffic, message: &str) {
+    sink(&McpLogEntry {
+        id: None,
+        server: server.to_string(),
+        stream,
+        message: truncate(message),
+        timestamp: chrono::Utc::now().timestamp_millis(),
+    });
+}
+
+#[derive(Default)]
+struct Servers {
+    running: HashMap<String, Running>,
+    /// Names reserved while their server is being started.
+    starting: HashSet<String>,
+    /// Set by `stop_all`; nothing starts afterwards.
+    closed: bool,
+}
+
+pub struct McpSupervisor {
+    servers: Mutex<Servers>,
+    sink: LogSink,
+}
+
+/// Releases a name reserved by `start` if the server never made it into
+/// the running set.
+struct Reservation<'a> {
+    supervisor: &'a McpSupervisor,
+    name: &'a str,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut servers) = self.supervisor.lock() {
+            servers.starting.remove(self.name);
+        }
+    }
+}
+
+impl McpSupervisor {
+    pub fn new(sink: LogSink) -> Self {
+        Self {
+            servers: Mutex::new(Servers::default()),
+            sink,
+        }
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Servers>, AppError> {
+        self.servers
+            .lock()
+            .map_err(|e| AppError::Mcp(format!("Lock error: {e}")))
+    }
+
+    fn reserve<'a>(&'a self, name: &'a str) -> Result<Reservation<'a>, AppError> {
+        let mut servers = self.lock()?;
+        if servers.closed {
+            return Err(AppError::Mcp("MCP servers are shutting down".to_string()));
+        }
+        if servers.running.contains_key(name) || !servers.starting.insert(name.to_string()) {
+            return Err(AppError::InvalidInput(format!("'{name}' is already running")));
+        }
+        Ok(Reservation {
+            supervisor: self,
+            name,
+        })
+    }
+
+    fn log(&self, server: &str, stream: Traffic, message: &str) {
+        emit(&self.sink, server, stream, message);
+    }
+
+    /// Launches a local server and performs the initialize handshake, so
+    /// requests can be sent straight away. A server that fails the handshake
+    /// is stopped again; its output is already in the log.
+    pub fn start(&self, name: &str, server: &McpServer) -> Result<SupervisedServer, AppError> {
+        if !matches!(server.server_type, McpServerType::Local) {
+            return Err(AppError::InvalidInput(format!(
+                "'{name}' is a remote server; only local servers can be supervised"
+            )));
+        }
+        let command = server
+            .command
+            .as_ref()
+            .filter(|c| !c.is_empty())
+            .ok_or_else(|| AppError::InvalidInput("MCP server command is empty".to_string()))?;
+
+        let _reservation = self.reserve(name)?;
+
+        let exited = Arc::new(AtomicBool::new(false));
+        let observer: TrafficObserver = {
+            let sink = Arc::clone(&self.sink);
+            let exited = Arc::clone(&exited);
+            let server = name.to_string();
+            Arc::new(move |stream, message| {
+                if stream == Traffic::Exited {
+                    exited.store(true, Ordering::SeqCst);
+                }
+                emit(&sink, &server, stream, message);
+            })
+        };
+
+        self.log(name, Traffic::Lifecycle, "Starting");
+        let mut session =
+            StdioSession::spawn_observed(command, server.environment.as_ref(), Some(observer))
+                .inspect_err(|e| self.log(name, Traffic::Lifecycle, &e.to_string()))?;
+
+        let info = match mcp_client::initialize(&mut session, mcp_client::server_timeout(server)) {
+            Ok(info) => info,
+            Err(e) => {
+                self.log(name, Traffic::Lifecycle, &format!("Handshake failed: {e}"));
+                session.shutdown();
+                return Err(e);
+            }
+        };
+        self.log(name, Traffic::Lifecycle, &format!("Running (pid {})", session.pid()));
+
+        let status = SupervisedServer {
+            name: name.to_string(),
+            pid: session.pid(),
+            command: command.clone(),
+            started_at: chrono::Utc::now().timestamp(),
+            running: true,
+            server: Some(info),
+        };
+        let mut servers = self.lock()?;
+        if servers.closed {
+            drop(servers);
+            session.shutdown();
+            return Err(AppError::Mcp("MCP servers are shutting down".to_string()));
+        }
+        servers.running.insert(
+            name.to_string(),
+            Running {
+                session: Arc::new(Mutex::new(Some(session))),
+                exited,
+                status: status.clone(),
+            },
+        );
+        drop(servers);
+        log::info!("Started supervised MCP server: {name}");
+        Ok(status)
+    }
+
+    /// Sends an arbitrary JSON-RPC message. Methods under `notifications/`
+    /// are sent as notifications and return `null`.
+    pub fn request(
+        &self,
+        name: &str,
+        method: &str,
+        params: Value,
+        timeout: Duration,
+    ) -> Result<Value, AppError> {
+        let session = {
+            let servers = self.lock()?;
+            let entry = servers
+                .running
+                .get(name)
+                .ok_or_else(|| AppError::InvalidInput(format!("'{name}' is not running")))?;
+            if entry.exited.load(Ordering::SeqCst) {
+                return Err(AppError::Mcp(format!("'{name}' has exited")));
+            }
+            Arc::clone(&entry.session)
+        };
+
+        let mut guard = session
+            .lock()
+            .map_err(|e| AppError::Mcp(format!("Lock error: {e}")))?;
+        let session = guard
+            .as_mut()
+            .ok_or_else(|| AppError::InvalidInput(format!("'{name}' is not running")))?;
+        if method.starts_with("notifications/") {
+            session.notify(method, params)?;
+            Ok(Value::Null)
+        } else {
+            session.request(method, params, timeout)
+        }
+    }
+
+    pub fn stop(&self, name: &str) -> Result<(), AppError> {
+        let entry = self
+            .lock()?
+            .running
+            .remove(name)
+            .ok_or_else(|| AppError::InvalidInput(format!("'{name}' is not running")))?;
+        self.shut_down(name, entry)
+    }
+
+    /// Stops every server and refuses to start new ones; called when the
+    /// app exits so no server outlives it.
+    pub fn stop_all(&self) {
+        let entries: Vec<(String, Running)> = match self.lock() {
+            Ok(mut servers) => {
+                servers.closed = true;
+                servers.running.drain().collect()
+            }
+            Err(e) => {
+                log::warn!("Failed to stop MCP servers: {e}");
+                return;
+            }
+        };
+        for (name, entry) in entries {
+            if let Err(e) = self.shut_down(&name, entry) {
+                log::warn!("Failed to stop MCP server {name}: {e}");
+            }
+        }
+    }
+
+    fn shut_down(&self, name: &str, entry: Running) -> Result<(), AppError> {
+        // Waits for a request in flight to finish or time out first.
+        let session = entry
+            .session
+            .lock()
+            .map_err(|e| AppError::Mcp(format!("Lock error: {e}")))?
+            .take();
+        if let Some(session) = session {
+            session.shutdown();
+        }
+        self.log(name, Traffic::Lifecycle, "Stopped");
+        log::info!("Stopped supervised MCP server: {name}");
+        Ok(())
+    }
+
+    pub fn list(&self) -> Result<Vec<SupervisedServer>, AppError> {
+        let mut servers: Vec<SupervisedServer> = self
+            .lock()?
+            .running
+            .values()
+            .map(|entry| SupervisedServer {
+                running: !entry.exited.load(Ordering::SeqCst),
+                ..entry.status.clone()
+            })
+            .collect();
+        servers.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(servers)
+    }
+}
+
+enum LogMessage {
+    Line(McpLogEntry),
+    Flush(Sender<()>),
+}
+
+/// Stores log lines on a background thread, in batches, so a chatty server
+/// does not hold the database lock that every command needs.
+pub struct LogWriter {
+    sender: Sender<LogMessage>,
+}
+
+impl LogWriter {
+    /// `on_stored` gets each line once it is stored, with its id, or
+    /// without one if storing failed.
+    pub fn spawn(db: Arc<Database>, on_stored: impl Fn(McpLogEntry) + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || write_logs(&db, &receiver, on_stored));
+        Self { sender }
+    }
+
+    pub fn write(&self, entry: &McpLogEntry) {
+        let _ = self.sender.send(LogMessage::Line(entry.clone()));
+    }
+
+    /// Waits until every line written so far is stored.
+    pub fn flush(&self) {
+        let (done, wait) = mpsc::channel();
+        if self.sender.send(LogMessage::Flush(done)).is_ok() {
+            let _ = wait.recv();
+        }
+    }
+}
+
+fn write_logs(db: &Database, receiver: &Receiver<LogMessage>, on_stored: impl Fn(McpLogEntry)) {
+    while let Ok(first) = receiver.recv() {
+        let mut lines = Vec::new();
+        let mut flushes = Vec::new();
+        for message in std::iter::once(first).chain(receiver.try_iter().take(LOG_BATCH - 1)) {
+            match message {
+                LogMessage::Line(entry) => lines.push(entry),
+                LogMessage::Flush(done) => flushes.push(done),
+            }
+        }
+        if !lines.is_empty() {
+            match db.add_mcp_logs(&lines) {
+                Ok(ids) => {
+                    for (entry, id) in lines.into_iter().zip(ids) {
+                        on_stored(McpLogEntry { id: Some(id), ..entry });
+                    }
+                }
+                Err(e) => {
+                    log::warn!("Failed to store {} MCP log line(s): {e}", lines.len());
+                    lines.into_iter().for_each(&on_stored);
+                }
+            }
+        }
+        for done in flushes {
+            let _ = done.send(());
+        }
+    }
+}
+
+/// Applies log retention: entries older than `LOG_RETENTION_DAYS`, and all
+/// but the newest `MAX_LOGS_PER_SERVER` per server, are deleted.
+pub fn prune_logs(db: &Database) -> Result<usize, AppError> {
+    let cutoff = chrono::Utc::now().timestamp_millis() - LOG_RETENTION_DAYS * 24 * 60 * 60 * 1000;
+    db.prune_mcp_logs(cutoff, MAX_LOGS_PER_SERVER)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::mcp_client::tests::{fake_server, wait_until_gone};
+    use std::thread;
+
+    fn supervisor() -> McpSupervisor {
+        McpSupervisor::new(Arc::new(|_: &McpLogEntry| {}))
+    }
+
+    #[test]
+    fn stores_log_lines_in_the_background() {
+        let db = Arc::new(Database::open_in_memory().unwrap());
+        let stored = Arc::new(Mutex::new(Vec::new()));
+        let writer = {
+            let stored = Arc::clone(&stored);
+            LogWriter::spawn(Arc::clone(&db), move |entry| stored.lock().unwrap().push(entry))
+        };
+
+        for i in 0..1000 {
+            writer.write(&McpLogEntry {
+                id: None,
+                server: "fake".to_string(),
+                stream: Traffic::Stderr,
+                message: format!("line {i}"),
+                timestamp: i,
+            });
+        }
+        writer.flush();
+
+        let stored = stored.lock().unwrap();
+        assert_eq!(stored.len(), 1000);
+        assert!(stored.windows(2).all(|w| w[0].id < w[1].id));
+        assert_eq!(stored[999].message, "line 999");
+        let logs = db.get_mcp_logs("fake", 10, None).unwrap();
+        assert_eq!(logs[0].id, stored[999].id);
+        assert_eq!(logs[0].message, "line 999");
+    }
+
+    #[test]
+    fn starts_a_name_only_once_when_raced() {
+        let supervisor = supervisor();
+        // Slow enough that both starts overlap.
+        let server = fake_server("sleep 0.3");
+        let results: Vec<_> = thread::scope(|scope| {
+            let starts: Vec<_> = (0..2)
+                .map(|_| scope.spawn(|| supervisor.start("fake", &server)))
+                .collect();
+            starts.into_iter().map(|start| start.join().unwrap()).collect()
+        });
+
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+        let error = results.iter().find_map(|r| r.as_ref().err()).unwrap();
+        assert!(error.to_string().contains("already running"), "{error}");
+        assert_eq!(supervisor.list().unwrap().len(), 1);
+        supervisor.stop("fake").unwrap();
+    }
+
+    #[test]
+    fn a_failed_start_releases_the_name() {
+        let supervisor = supervisor();
+        assert!(supervisor.start("fake", &fake_server("exit 1")).is_err());
+        supervisor.start("fake", &fake_server("")).unwrap();
+        supervisor.stop("fake").unwrap();
+    }
+
+    #[test]
+    fn stop_all_stops_every_server_and_later_starts() {
+        let supervisor = supervisor();
+        let pids: Vec<u32> = ["one", "two"]
+            .iter()
+            .map(|name| supervisor.start(name, &fake_server("")).unwrap().pid)
+            .collect();
+
+        supervisor.stop_all();
+        assert!(supervisor.list().unwrap().is_empty());
+        for pid in pids {
+            assert!(wait_until_gone(pid as i32), "{pid} still running");
+        }
+        assert!(supervisor.start("three", &fake_server("")).is_err());
+    }
+}
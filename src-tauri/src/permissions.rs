@@ -0,0 +1,221 @@
+use crate::config::get_auth_path;
+use crate::database::Database;
+use crate::error::AppError;
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
+
+#[cfg(unix)]
+const PRIVATE_FILE_MODE: u32 = 0o600;
+#[cfg(unix)]
+const PRIVATE_DIR_MODE: u32 = 0o700;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionDiagnostic {
+    pub level: String,
+    pub path: String,
+    pub mode: String,
+    pub message: String,
+    pub repaired: bool,
+}
+
+pub fn create_private_dir(path: &Path) -> Result<(), AppError> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    builder.mode(PRIVATE_DIR_MODE);
+    builder.create(path).map_err(|e| AppError::io(path, e))
+}
+
+fn open_private(path: &Path, truncate: bool) -> Result<fs::File, AppError> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(truncate);
+    #[cfg(unix)]
+    options.mode(PRIVATE_FILE_MODE);
+    let file = options.open(path).map_err(|e| AppError::io(path, e))?;
+
+    // `mode` only applies when the file is created, so tighten leftovers too.
+    #[cfg(unix)]
+    file.set_permissions(fs::Permissions::from_mode(PRIVATE_FILE_MODE))
+        .map_err(|e| AppError::io(path, e))?;
+
+    Ok(file)
+}
+
+/// Atomically writes `content` to `path` through a temp file that is
+/// owner-only from the moment it is created.
+pub fn write_private_file(path: &Path, content: &str) -> Result<(), AppError> {
+    let extension = path
+        .extension()
+        .map(|e| format!("{}.tmp", e.to_string_lossy()))
+        .unwrap_or_else(|| "tmp".to_string());
+    let temp_path = path.with_extension(extension);
+
+    let mut file = open_private(&temp_path, true)?;
+    file.write_all(content.as_bytes())
+        .map_err(|e| AppError::io(&temp_path, e))?;
+    drop(file);
+
+    fs::rename(&temp_path, path).map_err(|e| AppError::io(path, e))?;
+    Ok(())
+}
+
+/// Creates an empty owner-only file if `path` does not exist yet, so that
+/// tools which create files with the default umask (SQLite) inherit it.
+pub fn ensure_private_file(path: &Path) -> Result<(), AppError> {
+    if path.exists() {
+        return Ok(());
+    }
+    open_private(path, false).map(|_| ())
+}
+
+pub fn secret_file_paths() -> Result<Vec<PathBuf>, AppError> {
+    let db_path = Database::get_db_path()?;
+    let mut paths = vec![get_auth_path()?];
+    for suffix in ["", "-journal", "-wal", "-shm"] {
+        let mut name = db_path.clone().into_os_string();
+        name.push(suffix);
+        paths.push(PathBuf::from(name));
+    }
+    Ok(paths)
+}
+
+#[cfg(unix)]
+fn check_file(path: &Path) -> Option<PermissionDiagnostic> {
+    let mode = fs::metadata(path).ok()?.permissions().mode() & 0o777;
+    if mode & 0o077 == 0 {
+        return None;
+    }
+    Some(PermissionDiagnostic {
+        level: "warning".to_string(),
+        path: path.display().to_string(),
+        mode: format!("{mode:04o}"),
+        message: format!(
+            "{} is readable by other users (mode {mode:04o}), expected 0600",
+            path.display()
+        ),
+        repaired: false,
+    })
+}
+
+#[cfg(not(unix))]
+fn check_file(_path: &Path) -> Option<PermissionDiagnostic> {
+    None
+}
+
+pub fn check_secret_files() -> Result<Vec<PermissionDiagnostic>, AppError> {
+    Ok(check_files(&secret_file_paths()?))
+}
+
+fn check_files(paths: &[PathBuf]) -> Vec<PermissionDiagnostic> {
+    paths.iter().filter_map(|p| check_file(p)).collect()
+}
+
+/// Tightens group/world readable secret files and reports what was found.
+pub fn repair_secret_files() -> Result<Vec<PermissionDiagnostic>, AppError> {
+    Ok(repair_files(&secret_file_paths()?))
+}
+
+fn repair_files(paths: &[PathBuf]) -> Vec<PermissionDiagnostic> {
+    #[cfg_attr(not(unix), allow(unused_mut))]
+    let mut diagnostics = check_files(paths);
+
+    #[cfg(unix)]
+    for diagnostic in diagnostics.iter_mut() {
+        let permissions = fs::Permissions::from_mode(PRIVATE_FILE_MODE);
+        match fs::set_permissions(&diagnostic.path, permissions) {
+            Ok(()) => {
+                diagnostic.repaired = true;
+                log::warn!("Repaired permissions: {}", diagnostic.message);
+            }
+            Err(e) => log::warn!("{} (repair failed: {e})", diagnostic.message),
+        }
+    }
+
+    diagnostics
+}
+
+/// Merges diagnostics gathered at startup with the current state so the UI
+/// sees both files that were fixed and files that are still exposed.
+pub fn current_diagnostics(
+    startup: &[PermissionDiagnostic],
+) -> Result<Vec<PermissionDiagnostic>, AppError> {
+    let mut diagnostics = check_secret_files()?;
+    for earlier in startup.iter().filter(|d| d.repaired) {
+        if !diagnostics.iter().any(|d| d.path == earlier.path) {
+            diagnostics.push(earlier.clone());
+        }
+    }
+    Ok(diagnostics)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn temp_dir() -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "open-switch-permissions-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn mode(path: &Path) -> u32 {
+        fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn repairs_files_readable_by_others() {
+        let dir = temp_dir();
+        let exposed = dir.join("auth.json");
+        let private = dir.join("opencode-switch.db");
+        fs::write(&exposed, "{}").unwrap();
+        fs::set_permissions(&exposed, fs::Permissions::from_mode(0o644)).unwrap();
+        write_private_file(&private, "").unwrap();
+        let paths = [exposed.clone(), private, dir.join("missing-wal")];
+
+        let diagnostics = repair_files(&paths);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].path, exposed.display().to_string());
+        assert_eq!(diagnostics[0].mode, "0644");
+        assert!(diagnostics[0].repaired);
+        assert_eq!(mode(&exposed), 0o600);
+        assert!(check_files(&paths).is_empty());
+    }
+
+    #[test]
+    fn writes_private_files_whatever_the_umask() {
+        let dir = temp_dir();
+        let path = dir.join("auth.json");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o666)).unwrap();
+
+        // SAFETY: umask(2) has no memory-safety preconditions.
+        let previous = unsafe { libc::umask(0) };
+        let written = write_private_file(&path, "{\"a\":1}");
+        let created = create_private_dir(&dir.join("nested/dir"));
+        let ensured = ensure_private_file(&dir.join("new.db"));
+        // SAFETY: as above.
+        unsafe { libc::umask(previous) };
+        written.unwrap();
+        created.unwrap();
+        ensured.unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"a\":1}");
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(&dir.join("nested/dir")), 0o700);
+        assert_eq!(mode(&dir.join("new.db")), 0o600);
+        assert!(!dir.join("auth.json.tmp").exists());
+    }
+}
@@ -0,0 +1,120 @@
+use std::process::{Child, Command};
+
+/// Starts the child in a process group of its own, so that whatever it
+/// launches (`npx` starting `node`, say) can be killed with it.
+pub fn isolate(cmd: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    #[cfg(not(unix))]
+    let _ = cmd;
+}
+
+/// A child process and everything it started.
+pub struct ProcessTree {
+    #[cfg(unix)]
+    group: i32,
+    #[cfg(windows)]
+    job: Option<job::Job>,
+}
+
+impl ProcessTree {
+    /// Call right after spawning a command passed to [`isolate`].
+    pub fn attach(child: &Child) -> Self {
+        #[cfg(unix)]
+        {
+            Self { group: child.id() as i32 }
+        }
+        #[cfg(windows)]
+        {
+            let job = job::Job::assign(child);
+            if job.is_none() {
+                log::warn!("Failed to put process {} in a job object", child.id());
+            }
+            Self { job }
+        }
+        #[cfg(not(any(unix, windows)))]
+        {
+            let _ = child;
+            Self {}
+        }
+    }
+
+    /// Kills every process in the tree. Call before the child is reaped, so
+    /// its id cannot have been reused.
+    pub fn kill(&self) {
+        #[cfg(unix)]
+        // SAFETY: kill(2) has no memory-safety preconditions.
+        unsafe {
+            libc::kill(-self.group, libc::SIGKILL);
+        }
+        #[cfg(windows)]
+        if let Some(job) = &self.job {
+            job.terminate();
+        }
+    }
+}
+
+#[cfg(windows)]
+mod job {
+    use std::os::windows::io::AsRawHandle;
+    use std::process::Child;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+        SetInformationJobObject, TerminateJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+
+    /// A job object that kills its processes when the last handle closes,
+    /// so they also go if Open Switch itself exits.
+    pub struct Job(HANDLE);
+
+    // SAFETY: a job handle may be used and closed from any thread.
+    unsafe impl Send for Job {}
+    unsafe impl Sync for Job {}
+
+    impl Job {
+        pub fn assign(child: &Child) -> Option<Self> {
+            // SAFETY: the handle is checked before use and owned by `Job`,
+            // which closes it; `info` outlives the call that reads it.
+            unsafe {
+                let handle = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+                if handle.is_null() {
+                    return None;
+                }
+                let job = Job(handle);
+                let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+                info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+                let configured = SetInformationJobObject(
+                    job.0,
+                    JobObjectExtendedLimitInformation,
+                    &info as *const _ as *const _,
+                    std::mem::size_of_val(&info) as u32,
+                );
+                if configured == 0 || AssignProcessToJobObject(job.0, child.as_raw_handle() as HANDLE) == 0 {
+                    return None;
+                }
+                Some(job)
+            }
+        }
+
+        pub fn terminate(&self) {
+            // SAFETY: the handle is valid until `drop`.
+            unsafe {
+                TerminateJobObject(self.0, 1);
+            }
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            // SAFETY: the handle is owned and closed exactly once.
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+}
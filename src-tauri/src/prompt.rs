@@ -13,4 +13,175 @@ pub struct Prompt {
     pub created_at: Option<i64>,
     #[serde(rename = "updatedAt", skip_serializing_if = "Option::is_none")]
     pub updated_at: Option<i64>,
+    /// Order among the enabled prompts composed into AGENTS.md.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Slash-separated, e.g. `team/backend`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder: Option<String>,
+}
+
+/// A directory whose own AGENTS.md is composed from prompts, separately
+/// from the global one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Project {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
+
+/// Who last wrote an AGENTS.md, as far as Open Switch can tell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AgentsFileState {
+    Missing,
+    /// Exactly what Open Switch last wrote.
+    Managed,
+    /// Written by Open Switch and edited since.
+    Modified,
+    /// Never written by Open Switch.
+    HandWritten,
+}
+
+/// How much of an AGENTS.md Open Switch writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AgentsMdMode {
+    /// The file is replaced with the enabled prompts.
+    WholeFile,
+    /// Only the text between `<!-- open-switch:begin -->` and
+    /// `<!-- open-switch:end -->` is written; the rest is left alone.
+    Region,
+}
+
+impl AgentsMdMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AgentsMdMode::WholeFile => "wholeFile",
+            AgentsMdMode::Region => "region",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "wholeFile" => Some(AgentsMdMode::WholeFile),
+            "region" => Some(AgentsMdMode::Region),
+            _ => None,
+        }
+    }
+}
+
+/// How to settle an AGENTS.md that was edited outside Open Switch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DriftResolution {
+    /// Copy the edits back into the prompts they belong to.
+    KeepFile,
+    /// Overwrite the file with the stored prompts.
+    KeepStored,
+    /// Save the file as a new prompt, then overwrite it with the stored ones.
+    SaveAsNew,
+}
+
+/// What produced a prompt revision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptSource {
+    /// Content that existed before version history was recorded.
+    Initial,
+    Ui,
+    /// Text taken from an AGENTS.md edited outside Open Switch.
+    Backfill,
+    Import,
+    Restore,
+    /// Pulled from the git working copy prompts are synced with.
+    Sync,
+}
+
+impl PromptSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PromptSource::Initial => "initial",
+            PromptSource::Ui => "ui",
+            PromptSource::Backfill => "backfill",
+            PromptSource::Import => "import",
+            PromptSource::Restore => "restore",
+            PromptSource::Sync => "sync",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "initial" => Some(PromptSource::Initial),
+            "ui" => Some(PromptSource::Ui),
+            "backfill" => Some(PromptSource::Backfill),
+            "import" => Some(PromptSource::Import),
+            "restore" => Some(PromptSource::Restore),
+            "sync" => Some(PromptSource::Sync),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptVersion {
+    pub id: i64,
+    #[serde(rename = "promptId")]
+    pub prompt_id: String,
+    /// Counts up from 1 within a prompt.
+    pub version: i64,
+    pub name: String,
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub source: PromptSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
+
+/// Delimiters SQLite puts around matches in highlights and snippets. They
+/// cannot occur in prompt text, unlike any printable marker.
+pub const HIGHLIGHT_START: char = '\u{2}';
+pub const HIGHLIGHT_END: char = '\u{3}';
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TextPart {
+    pub text: String,
+    pub highlight: bool,
+}
+
+/// Splits delimited text into plain and highlighted parts, so the UI never
+/// has to render markup from prompt content.
+pub fn highlight_parts(text: &str) -> Vec<TextPart> {
+    let mut parts = Vec::new();
+    let mut highlight = false;
+    for piece in text.split([HIGHLIGHT_START, HIGHLIGHT_END]) {
+        if !piece.is_empty() {
+            parts.push(TextPart {
+                text: piece.to_string(),
+                highlight,
+            });
+        }
+        highlight = !highlight;
+    }
+    parts
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptSearchHit {
+    pub prompt: Prompt,
+    /// The prompt name with matching terms highlighted.
+    pub name: Vec<TextPart>,
+    /// The best matching passage; empty when searching by filters only.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub snippet: Vec<TextPart>,
+    /// bm25 rank; lower is better.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
 }
@@ -0,0 +1,296 @@
+use crate::error::AppError;
+
+const MARKER_PREFIX: &str = "<!-- open-switch:fragment id=\"";
+const MARKER_SUFFIX: &str = "\" -->";
+const REGION_BEGIN: &str = "<!-- open-switch:begin -->";
+const REGION_END: &str = "<!-- open-switch:end -->";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fragment {
+    pub id: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SplitFile {
+    /// Text before the first marker, which belongs to no fragment.
+    pub preamble: String,
+    pub fragments: Vec<Fragment>,
+}
+
+fn marker(id: &str) -> String {
+    format!("{MARKER_PREFIX}{id}{MARKER_SUFFIX}")
+}
+
+fn parse_marker(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix(MARKER_PREFIX)?
+        .strip_suffix(MARKER_SUFFIX)
+        .filter(|id| !id.is_empty())
+}
+
+/// Builds AGENTS.md from the enabled prompts, in order. A single prompt is
+/// written as-is; several are each preceded by a marker line so edits to
+/// the file can be split back into the prompt they belong to.
+pub fn compose(fragments: &[Fragment]) -> String {
+    match fragments {
+        [] => String::new(),
+        [only] => only.content.clone(),
+        _ => fragments
+            .iter()
+            .map(|f| format!("{}\n{}\n", marker(&f.id), f.content.trim_end()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+/// Splits a composed file at its marker lines. Returns `None` when the file
+/// has no markers, i.e. it holds a single prompt or was written by hand.
+pub fn split(content: &str) -> Option<SplitFile> {
+    let mut file = SplitFile::default();
+    let mut current: Option<Fragment> = None;
+
+    for line in content.split_inclusive('\n') {
+        if let Some(id) = parse_marker(line) {
+            file.fragments.extend(current.take());
+            current = Some(Fragment {
+                id: id.to_string(),
+                content: String::new(),
+            });
+        } else if let Some(fragment) = current.as_mut() {
+            fragment.content.push_str(line);
+        } else {
+            file.preamble.push_str(line);
+        }
+    }
+    file.fragments.extend(current);
+
+    if file.fragments.is_empty() {
+        return None;
+    }
+    for fragment in &mut file.fragments {
+        fragment.content.truncate(fragment.content.trim_end().len());
+    }
+    Some(file)
+}
+
+/// Whether two versions of a fragment differ beyond trailing whitespace,
+/// which composing does not preserve.
+pub fn differs(a: &str, b: &str) -> bool {
+    a.trim_end() != b.trim_end()
+}
+
+/// An AGENTS.md split around the region Open Switch writes to.
+#[derive(Debug, Clone)]
+pub struct ManagedRegion {
+    pub before: String,
+    /// The text between the marker lines, without trailing whitespace.
+    pub inner: String,
+    pub after: String,
+}
+
+/// Finds the first `<!-- open-switch:begin -->` line and the
+/// `<!-- open-switch:end -->` line after it.
+pub fn split_region(content: &str) -> Option<ManagedRegion> {
+    let mut offset = 0;
+    let mut begin = None;
+    for line in content.split_inclusive('\n') {
+        let line_end = offset + line.len();
+        match (begin, line.trim()) {
+            (None, REGION_BEGIN) => begin = Some((offset, line_end)),
+            (Some((start, inner_start)), REGION_END) => {
+                return Some(ManagedRegion {
+                    before: content[..start].to_string(),
+                    inner: content[inner_start..offset].trim_end().to_string(),
+                    after: content[line_end..].to_string(),
+                });
+            }
+            _ => {}
+        }
+        offset = line_end;
+    }
+    None
+}
+
+/// Like `split_region`, but refuses a file whose marker lines are not a
+/// single begin/end pair: writing to it would leave a stray marker behind.
+pub fn paired_region(content: &str) -> Result<Option<ManagedRegion>, AppError> {
+    let markers = content
+        .lines()
+        .filter(|line| matches!(line.trim(), REGION_BEGIN | REGION_END))
+        .count();
+    match (split_region(content), markers) {
+        (None, 0) => Ok(None),
+        (Some(region), 2) => Ok(Some(region)),
+        _ => Err(AppError::InvalidInput(format!(
+            "AGENTS.md has unpaired '{REGION_BEGIN}' and '{REGION_END}' lines; fix or remove them first"
+        ))),
+    }
+}
+
+/// Puts `inner` between the region markers, keeping the text around them.
+/// A file without markers gets the region appended.
+pub fn with_region(content: &str, inner: &str) -> Result<String, AppError> {
+    let region = format!("{REGION_BEGIN}\n{}\n{REGION_END}\n", inner.trim_end());
+    Ok(match paired_region(content)? {
+        Some(file) => format!("{}{region}{}", file.before, file.after),
+        None if content.trim().is_empty() => region,
+        None => format!("{}\n\n{region}", content.trim_end()),
+    })
+}
+
+/// Puts markers around the first whole-line occurrence of `inner`, leaving
+/// the rest of the file as it is. Returns `None` when `inner` is not there.
+pub fn wrap_region(content: &str, inner: &str) -> Option<String> {
+    let inner = inner.trim_end();
+    if inner.is_empty() {
+        return None;
+    }
+    content.match_indices(inner).find_map(|(start, _)| {
+        let before = &content[..start];
+        let after = &content[start + inner.len()..];
+        let rest = match after.strip_prefix("\r\n").or_else(|| after.strip_prefix('\n')) {
+            Some(rest) => rest,
+            None if after.is_empty() => after,
+            None => return None,
+        };
+        (before.is_empty() || before.ends_with('\n'))
+            .then(|| format!("{before}{REGION_BEGIN}\n{inner}\n{REGION_END}\n{rest}"))
+    })
+}
+
+/// Removes the region. Returns `None` when nothing else is left.
+pub fn without_region(content: &str) -> Result<Option<String>, AppError> {
+    let rest = match paired_region(content)? {
+        Some(file) => match (file.before.trim_end(), file.after.trim_start()) {
+            ("", after) => after.to_string(),
+            (before, "") => format!("{before}\n"),
+            (before, after) => format!("{before}\n\n{after}"),
+        },
+        None => content.to_string(),
+    };
+    Ok((!rest.trim().is_empty()).then_some(rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fragment(id: &str, content: &str) -> Fragment {
+        Fragment {
+            id: id.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn writes_a_single_prompt_without_markers() {
+        let composed = compose(&[fragment("a", "# Rules\n\nBe brief.\n")]);
+        assert_eq!(composed, "# Rules\n\nBe brief.\n");
+        assert!(split(&composed).is_none());
+        assert_eq!(compose(&[]), "");
+    }
+
+    #[test]
+    fn splits_composed_fragments_back_apart() {
+        let fragments = vec![
+            fragment("a", "# First\n\nOne."),
+            fragment("b", "# Second\n\n<!-- a comment -->\nTwo."),
+        ];
+        let composed = compose(&fragments);
+        assert!(composed.starts_with("<!-- open-switch:fragment id=\"a\" -->\n"));
+
+        let file = split(&composed).unwrap();
+        assert_eq!(file.preamble, "");
+        assert_eq!(file.fragments, fragments);
+    }
+
+    #[test]
+    fn keeps_text_above_the_first_marker_as_preamble() {
+        let content = "Notes\n\n<!-- open-switch:fragment id=\"a\" -->\nOne.\n  \n";
+        let file = split(content).unwrap();
+        assert_eq!(file.preamble, "Notes\n\n");
+        assert_eq!(file.fragments, vec![fragment("a", "One.")]);
+    }
+
+    #[test]
+    fn ignores_markers_without_an_id() {
+        let content = "<!-- open-switch:fragment id=\"\" -->\nOne.\n";
+        assert!(split(content).is_none());
+    }
+
+    #[test]
+    fn compares_fragments_up_to_trailing_whitespace() {
+        assert!(!differs("One.\n\n", "One."));
+        assert!(differs(" One.", "One."));
+        assert!(differs("One.", "Two."));
+    }
+
+    #[test]
+    fn finds_the_region_between_markers() {
+        let content = "Mine\n<!-- open-switch:begin -->\nOurs\n\n<!-- open-switch:end -->\nAlso mine\n";
+        let region = split_region(content).unwrap();
+        assert_eq!(region.before, "Mine\n");
+        assert_eq!(region.inner, "Ours");
+        assert_eq!(region.after, "Also mine\n");
+
+        assert!(split_region("Mine\n").is_none());
+        assert!(split_region("<!-- open-switch:begin -->\nOurs\n").is_none());
+        assert!(split_region("<!-- open-switch:end -->\n<!-- open-switch:begin -->\n").is_none());
+    }
+
+    #[test]
+    fn replaces_appends_and_removes_the_region() {
+        let content = "Mine\n<!-- open-switch:begin -->\nOld\n<!-- open-switch:end -->\nAfter\n";
+        assert_eq!(
+            with_region(content, "New\n").unwrap(),
+            "Mine\n<!-- open-switch:begin -->\nNew\n<!-- open-switch:end -->\nAfter\n"
+        );
+        assert_eq!(
+            with_region("Mine\n", "New").unwrap(),
+            "Mine\n\n<!-- open-switch:begin -->\nNew\n<!-- open-switch:end -->\n"
+        );
+        assert_eq!(
+            with_region("", "New").unwrap(),
+            "<!-- open-switch:begin -->\nNew\n<!-- open-switch:end -->\n"
+        );
+
+        assert_eq!(without_region(content).unwrap().as_deref(), Some("Mine\n\nAfter\n"));
+        assert_eq!(
+            without_region("<!-- open-switch:begin -->\nOld\n<!-- open-switch:end -->\n").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn refuses_to_write_around_unpaired_markers() {
+        let unclosed = "Mine\n<!-- open-switch:begin -->\nOld\n";
+        let extra = "<!-- open-switch:begin -->\nOld\n<!-- open-switch:end -->\n<!-- open-switch:end -->\n";
+        let reversed = "<!-- open-switch:end -->\nOld\n<!-- open-switch:begin -->\n";
+        for content in [unclosed, extra, reversed] {
+            assert!(with_region(content, "New").is_err(), "{content}");
+            assert!(without_region(content).is_err(), "{content}");
+        }
+    }
+
+    #[test]
+    fn wraps_the_written_text_in_place() {
+        let content = "# Mine\n\nOne.\nTwo.\n\nMore of mine\n";
+        assert_eq!(
+            wrap_region(content, "One.\nTwo.\n").unwrap(),
+            "# Mine\n\n<!-- open-switch:begin -->\nOne.\nTwo.\n<!-- open-switch:end -->\n\nMore of mine\n"
+        );
+        assert_eq!(
+            wrap_region("One.\nTwo.", "One.\nTwo.").unwrap(),
+            "<!-- open-switch:begin -->\nOne.\nTwo.\n<!-- open-switch:end -->\n"
+        );
+        // Only whole lines count.
+        assert_eq!(
+            wrap_region("Not One.\nOne.\n", "One.").unwrap(),
+            "Not One.\n<!-- open-switch:begin -->\nOne.\n<!-- open-switch:end -->\n"
+        );
+        assert!(wrap_region("One. Two.\n", "One.").is_none());
+        assert!(wrap_region("Mine\n", "One.").is_none());
+        assert!(wrap_region("Mine\n", "").is_none());
+    }
+}
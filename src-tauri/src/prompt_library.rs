@@ -0,0 +1,495 @@
+use crate::prompt::Prompt;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+
+const FENCE: &str = "---";
+
+/// SHA-256 of the content with line endings normalized and trailing
+/// whitespace dropped, so a checkout that converts to CRLF or an editor
+/// that adds a final newline does not count as a change.
+pub fn content_hash(content: &str) -> String {
+    let normalized = content.replace("\r\n", "\n");
+    Sha256::digest(normalized.trim_end().as_bytes())
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/// A prompt as read from a library file. Everything but the content is
+/// optional, since files may be written by hand.
+#[derive(Debug, Clone, Default)]
+pub struct LibraryEntry {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub folder: Option<String>,
+    pub created_at: Option<i64>,
+    pub updated_at: Option<i64>,
+    /// The hash recorded at export. When it no longer matches the body, the
+    /// file was edited after it was written.
+    pub content_hash: Option<String>,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedPrompt {
+    pub id: String,
+    pub file: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryExport {
+    pub directory: String,
+    pub exported: Vec<ExportedPrompt>,
+    /// Ids that were selected but do not exist.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LibraryAction {
+    Create,
+    Update,
+    Skip,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryImportItem {
+    pub file: String,
+    pub action: LibraryAction,
+    #[serde(rename = "promptId", skip_serializing_if = "Option::is_none")]
+    pub prompt_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryImport {
+    pub directory: String,
+    /// Nothing was written; the items say what an import would do.
+    #[serde(rename = "dryRun")]
+    pub dry_run: bool,
+    pub items: Vec<LibraryImportItem>,
+}
+
+// ============== Writing ==============
+
+fn quote(value: &str) -> String {
+    // A JSON string is also a valid double-quoted YAML scalar.
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn timestamp(secs: i64) -> Option<String> {
+    DateTime::<Utc>::from_timestamp(secs, 0)
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// Renders a prompt as Markdown with YAML front matter.
+pub fn to_markdown(prompt: &Prompt) -> String {
+    let mut out = format!("{FENCE}\nid: {}\nname: {}\n", quote(&prompt.id), quote(&prompt.name));
+    if let Some(description) = prompt.description.as_deref().filter(|d| !d.is_empty()) {
+        let _ = writeln!(out, "description: {}", quote(description));
+    }
+    if !prompt.tags.is_empty() {
+        let tags: Vec<String> = prompt.tags.iter().map(|t| quote(t)).collect();
+        let _ = writeln!(out, "tags: [{}]", tags.join(", "));
+    }
+    if let Some(folder) = &prompt.folder {
+        let _ = writeln!(out, "folder: {}", quote(folder));
+    }
+    if let Some(created) = prompt.created_at.and_then(timestamp) {
+        let _ = writeln!(out, "createdAt: {created}");
+    }
+    if let Some(updated) = prompt.updated_at.and_then(timestamp) {
+        let _ = writeln!(out, "updatedAt: {updated}");
+    }
+    let _ = writeln!(out, "contentHash: {}", content_hash(&prompt.content));
+    let _ = write!(out, "{FENCE}\n\n{}", prompt.content);
+    if !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+/// Identifies what a prompt looks like in a library file, leaving out
+/// timestamps and state that only matters on this machine.
+pub fn fingerprint(prompt: &Prompt) -> String {
+    content_hash(&to_markdown(&Prompt {
+        enabled: false,
+        created_at: None,
+        updated_at: None,
+        position: None,
+        ..prompt.clone()
+    }))
+}
+
+/// A file name for the prompt, from its name: `Code Review` becomes
+/// `code-review.md`.
+pub fn file_stem(prompt: &Prompt) -> String {
+    let mut stem = String::new();
+    for c in prompt.name.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            stem.push(c);
+        } else if !stem.is_empty() && !stem.ends_with('-') {
+            stem.push('-');
+        }
+        if stem.chars().count() >= 60 {
+            break;
+        }
+    }
+    let stem = stem.trim_end_matches('-');
+    if stem.is_empty() {
+        "prompt".to_string()
+    } else {
+        stem.to_string()
+    }
+}
+
+// ============== Reading ==============
+
+enum Value {
+    Null,
+    Scalar(String),
+    List(Vec<String>),
+    /// Indented lines that are not a list, e.g. a nested mapping.
+    Nested,
+}
+
+fn unquote(raw: &str) -> Result<String, String> {
+    if raw.starts_with('"') {
+        serde_json::from_str(raw).map_err(|_| format!("invalid quoted string {raw}"))
+    } else if let Some(inner) = raw.strip_prefix('\'') {
+        inner
+            .strip_suffix('\'')
+            .map(|inner| inner.replace("''", "'"))
+            .ok_or_else(|| format!("unclosed quote in {raw}"))
+    } else {
+        Ok(raw.to_string())
+    }
+}
+
+/// Drops a trailing ` # comment` outside of quotes.
+fn strip_comment(raw: &str) -> &str {
+    let mut quote = None;
+    let mut prev = ' ';
+    for (i, c) in raw.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q && !(q == '"' && prev == '\\') => quote = None,
+            (None, '#') if prev.is_whitespace() => return raw[..i].trim_end(),
+            _ => {}
+        }
+        prev = c;
+    }
+    raw.trim_end()
+}
+
+/// Splits `a, "b, c", 'd'` at commas outside of quotes.
+fn split_flow(raw: &str) -> Result<Vec<String>, String> {
+    let mut items = Vec::new();
+    let mut quote = None;
+    let mut prev = ' ';
+    let mut start = 0;
+    for (i, c) in raw.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q && !(q == '"' && prev == '\\') => quote = None,
+            (None, ',') => {
+                items.push(&raw[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        prev = c;
+    }
+    items.push(&raw[start..]);
+    items
+        .into_iter()
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(unquote)
+        .collect()
+}
+
+fn parse_value(raw: &str) -> Result<Value, String> {
+    let raw = strip_comment(raw.trim());
+    if matches!(raw, "" | "~" | "null") {
+        return Ok(Value::Null);
+    }
+    if let Some(inner) = raw.strip_prefix('[') {
+        let inner = inner
+            .strip_suffix(']')
+            .ok_or_else(|| format!("unclosed list {raw}"))?;
+        return split_flow(inner).map(Value::List);
+    }
+    unquote(raw).map(Value::Scalar)
+}
+
+/// Reads the small part of YAML that front matter uses: top-level
+/// `key: value` pairs with plain or quoted scalars, flow (`[a, b]`) and
+/// block (`- a`) lists, and `|`/`>` block text.
+fn parse_front_matter(lines: &[&str]) -> Result<Vec<(String, Value)>, String> {
+    let mut pairs = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        if line.starts_with([' ', '\t']) {
+            return Err(format!("unexpected indented line '{}'", line.trim()));
+        }
+        let (key, rest) = line
+            .split_once(':')
+            .ok_or_else(|| format!("expected 'key: value', found '{line}'"))?;
+        let key = key.trim().to_string();
+
+        // Indented lines that follow belong to this key.
+        let start = i;
+        while i < lines.len() && (lines[i].trim().is_empty() || lines[i].starts_with([' ', '\t'])) {
+            i += 1;
+        }
+        let nested = &lines[start..i];
+
+        let rest = rest.trim();
+        let value = match rest.chars().next() {
+            Some(style @ ('|' | '>')) => {
+                let indent = nested
+                    .iter()
+                    .filter(|l| !l.trim().is_empty())
+                    .map(|l| l.len() - l.trim_start().len())
+                    .min()
+                    .unwrap_or(0);
+                let text: Vec<&str> = nested
+                    .iter()
+                    .map(|l| l.get(indent..).unwrap_or("").trim_end())
+                    .collect();
+                let mut text = if style == '|' {
+                    text.join("\n")
+                } else {
+                    text.join(" ")
+                };
+                text.truncate(text.trim_end().len());
+                Value::Scalar(text)
+            }
+            None => {
+                let items: Vec<&str> = nested
+                    .iter()
+                    .map(|l| l.trim())
+                    .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                    .collect();
+                if items.is_empty() {
+                    Value::Null
+                } else if let Some(items) = items
+                    .iter()
+                    .map(|l| l.strip_prefix('-'))
+                    .collect::<Option<Vec<_>>>()
+                {
+                    Value::List(
+                        items
+                            .into_iter()
+                            .map(|item| unquote(strip_comment(item.trim())))
+                            .collect::<Result<_, _>>()?,
+                    )
+                } else {
+                    Value::Nested
+                }
+            }
+            _ => parse_value(rest)?,
+        };
+        pairs.push((key, value));
+    }
+    Ok(pairs)
+}
+
+fn parse_timestamp(key: &str, value: &str) -> Result<i64, String> {
+    value
+        .parse::<i64>()
+        .ok()
+        .or_else(|| DateTime::parse_from_rfc3339(value).ok().map(|t| t.timestamp()))
+        .ok_or_else(|| format!("'{key}' is not a date: {value}"))
+}
+
+/// Parses a library file. A file without front matter is read as content
+/// only.
+pub fn parse_markdown(text: &str) -> Result<LibraryEntry, String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    if lines.first().map(|l| l.trim_end()) != Some(FENCE) {
+        return Ok(LibraryEntry {
+            content: text.trim_end().replace("\r\n", "\n"),
+            ..LibraryEntry::default()
+        });
+    }
+    let end = lines
+        .iter()
+        .skip(1)
+        .position(|l| matches!(l.trim_end(), FENCE | "..."))
+        .map(|i| i + 1)
+        .ok_or("front matter is not closed with '---'")?;
+    let header: Vec<&str> = lines[1..end].iter().map(|l| l.trim_end_matches(['\r', '\n'])).collect();
+
+    let body = lines[end + 1..].concat();
+    let body = body
+        .strip_prefix("\r\n")
+        .or_else(|| body.strip_prefix('\n'))
+        .unwrap_or(&body);
+    let mut entry = LibraryEntry {
+        content: body.trim_end().replace("\r\n", "\n"),
+        ..LibraryEntry::default()
+    };
+
+    const KEYS: &[&str] = &[
+        "id",
+        "name",
+        "description",
+        "tags",
+        "folder",
+        "createdAt",
+        "updatedAt",
+        "contentHash",
+    ];
+    for (key, value) in parse_front_matter(&header)? {
+        if !KEYS.contains(&key.as_str()) {
+            continue;
+        }
+        let scalar = match value {
+            Value::Null => continue,
+            Value::List(items) if key == "tags" => {
+                entry.tags = items;
+                continue;
+            }
+            Value::List(_) | Value::Nested => {
+                return Err(format!("'{key}' must be a single value"));
+            }
+            Value::Scalar(scalar) => scalar,
+        };
+        match key.as_str() {
+            "id" => entry.id = Some(scalar),
+            "name" => entry.name = Some(scalar),
+            "description" => entry.description = Some(scalar),
+            "folder" => entry.folder = Some(scalar),
+            // `tags: a, b` is a common slip for a list.
+            "tags" => entry.tags = scalar.split(',').map(|t| t.trim().to_string()).collect(),
+            "createdAt" => entry.created_at = Some(parse_timestamp(&key, &scalar)?),
+            "updatedAt" => entry.updated_at = Some(parse_timestamp(&key, &scalar)?),
+            "contentHash" => entry.content_hash = Some(scalar),
+            _ => {}
+        }
+    }
+    entry.id = entry.id.map(|id| id.trim().to_string()).filter(|id| !id.is_empty());
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompt(name: &str, content: &str) -> Prompt {
+        Prompt {
+            id: "review".to_string(),
+            name: name.to_string(),
+            content: content.to_string(),
+            description: None,
+            enabled: false,
+            created_at: None,
+            updated_at: None,
+            position: None,
+            tags: Vec::new(),
+            folder: None,
+        }
+    }
+
+    #[test]
+    fn round_trips_through_markdown() {
+        let prompt = Prompt {
+            description: Some("Checks \"diffs\": carefully # not a comment".to_string()),
+            created_at: Some(1_700_000_000),
+            updated_at: Some(1_700_003_600),
+            tags: vec!["code".to_string(), "a, b".to_string()],
+            folder: Some("team/backend".to_string()),
+            ..prompt("Code Review", "---\n# Review\n\nLook at: {{team}}\n")
+        };
+        let entry = parse_markdown(&to_markdown(&prompt)).unwrap();
+
+        assert_eq!(entry.id.as_deref(), Some("review"));
+        assert_eq!(entry.name.as_deref(), Some("Code Review"));
+        assert_eq!(entry.description, prompt.description);
+        assert_eq!(entry.tags, prompt.tags);
+        assert_eq!(entry.folder, prompt.folder);
+        assert_eq!(entry.created_at, prompt.created_at);
+        assert_eq!(entry.updated_at, prompt.updated_at);
+        assert_eq!(entry.content, prompt.content.trim_end());
+        assert_eq!(entry.content_hash, Some(content_hash(&entry.content)));
+    }
+
+    #[test]
+    fn reads_hand_written_front_matter() {
+        let text = "\u{feff}---\r\n\
+            # a comment\r\n\
+            name: 'It''s mine'   # trailing comment\r\n\
+            tags:\r\n  - one\r\n  - \"two\"\r\n\
+            description: |\r\n  First line\r\n  Second line\r\n\
+            createdAt: 2024-01-02T03:04:05Z\r\n\
+            extra:\r\n  nested: true\r\n\
+            ---\r\n\r\nBody\r\ntext\r\n\r\n";
+        let entry = parse_markdown(text).unwrap();
+
+        assert_eq!(entry.id, None);
+        assert_eq!(entry.name.as_deref(), Some("It's mine"));
+        assert_eq!(entry.tags, vec!["one", "two"]);
+        assert_eq!(entry.description.as_deref(), Some("First line\nSecond line"));
+        assert_eq!(entry.created_at, Some(1_704_164_645));
+        assert_eq!(entry.content, "Body\ntext");
+    }
+
+    #[test]
+    fn reads_files_without_front_matter_as_content() {
+        let entry = parse_markdown("# Just text\n\n").unwrap();
+        assert_eq!(entry.name, None);
+        assert_eq!(entry.content, "# Just text");
+    }
+
+    #[test]
+    fn rejects_malformed_front_matter() {
+        assert!(parse_markdown("---\nname: x\n").is_err());
+        assert!(parse_markdown("---\nname: [a, b]\n---\n").is_err());
+        assert!(parse_markdown("---\ncreatedAt: yesterday\n---\n").is_err());
+        assert!(parse_markdown("---\nname: \"open\n---\n").is_err());
+    }
+
+    #[test]
+    fn ignores_line_endings_and_trailing_whitespace_in_hashes() {
+        assert_eq!(content_hash("a\r\nb\n\n"), content_hash("a\nb"));
+        assert_ne!(content_hash("a\nb"), content_hash("a b"));
+    }
+
+    #[test]
+    fn fingerprints_ignore_local_state() {
+        let base = prompt("Review", "Body");
+        let local = Prompt {
+            enabled: true,
+            position: Some(3),
+            updated_at: Some(1_700_000_000),
+            ..base.clone()
+        };
+        assert_eq!(fingerprint(&base), fingerprint(&local));
+        assert_ne!(fingerprint(&base), fingerprint(&prompt("Review", "Other")));
+    }
+
+    #[test]
+    fn derives_file_names_from_prompt_names() {
+        assert_eq!(file_stem(&prompt("Code Review", "")), "code-review");
+        assert_eq!(file_stem(&prompt("  C++ / Rust!  ", "")), "c-rust");
+        assert_eq!(file_stem(&prompt("???", "")), "prompt");
+        assert_eq!(file_stem(&prompt(&"x".repeat(100), "")).len(), 60);
+    }
+}
@@ -1,8 +1,25 @@
 use crate::database::Database;
+use crate::diff::{self, TextDiff};
 use crate::error::AppError;
-use crate::prompt::Prompt;
+use crate::mcp_service::normalize_tags;
+use crate::prompt::{
+    AgentsFileState, AgentsMdMode, DriftResolution, Project, Prompt, PromptSearchHit, PromptSource, PromptVersion,
+};
+use crate::prompt_fragments::{self, Fragment};
+use crate::prompt_library::{
+    self, ExportedPrompt, LibraryAction, LibraryEntry, LibraryExport, LibraryImport,
+    LibraryImportItem,
+};
+use crate::prompt_sync::{
+    self, Git, PromptSyncConfig, PromptSyncStatus, SyncAction, SyncConflict, SyncConflictKind,
+    SyncReport, SyncResolution, SyncedPrompt,
+};
+use crate::prompt_template::{self, Renderer};
+use crate::substitution::resolve_file_path;
 use indexmap::IndexMap;
-use std::path::PathBuf;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 fn get_unix_timestamp() -> Result<i64, AppError> {
@@ -31,6 +48,269 @@ fn write_text_file(path: &PathBuf, content: &str) -> Result<(), AppError> {
     Ok(())
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptVersionDiff {
+    pub from: PromptVersion,
+    pub to: PromptVersion,
+    #[serde(flatten)]
+    pub diff: TextDiff,
+}
+
+/// An AGENTS.md edited since Open Switch last wrote it, in a way that
+/// rewriting it would lose.
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptDrift {
+    pub path: String,
+    /// Enabled prompts whose part of the file was edited.
+    pub prompts: Vec<String>,
+    /// Text in the file that belongs to no enabled prompt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unassigned: Option<String>,
+    /// From what was written to what the file holds now.
+    #[serde(flatten)]
+    pub diff: TextDiff,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectStatus {
+    #[serde(flatten)]
+    pub project: Project,
+    #[serde(rename = "agentsMd")]
+    pub agents_md: AgentsFileState,
+    /// Enabled prompt ids, in composition order.
+    pub prompts: Vec<String>,
+}
+
+/// An AGENTS.md that enabled prompts are composed into.
+enum Target {
+    Global,
+    Project(Project),
+}
+
+impl Target {
+    /// Key for the written output; the global file is "".
+    fn key(&self) -> &str {
+        match self {
+            Target::Global => "",
+            Target::Project(project) => &project.id,
+        }
+    }
+
+    fn path(&self) -> Result<PathBuf, AppError> {
+        match self {
+            Target::Global => get_agents_md_path(),
+            Target::Project(project) => Ok(Path::new(&project.path).join("AGENTS.md")),
+        }
+    }
+}
+
+const AUTO_BACKFILL: &str = "prompt.auto_backfill";
+const AGENTS_MD_MODE: &str = "prompt.agents_md_mode";
+
+/// Edits found in a target's AGENTS.md.
+struct FileEdits {
+    /// The part of the file Open Switch writes.
+    managed: String,
+    /// What was last written there, composed.
+    expected: String,
+    /// The file's text without markers, in order.
+    texts: Vec<String>,
+    /// Enabled prompts with the text the file now holds for them.
+    edited: Vec<(Prompt, String)>,
+    unassigned: Vec<String>,
+}
+
+impl FileEdits {
+    fn is_empty(&self) -> bool {
+        self.edited.is_empty() && self.unassigned.is_empty()
+    }
+}
+
+/// Trims each segment and drops empty ones: ` /team//backend/ ` becomes
+/// `team/backend`.
+fn normalize_folder(folder: &str) -> Option<String> {
+    let segments: Vec<&str> = folder
+        .split('/')
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    (!segments.is_empty()).then(|| segments.join("/"))
+}
+
+/// Turns free text into an FTS5 query: every word must match, the last one
+/// as a prefix so results follow typing. Quoting each word keeps FTS5
+/// operators and punctuation from being parsed; words with nothing to
+/// index are dropped.
+fn fts_query(input: &str) -> Option<String> {
+    let words: Vec<String> = input
+        .split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect();
+    let (last, rest) = words.split_last()?;
+    Some(rest.iter().chain([&format!("{last}*")]).cloned().collect::<Vec<_>>().join(" "))
+}
+
+/// A library file name with its parsed entry, or why it could not be read.
+type LibraryFile = (String, Result<LibraryEntry, String>);
+
+/// The `.md` files in a library directory, sorted by name.
+fn read_library(dir: &Path) -> Result<Vec<LibraryFile>, AppError> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(|e| AppError::io(dir, e))? {
+        let path = entry.map_err(|e| AppError::io(dir, e))?.path();
+        let is_markdown = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("md"));
+        if !is_markdown || !path.is_file() {
+            continue;
+        }
+        let file = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let entry = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| prompt_library::parse_markdown(&text));
+        files.push((file, entry));
+    }
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files)
+}
+
+/// Which file of a library directory holds each prompt, and the names in
+/// use, lowercased since file systems may ignore case.
+#[derive(Default)]
+struct LibraryNames {
+    by_id: HashMap<String, String>,
+    taken: HashSet<String>,
+}
+
+impl LibraryNames {
+    fn read(dir: &Path) -> Result<Self, AppError> {
+        let mut names = LibraryNames::default();
+        if !dir.is_dir() {
+            return Ok(names);
+        }
+        for (file, entry) in read_library(dir)? {
+            if let Ok(LibraryEntry { id: Some(id), .. }) = entry {
+                names.by_id.entry(id).or_insert_with(|| file.clone());
+            }
+            names.taken.insert(file.to_lowercase());
+        }
+        Ok(names)
+    }
+
+    /// An unused name for the prompt: `code-review.md`, then
+    /// `code-review-2.md` and so on.
+    fn claim(&mut self, prompt: &Prompt) -> String {
+        let stem = prompt_library::file_stem(prompt);
+        let file = (1..)
+            .map(|n| match n {
+                1 => format!("{stem}.md"),
+                n => format!("{stem}-{n}.md"),
+            })
+            .find(|file| !self.taken.contains(&file.to_lowercase()))
+            .unwrap_or_default();
+        self.taken.insert(file.to_lowercase());
+        file
+    }
+}
+
+/// Copies what a library file describes onto a prompt. Content that only
+/// differs in line endings or trailing whitespace is kept as stored.
+fn apply_entry(prompt: &mut Prompt, entry: LibraryEntry) {
+    if prompt_library::content_hash(&prompt.content) != prompt_library::content_hash(&entry.content) {
+        prompt.content = entry.content;
+    }
+    if let Some(name) = entry.name.filter(|n| !n.trim().is_empty()) {
+        prompt.name = name;
+    }
+    prompt.description = entry.description.filter(|d| !d.is_empty());
+    prompt.tags = normalize_tags(entry.tags);
+    prompt.folder = entry.folder.as_deref().and_then(normalize_folder);
+}
+
+/// A prompt that only exists in a library file so far.
+fn prompt_from_entry(id: String, name: String, entry: LibraryEntry, timestamp: i64) -> Prompt {
+    let mut prompt = Prompt {
+        id,
+        name,
+        content: String::new(),
+        description: None,
+        enabled: false,
+        created_at: Some(entry.created_at.unwrap_or(timestamp)),
+        updated_at: Some(entry.updated_at.unwrap_or(timestamp)),
+        position: None,
+        tags: Vec::new(),
+        folder: None,
+    };
+    apply_entry(&mut prompt, entry);
+    prompt
+}
+
+const SYNC_PATH: &str = "prompt_sync.path";
+const SYNC_DIRECTORY: &str = "prompt_sync.directory";
+const SYNC_LAST: &str = "prompt_sync.last_synced_at";
+
+/// A prompt file as of the upstream branch, read as the prompt it would
+/// become here.
+struct RemoteFile {
+    file: String,
+    prompt: Prompt,
+}
+
+struct SyncItem {
+    action: SyncAction,
+    local: Option<Prompt>,
+    remote: Option<RemoteFile>,
+    /// The repository's file, or the one it was in at the last sync.
+    file: Option<String>,
+    /// The repository's version could not be saved here.
+    failed: bool,
+}
+
+impl SyncItem {
+    fn id(&self) -> Option<&str> {
+        self.local
+            .as_ref()
+            .map(|p| p.id.as_str())
+            .or(self.remote.as_ref().map(|r| r.prompt.id.as_str()))
+    }
+
+    /// Whether the repository keeps its version of the file for now.
+    fn is_held(&self) -> bool {
+        self.failed || matches!(self.action, SyncAction::Conflict(_))
+    }
+}
+
+#[derive(Default)]
+struct SyncPlan {
+    upstream: String,
+    items: Vec<SyncItem>,
+    errors: Vec<String>,
+}
+
+impl SyncPlan {
+    fn conflicts(&self) -> Vec<SyncConflict> {
+        self.items
+            .iter()
+            .filter_map(|item| {
+                let SyncAction::Conflict(kind) = item.action else {
+                    return None;
+                };
+                let prompt = item.local.as_ref().or(item.remote.as_ref().map(|r| &r.prompt))?;
+                Some(SyncConflict {
+                    prompt_id: prompt.id.clone(),
+                    name: prompt.name.clone(),
+                    file: item.file.clone().unwrap_or_default(),
+                    kind,
+                })
+            })
+            .collect()
+    }
+}
+
 pub struct PromptService;
 
 impl PromptService {
@@ -38,13 +318,37 @@ impl PromptService {
         db.get_prompts()
     }
 
-    pub fn upsert_prompt(db: &Arc<Database>, prompt: Prompt) -> Result<(), AppError> {
-        let is_enabled = prompt.enabled;
-        db.save_prompt(&prompt)?;
+    /// Saves a prompt edited in the UI. Whether and where it is enabled is
+    /// kept as stored; only the enable and reorder commands change that.
+    /// The form sends no creation time for edits, so the stored one stays.
+    pub fn upsert_prompt(db: &Arc<Database>, mut prompt: Prompt) -> Result<(), AppError> {
+        prompt.tags = normalize_tags(prompt.tags);
+        prompt.folder = prompt.folder.as_deref().and_then(normalize_folder);
+        let stored = db.get_prompts()?.shift_remove(&prompt.id);
+        prompt.enabled = stored.as_ref().is_some_and(|p| p.enabled);
+        prompt.position = stored.as_ref().and_then(|p| p.position);
+        prompt.created_at = prompt.created_at.or(stored.as_ref().and_then(|p| p.created_at));
+        let verb = if stored.is_some() { "Update" } else { "Add" };
+        Self::save(db, &prompt, PromptSource::Ui)?;
+        Self::commit_to_sync(db, &format!("{verb} prompt '{}'", prompt.name));
+        Ok(())
+    }
+
+    /// Saves a prompt and rewrites every AGENTS.md it is composed into.
+    fn save(db: &Arc<Database>, prompt: &Prompt, source: PromptSource) -> Result<(), AppError> {
+        let was_enabled = db.get_prompts()?.get(&prompt.id).is_some_and(|p| p.enabled);
+        let targets = Self::targets_using(db, &prompt.id, prompt.enabled || was_enabled)?;
+        if !targets.is_empty() {
+            Self::render_with(db, prompt)?;
+            // Hand edits to the other fragments must not be lost when the files are rewritten.
+            for target in &targets {
+                Self::before_rewrite(db, target)?;
+            }
+        }
+        db.save_prompt(prompt, source, prompt_template::user_name().as_deref())?;
 
-        if is_enabled {
-            let target_path = get_agents_md_path()?;
-            write_text_file(&target_path, &prompt.content)?;
+        for target in &targets {
+            Self::write_enabled(db, target)?;
         }
 
         Ok(())
@@ -58,70 +362,670 @@ impl PromptService {
                 return Err(AppError::InvalidInput("Cannot delete enabled prompt".to_string()));
             }
         }
+        if !db.get_prompt_project_ids(id)?.is_empty() {
+            return Err(AppError::InvalidInput(
+                "Cannot delete a prompt enabled in a project".to_string(),
+            ));
+        }
 
         db.delete_prompt(id)?;
+        if let Some(prompt) = prompts.get(id) {
+            Self::commit_to_sync(db, &format!("Delete prompt '{}'", prompt.name));
+        }
         Ok(())
     }
 
-    pub fn enable_prompt(db: &Arc<Database>, id: &str) -> Result<(), AppError> {
-        let target_path = get_agents_md_path()?;
-        
-        if target_path.exists() {
-            if let Ok(live_content) = std::fs::read_to_string(&target_path) {
-                if !live_content.trim().is_empty() {
-                    let prompts = db.get_prompts()?;
-
-                    if let Some((enabled_id, enabled_prompt)) = prompts
-                        .iter()
-                        .find(|(_, p)| p.enabled)
-                        .map(|(id, p)| (id.clone(), p.clone()))
-                    {
-                        let timestamp = get_unix_timestamp()?;
-                        let mut updated_prompt = enabled_prompt;
-                        updated_prompt.content = live_content.clone();
-                        updated_prompt.updated_at = Some(timestamp);
-                        log::info!("Backfill live content to enabled prompt: {enabled_id}");
-                        db.save_prompt(&updated_prompt)?;
-                    } else {
-                        let content_exists = prompts
-                            .values()
-                            .any(|p| p.content.trim() == live_content.trim());
-                        if !content_exists {
-                            let timestamp = get_unix_timestamp()?;
-                            let backup_id = format!("backup-{timestamp}");
-                            let backup_prompt = Prompt {
-                                id: backup_id.clone(),
-                                name: format!(
-                                    "Original Prompt {}",
-                                    chrono::Local::now().format("%Y-%m-%d %H:%M")
-                                ),
-                                content: live_content,
-                                description: Some("Auto-backup of original prompt".to_string()),
-                                enabled: false,
-                                created_at: Some(timestamp),
-                                updated_at: Some(timestamp),
-                            };
-                            log::info!("Create backup prompt: {backup_id}");
-                            db.save_prompt(&backup_prompt)?;
+    fn target(db: &Arc<Database>, project_id: Option<&str>) -> Result<Target, AppError> {
+        let Some(project_id) = project_id else {
+            return Ok(Target::Global);
+        };
+        db.get_projects()?
+            .into_iter()
+            .find(|p| p.id == project_id)
+            .map(Target::Project)
+            .ok_or_else(|| AppError::InvalidInput(format!("Project {project_id} not found")))
+    }
+
+    /// The prompts composed into a target, in order.
+    fn enabled_prompts(db: &Arc<Database>, target: &Target) -> Result<Vec<Prompt>, AppError> {
+        match target {
+            Target::Global => db.get_enabled_prompts(),
+            Target::Project(project) => {
+                let mut prompts = db.get_prompts()?;
+                Ok(db
+                    .get_project_prompt_ids(&project.id)?
+                    .iter()
+                    .filter_map(|id| prompts.shift_remove(id))
+                    .collect())
+            }
+        }
+    }
+
+    /// Targets that `prompt_id` is written to. Whether it is enabled
+    /// globally is passed in, since callers may be about to change that.
+    fn targets_using(
+        db: &Arc<Database>,
+        prompt_id: &str,
+        global: bool,
+    ) -> Result<Vec<Target>, AppError> {
+        let project_ids = db.get_prompt_project_ids(prompt_id)?;
+        let mut targets = Vec::new();
+        if global {
+            targets.push(Target::Global);
+        }
+        targets.extend(
+            db.get_projects()?
+                .into_iter()
+                .filter(|p| project_ids.contains(&p.id))
+                .map(Target::Project),
+        );
+        Ok(targets)
+    }
+
+    /// Targets with at least one enabled prompt.
+    fn active_targets(db: &Arc<Database>) -> Result<Vec<Target>, AppError> {
+        let mut targets = vec![Target::Global];
+        targets.extend(db.get_projects()?.into_iter().map(Target::Project));
+        let mut active = Vec::new();
+        for target in targets {
+            if !Self::enabled_prompts(db, &target)?.is_empty() {
+                active.push(target);
+            }
+        }
+        Ok(active)
+    }
+
+    /// The target's AGENTS.md, unless it is missing or blank.
+    fn read_live(target: &Target) -> Result<Option<String>, AppError> {
+        let path = target.path()?;
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(AppError::io(&path, e)),
+        };
+        Ok((!content.trim().is_empty()).then_some(content))
+    }
+
+    /// What was last written to the target for each enabled prompt. The file
+    /// holds rendered text, so edits are measured against that; rendering
+    /// again could differ (`{{date}}`).
+    fn written(db: &Arc<Database>, target: &Target, enabled: &[Prompt]) -> Result<Vec<Fragment>, AppError> {
+        let prompts = db.get_prompts()?;
+        let variables = db.get_prompt_variables()?;
+        let output = db.get_prompt_output(target.key())?;
+        let renderer = Renderer::new(&prompts, &variables);
+        Ok(enabled
+            .iter()
+            .map(|p| Fragment {
+                id: p.id.clone(),
+                content: output
+                    .get(&p.id)
+                    .cloned()
+                    .or_else(|| renderer.render_prompt(p).ok())
+                    .unwrap_or_else(|| p.content.clone()),
+            })
+            .collect())
+    }
+
+    /// Compares the target's AGENTS.md with what was last written to it.
+    /// In region mode only the managed region is compared; text around it
+    /// is not Open Switch's. In whole-file mode that text is unassigned.
+    fn edits(
+        db: &Arc<Database>,
+        target: &Target,
+        mode: AgentsMdMode,
+    ) -> Result<Option<FileEdits>, AppError> {
+        let Some(live) = Self::read_live(target)? else {
+            return Ok(None);
+        };
+
+        let enabled = Self::enabled_prompts(db, target)?;
+        let written = Self::written(db, target, &enabled)?;
+
+        let region = prompt_fragments::split_region(&live);
+        let mut edits = FileEdits {
+            managed: String::new(),
+            expected: prompt_fragments::compose(&written),
+            texts: Vec::new(),
+            edited: Vec::new(),
+            unassigned: Vec::new(),
+        };
+        let mut after = None;
+        match (region, mode) {
+            (Some(region), _) => {
+                if mode == AgentsMdMode::WholeFile {
+                    let outside = [region.before.trim(), region.after.trim()];
+                    edits.unassigned.extend(outside.iter().filter(|t| !t.is_empty()).map(|t| t.to_string()));
+                    edits.texts.push(outside[0].to_string());
+                    after = Some(outside[1].to_string());
+                } else {
+                    after = Some(String::new());
+                }
+                edits.managed = region.inner;
+            }
+            (None, AgentsMdMode::WholeFile) => edits.managed = live,
+            (None, AgentsMdMode::Region) => {
+                edits.managed = live;
+                edits.texts.push(edits.managed.clone());
+                return Ok(Some(edits));
+            }
+        }
+
+        match prompt_fragments::split(&edits.managed) {
+            Some(file) => {
+                if !file.preamble.trim().is_empty() {
+                    edits.unassigned.push(file.preamble.trim_end().to_string());
+                }
+                edits.texts.push(file.preamble.trim_end().to_string());
+                for fragment in file.fragments {
+                    edits.texts.push(fragment.content.clone());
+                    let position = enabled.iter().position(|p| p.id == fragment.id);
+                    match position {
+                        Some(i) if prompt_fragments::differs(&written[i].content, &fragment.content) => {
+                            edits.edited.push((enabled[i].clone(), fragment.content));
+                        }
+                        Some(_) => {}
+                        None if !fragment.content.trim().is_empty() => {
+                            edits.unassigned.push(fragment.content);
+                        }
+                        None => {}
+                    }
+                }
+            }
+            None => {
+                edits.texts.push(edits.managed.clone());
+                match enabled.as_slice() {
+                    // The region's trailing whitespace is not kept.
+                    [only] if after.is_some() => {
+                        if prompt_fragments::differs(&written[0].content, &edits.managed) {
+                            edits.edited.push((only.clone(), edits.managed.clone()));
+                        }
+                    }
+                    [only] => {
+                        if written[0].content != edits.managed {
+                            edits.edited.push((only.clone(), edits.managed.clone()));
                         }
                     }
+                    _ if edits.managed.trim().is_empty() => {}
+                    _ => edits.unassigned.push(edits.managed.clone()),
                 }
             }
         }
+        edits.texts.extend(after);
+        edits.texts.retain(|text| !text.trim().is_empty());
+        Ok(Some(edits))
+    }
 
-        db.disable_all_prompts()?;
+    /// Copies edits made directly to AGENTS.md back into the prompts they
+    /// came from. Content that belongs to no enabled prompt is kept as a
+    /// backup prompt instead of being lost.
+    fn backfill(db: &Arc<Database>, edits: FileEdits) -> Result<(), AppError> {
+        for (prompt, content) in edits.edited {
+            Self::save_backfill(db, prompt, content)?;
+        }
+        Self::back_up(db, &edits.unassigned.join("\n\n"))
+    }
 
-        let prompts = db.get_prompts()?;
-        if let Some(prompt) = prompts.get(id) {
-            write_text_file(&target_path, &prompt.content)?;
-            db.enable_prompt(id)?;
+    /// Makes sure rewriting the target's AGENTS.md loses nothing. A file
+    /// with no enabled prompts is backed up; edits to one that has them are
+    /// backfilled only when automatic backfill is on, and otherwise have to
+    /// be resolved first.
+    fn before_rewrite(db: &Arc<Database>, target: &Target) -> Result<(), AppError> {
+        Self::check_rewrite(db, target, Self::agents_md_mode(db)?)
+    }
+
+    fn check_rewrite(db: &Arc<Database>, target: &Target, mode: AgentsMdMode) -> Result<(), AppError> {
+        let Some(edits) = Self::edits(db, target, mode)? else {
+            return Ok(());
+        };
+        if edits.is_empty() {
+            return Ok(());
+        }
+        if Self::enabled_prompts(db, target)?.is_empty() || Self::auto_backfill(db)? {
+            return Self::backfill(db, edits);
+        }
+        Err(AppError::InvalidInput(format!(
+            "{} was edited outside Open Switch; keep the file, keep the stored prompts or save the file as a new prompt first",
+            target.path()?.display()
+        )))
+    }
+
+    pub fn get_drift(db: &Arc<Database>, project_id: Option<&str>) -> Result<Option<PromptDrift>, AppError> {
+        let target = Self::target(db, project_id)?;
+        if Self::enabled_prompts(db, &target)?.is_empty() {
+            return Ok(None);
+        }
+        let Some(edits) = Self::edits(db, &target, Self::agents_md_mode(db)?)? else {
+            return Ok(None);
+        };
+        if edits.is_empty() {
+            return Ok(None);
+        }
+        let unassigned = edits.unassigned.join("\n\n");
+        Ok(Some(PromptDrift {
+            path: target.path()?.display().to_string(),
+            prompts: edits.edited.iter().map(|(p, _)| p.id.clone()).collect(),
+            unassigned: (!unassigned.is_empty()).then_some(unassigned),
+            diff: diff::diff_lines(&edits.expected, &edits.managed),
+        }))
+    }
+
+    /// Settles a drifted AGENTS.md and rewrites it from the prompts.
+    pub fn resolve_drift(
+        db: &Arc<Database>,
+        project_id: Option<&str>,
+        resolution: DriftResolution,
+    ) -> Result<(), AppError> {
+        let target = Self::target(db, project_id)?;
+        if let Some(edits) = Self::edits(db, &target, Self::agents_md_mode(db)?)? {
+            match resolution {
+                DriftResolution::KeepFile => Self::backfill(db, edits)?,
+                DriftResolution::KeepStored => {}
+                DriftResolution::SaveAsNew => {
+                    let content = edits.texts.join("\n\n");
+                    let name = format!("AGENTS.md {}", chrono::Local::now().format("%Y-%m-%d %H:%M"));
+                    let prompt = Self::save_copy(db, "agents-md", name, "Saved from an edited AGENTS.md", content)?;
+                    Self::commit_to_sync(db, &format!("Add prompt '{}'", prompt.name));
+                }
+            }
+        }
+        Self::write_enabled(db, &target)
+    }
+
+    /// Off unless turned on, so edits made in AGENTS.md are only saved to
+    /// the prompts once the drift has been reviewed.
+    pub fn auto_backfill(db: &Arc<Database>) -> Result<bool, AppError> {
+        Ok(db.get_setting(AUTO_BACKFILL)?.as_deref() == Some("true"))
+    }
+
+    pub fn set_auto_backfill(db: &Arc<Database>, enabled: bool) -> Result<(), AppError> {
+        if enabled {
+            db.set_setting(AUTO_BACKFILL, "true")
         } else {
-            return Err(AppError::InvalidInput(format!("Prompt {id} not found")));
+            db.delete_setting(AUTO_BACKFILL)
+        }
+    }
+
+    pub fn agents_md_mode(db: &Arc<Database>) -> Result<AgentsMdMode, AppError> {
+        Ok(db
+            .get_setting(AGENTS_MD_MODE)?
+            .as_deref()
+            .and_then(AgentsMdMode::parse)
+            .unwrap_or(AgentsMdMode::WholeFile))
+    }
+
+    /// Switches mode and rewrites every AGENTS.md with enabled prompts.
+    /// Moving to region mode puts the markers around what was last written
+    /// and keeps the text around it. Otherwise files are checked as whole
+    /// files first, so edits and text around a region are backed up before
+    /// the file is replaced.
+    pub fn set_agents_md_mode(db: &Arc<Database>, mode: AgentsMdMode) -> Result<(), AppError> {
+        if Self::agents_md_mode(db)? == mode {
+            return Ok(());
+        }
+        let targets = Self::active_targets(db)?;
+        let mut existing = Vec::with_capacity(targets.len());
+        for target in &targets {
+            let kept = match mode {
+                AgentsMdMode::Region => Self::into_region(db, target)?,
+                AgentsMdMode::WholeFile => None,
+            };
+            if kept.is_none() {
+                Self::check_rewrite(db, target, AgentsMdMode::WholeFile)?;
+            }
+            existing.push(kept.unwrap_or_default());
+        }
+        db.set_setting(AGENTS_MD_MODE, mode.as_str())?;
+        for (target, existing) in targets.iter().zip(&existing) {
+            Self::write_composed(db, target, mode, existing)?;
+        }
+        Ok(())
+    }
+
+    /// The target's AGENTS.md with region markers around what was last
+    /// written to it. A file that already has a region is checked as one.
+    /// Returns `None` when the written text is no longer in the file.
+    fn into_region(db: &Arc<Database>, target: &Target) -> Result<Option<String>, AppError> {
+        let Some(live) = Self::read_live(target)? else {
+            return Ok(Some(String::new()));
+        };
+        if prompt_fragments::paired_region(&live)?.is_some() {
+            Self::check_rewrite(db, target, AgentsMdMode::Region)?;
+            return Ok(Some(live));
+        }
+        let enabled = Self::enabled_prompts(db, target)?;
+        let written = prompt_fragments::compose(&Self::written(db, target, &enabled)?);
+        Ok(prompt_fragments::wrap_region(&live, &written))
+    }
+
+    /// Saves an edit made in AGENTS.md to the prompt it came from. The file
+    /// holds rendered text, so edits to a templated prompt are saved as a
+    /// new prompt instead of replacing its `{{...}}` source.
+    fn save_backfill(db: &Arc<Database>, mut prompt: Prompt, content: String) -> Result<(), AppError> {
+        if prompt.content.contains("{{") {
+            log::warn!("Templated prompt {} was edited in AGENTS.md; saving the edit as a new prompt", prompt.id);
+            let copy = Self::save_copy(
+                db,
+                "edited",
+                format!("{} (edited in AGENTS.md)", prompt.name),
+                "Edits made in AGENTS.md to a templated prompt",
+                content,
+            )?;
+            Self::commit_to_sync(db, &format!("Save edits to '{}' made in AGENTS.md as '{}'", prompt.name, copy.name));
+            return Ok(());
+        }
+        log::info!("Backfill live content to enabled prompt: {}", prompt.id);
+        prompt.content = content;
+        prompt.updated_at = Some(get_unix_timestamp()?);
+        db.save_prompt(&prompt, PromptSource::Backfill, prompt_template::user_name().as_deref())?;
+        Self::commit_to_sync(db, &format!("Save edits to '{}' made in AGENTS.md", prompt.name));
+        Ok(())
+    }
+
+    fn back_up(db: &Arc<Database>, content: &str) -> Result<(), AppError> {
+        if content.trim().is_empty() {
+            return Ok(());
+        }
+        let content_exists = db
+            .get_prompts()?
+            .values()
+            .any(|p| p.content.trim() == content.trim());
+        if content_exists {
+            return Ok(());
         }
 
+        let name = format!(
+            "Original Prompt {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M")
+        );
+        let prompt = Self::save_copy(db, "backup", name, "Auto-backup of original prompt", content.to_string())?;
+        Self::commit_to_sync(db, &format!("Back up AGENTS.md as '{}'", prompt.name));
         Ok(())
     }
 
+    /// Saves text taken from AGENTS.md as a new, disabled prompt.
+    fn save_copy(
+        db: &Arc<Database>,
+        prefix: &str,
+        name: String,
+        description: &str,
+        content: String,
+    ) -> Result<Prompt, AppError> {
+        let timestamp = get_unix_timestamp()?;
+        let prompts = db.get_prompts()?;
+        let mut id = format!("{prefix}-{timestamp}");
+        let mut n = 1;
+        while prompts.contains_key(&id) {
+            n += 1;
+            id = format!("{prefix}-{timestamp}-{n}");
+        }
+        let prompt = Prompt {
+            id,
+            name,
+            content,
+            description: Some(description.to_string()),
+            enabled: false,
+            created_at: Some(timestamp),
+            updated_at: Some(timestamp),
+            position: None,
+            tags: Vec::new(),
+            folder: None,
+        };
+        log::info!("Create prompt from AGENTS.md: {}", prompt.id);
+        db.save_prompt(&prompt, PromptSource::Backfill, prompt_template::user_name().as_deref())?;
+        Ok(prompt)
+    }
+
+    /// Writes the enabled prompts to the target's AGENTS.md, or removes the
+    /// file once none are enabled. Their content is still in the database.
+    /// In region mode the rest of the file is kept.
+    fn write_enabled(db: &Arc<Database>, target: &Target) -> Result<(), AppError> {
+        let mode = Self::agents_md_mode(db)?;
+        let existing = match mode {
+            AgentsMdMode::WholeFile => String::new(),
+            AgentsMdMode::Region => Self::read_live(target)?.unwrap_or_default(),
+        };
+        Self::write_composed(db, target, mode, &existing)
+    }
+
+    fn write_composed(
+        db: &Arc<Database>,
+        target: &Target,
+        mode: AgentsMdMode,
+        existing: &str,
+    ) -> Result<(), AppError> {
+        let target_path = target.path()?;
+        let enabled = Self::enabled_prompts(db, target)?;
+        let fragments = Self::render_all(db, &enabled)?;
+        let composed = (!fragments.is_empty()).then(|| prompt_fragments::compose(&fragments));
+        let content = match (mode, composed) {
+            (AgentsMdMode::WholeFile, composed) => composed,
+            (AgentsMdMode::Region, Some(composed)) => {
+                Some(prompt_fragments::with_region(existing, &composed)?)
+            }
+            (AgentsMdMode::Region, None) => prompt_fragments::without_region(existing)?,
+        };
+        match content {
+            Some(content) => write_text_file(&target_path, &content)?,
+            None if target_path.exists() => {
+                std::fs::remove_file(&target_path).map_err(|e| AppError::io(&target_path, e))?;
+            }
+            None => {}
+        }
+        db.set_prompt_output(
+            target.key(),
+            &fragments.into_iter().map(|f| (f.id, f.content)).collect(),
+        )
+    }
+
+    fn render_all(db: &Arc<Database>, enabled: &[Prompt]) -> Result<Vec<Fragment>, AppError> {
+        let prompts = db.get_prompts()?;
+        let variables = db.get_prompt_variables()?;
+        let renderer = Renderer::new(&prompts, &variables);
+        enabled
+            .iter()
+            .map(|prompt| {
+                Ok(Fragment {
+                    id: prompt.id.clone(),
+                    content: renderer.render_prompt(prompt)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Renders `prompt` as if it were saved, so a template error is reported
+    /// before anything changes.
+    fn render_with(db: &Arc<Database>, prompt: &Prompt) -> Result<String, AppError> {
+        let mut prompts = db.get_prompts()?;
+        prompts.insert(prompt.id.clone(), prompt.clone());
+        let variables = db.get_prompt_variables()?;
+        Renderer::new(&prompts, &variables).render_prompt(prompt)
+    }
+
+    fn require_prompt(db: &Arc<Database>, id: &str) -> Result<Prompt, AppError> {
+        db.get_prompts()?
+            .shift_remove(id)
+            .ok_or_else(|| AppError::InvalidInput(format!("Prompt {id} not found")))
+    }
+
+    /// Rewrites the target with `ids` enabled in that order.
+    fn recompose(db: &Arc<Database>, target: &Target, ids: &[String]) -> Result<(), AppError> {
+        Self::before_rewrite(db, target)?;
+        match target {
+            Target::Global => db.set_enabled_prompts(ids)?,
+            Target::Project(project) => db.set_project_prompts(&project.id, ids)?,
+        }
+        Self::write_enabled(db, target)
+    }
+
+    /// Makes `id` the only enabled prompt, globally or in a project.
+    pub fn enable_prompt(db: &Arc<Database>, id: &str, project_id: Option<&str>) -> Result<(), AppError> {
+        let target = Self::target(db, project_id)?;
+        let prompt = Self::require_prompt(db, id)?;
+        Self::render_with(db, &prompt)?;
+        Self::recompose(db, &target, &[prompt.id])
+    }
+
+    /// Adds a prompt to, or removes it from, a composed AGENTS.md. Newly
+    /// enabled prompts go last.
+    pub fn set_prompt_enabled(
+        db: &Arc<Database>,
+        id: &str,
+        enabled: bool,
+        project_id: Option<&str>,
+    ) -> Result<(), AppError> {
+        let target = Self::target(db, project_id)?;
+        let prompt = Self::require_prompt(db, id)?;
+        let mut ids: Vec<String> = Self::enabled_prompts(db, &target)?
+            .into_iter()
+            .map(|p| p.id)
+            .collect();
+
+        if enabled {
+            Self::render_with(db, &prompt)?;
+            if !ids.contains(&prompt.id) {
+                ids.push(prompt.id);
+            }
+        } else {
+            ids.retain(|enabled_id| *enabled_id != prompt.id);
+        }
+        Self::recompose(db, &target, &ids)
+    }
+
+    /// Reorders the enabled prompts. Ones missing from `order` keep their
+    /// relative order after those it names.
+    pub fn reorder_enabled(
+        db: &Arc<Database>,
+        order: &[String],
+        project_id: Option<&str>,
+    ) -> Result<(), AppError> {
+        let target = Self::target(db, project_id)?;
+        let mut ids: Vec<String> = Self::enabled_prompts(db, &target)?
+            .into_iter()
+            .map(|p| p.id)
+            .collect();
+        if let Some(unknown) = order.iter().find(|id| !ids.contains(id)) {
+            return Err(AppError::InvalidInput(format!("Prompt {unknown} is not enabled")));
+        }
+
+        ids.sort_by_cached_key(|id| order.iter().position(|o| o == id).unwrap_or(order.len()));
+        Self::recompose(db, &target, &ids)
+    }
+
+    /// Whether a target's AGENTS.md is still what Open Switch wrote.
+    fn file_state(db: &Arc<Database>, target: &Target) -> Result<AgentsFileState, AppError> {
+        let path = target.path()?;
+        if !path.exists() {
+            return Ok(AgentsFileState::Missing);
+        }
+        let content = std::fs::read_to_string(&path).map_err(|e| AppError::io(&path, e))?;
+        let output = db.get_prompt_output(target.key())?;
+        let enabled = Self::enabled_prompts(db, target)?;
+        // A region's trailing whitespace is not kept.
+        let region = prompt_fragments::split_region(&content);
+        let in_region = region.is_some();
+        let content = region.map(|r| r.inner).unwrap_or(content);
+
+        // Files written before output was recorded are compared with a
+        // fresh render instead.
+        let expected = if enabled.is_empty() {
+            None
+        } else if enabled.iter().all(|p| output.contains_key(&p.id)) {
+            Some(
+                enabled
+                    .iter()
+                    .map(|p| Fragment {
+                        id: p.id.clone(),
+                        content: output[&p.id].clone(),
+                    })
+                    .collect(),
+            )
+        } else {
+            Self::render_all(db, &enabled).ok()
+        };
+
+        let unchanged = expected.is_some_and(|fragments| {
+            let composed = prompt_fragments::compose(&fragments);
+            if in_region {
+                !prompt_fragments::differs(&composed, &content)
+            } else {
+                composed == content
+            }
+        });
+
+        Ok(if unchanged {
+            AgentsFileState::Managed
+        } else if !output.is_empty() || prompt_fragments::split(&content).is_some() {
+            AgentsFileState::Modified
+        } else {
+            AgentsFileState::HandWritten
+        })
+    }
+
+    pub fn get_file_state(
+        db: &Arc<Database>,
+        project_id: Option<&str>,
+    ) -> Result<AgentsFileState, AppError> {
+        Self::file_state(db, &Self::target(db, project_id)?)
+    }
+
+    pub fn get_projects(db: &Arc<Database>) -> Result<Vec<ProjectStatus>, AppError> {
+        db.get_projects()?
+            .into_iter()
+            .map(|project| {
+                let target = Target::Project(project.clone());
+                Ok(ProjectStatus {
+                    project,
+                    agents_md: Self::file_state(db, &target)?,
+                    prompts: Self::enabled_prompts(db, &target)?
+                        .into_iter()
+                        .map(|p| p.id)
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn add_project(db: &Arc<Database>, path: &str, name: Option<String>) -> Result<Project, AppError> {
+        let resolved = resolve_file_path(path.trim(), Path::new("."));
+        if !resolved.is_absolute() {
+            return Err(AppError::InvalidInput(format!("'{path}' must be an absolute path")));
+        }
+        if !resolved.is_dir() {
+            return Err(AppError::InvalidInput(format!("'{path}' is not an existing directory")));
+        }
+        let resolved = resolved.canonicalize().map_err(|e| AppError::io(&resolved, e))?;
+        let path = resolved.display().to_string();
+
+        if let Some(existing) = db.get_projects()?.into_iter().find(|p| p.path == path) {
+            return Err(AppError::InvalidInput(format!(
+                "'{path}' is already registered as {}",
+                existing.name
+            )));
+        }
+
+        let name = name
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty())
+            .or_else(|| resolved.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| path.clone());
+        let project = Project {
+            id: format!("project-{}", chrono::Utc::now().timestamp_millis()),
+            name,
+            path,
+            created_at: get_unix_timestamp()?,
+        };
+        db.add_project(&project)?;
+        log::info!("Registered project {} at {}", project.id, project.path);
+        Ok(project)
+    }
+
+    /// Forgets a project. Its AGENTS.md is left as it is.
+    pub fn remove_project(db: &Arc<Database>, id: &str) -> Result<(), AppError> {
+        Self::target(db, Some(id))?;
+        db.delete_project(id)
+    }
+
     pub fn import_from_file(db: &Arc<Database>) -> Result<String, AppError> {
         let file_path = get_agents_md_path()?;
 
@@ -144,14 +1048,21 @@ impl PromptService {
             enabled: false,
             created_at: Some(timestamp),
             updated_at: Some(timestamp),
+            position: None,
+            tags: Vec::new(),
+            folder: None,
         };
 
-        db.save_prompt(&prompt)?;
+        db.save_prompt(&prompt, PromptSource::Import, prompt_template::user_name().as_deref())?;
+        Self::commit_to_sync(db, &format!("Import AGENTS.md as '{}'", prompt.name));
         Ok(id)
     }
 
-    pub fn get_current_file_content() -> Result<Option<String>, AppError> {
-        let file_path = get_agents_md_path()?;
+    pub fn get_current_file_content(
+        db: &Arc<Database>,
+        project_id: Option<&str>,
+    ) -> Result<Option<String>, AppError> {
+        let file_path = Self::target(db, project_id)?.path()?;
         if !file_path.exists() {
             return Ok(None);
         }
@@ -197,10 +1108,1348 @@ impl PromptService {
             enabled: true,
             created_at: Some(timestamp),
             updated_at: Some(timestamp),
+            position: None,
+            tags: Vec::new(),
+            folder: None,
         };
 
-        db.save_prompt(&prompt)?;
+        db.save_prompt(&prompt, PromptSource::Import, prompt_template::user_name().as_deref())?;
         log::info!("Auto-import completed: {id}");
         Ok(1)
     }
+
+    pub fn search(
+        db: &Arc<Database>,
+        query: &str,
+        tags: Vec<String>,
+        folder: Option<&str>,
+        limit: Option<u32>,
+    ) -> Result<Vec<PromptSearchHit>, AppError> {
+        db.search_prompts(
+            fts_query(query).as_deref(),
+            &normalize_tags(tags),
+            folder.and_then(normalize_folder).as_deref(),
+            limit.unwrap_or(50).min(500),
+        )
+    }
+
+    /// Writes prompts (all of them when `ids` is `None`) to `directory` as
+    /// Markdown files. A prompt exported there before keeps its file; new
+    /// ones are named after the prompt without replacing other files.
+    pub fn export_library(
+        db: &Arc<Database>,
+        directory: &str,
+        ids: Option<Vec<String>>,
+    ) -> Result<LibraryExport, AppError> {
+        let dir = resolve_file_path(directory.trim(), Path::new("."));
+        if !dir.is_absolute() {
+            return Err(AppError::InvalidInput(format!("'{directory}' must be an absolute path")));
+        }
+        std::fs::create_dir_all(&dir).map_err(|e| AppError::io(&dir, e))?;
+
+        let mut prompts = db.get_prompts()?;
+        let mut export = LibraryExport {
+            directory: dir.display().to_string(),
+            exported: Vec::new(),
+            missing: Vec::new(),
+        };
+        let selected: Vec<Prompt> = match ids {
+            Some(ids) => ids
+                .into_iter()
+                .filter_map(|id| match prompts.shift_remove(&id) {
+                    Some(prompt) => Some(prompt),
+                    None => {
+                        export.missing.push(id);
+                        None
+                    }
+                })
+                .collect(),
+            None => prompts.into_values().collect(),
+        };
+
+        let mut names = LibraryNames::read(&dir)?;
+        for prompt in selected {
+            let file = match names.by_id.get(&prompt.id) {
+                Some(file) => file.clone(),
+                None => names.claim(&prompt),
+            };
+            write_text_file(&dir.join(&file), &prompt_library::to_markdown(&prompt))?;
+            export.exported.push(ExportedPrompt { id: prompt.id, file });
+        }
+
+        log::info!("Exported {} prompt(s) to {}", export.exported.len(), export.directory);
+        Ok(export)
+    }
+
+    /// Reads the Markdown files in `directory`. A file whose id is a stored
+    /// prompt updates it, unless nothing changed or the stored prompt was
+    /// edited after the file was exported; other files create prompts,
+    /// unless one with the same content exists. With `dry_run` the
+    /// outcome is reported without saving anything.
+    pub fn import_library(
+        db: &Arc<Database>,
+        directory: &str,
+        dry_run: bool,
+    ) -> Result<LibraryImport, AppError> {
+        let dir = resolve_file_path(directory.trim(), Path::new("."));
+        if !dir.is_dir() {
+            return Err(AppError::InvalidInput(format!("'{directory}' is not an existing directory")));
+        }
+
+        let mut prompts = db.get_prompts()?;
+        let mut hashes: HashMap<String, String> = prompts
+            .values()
+            .map(|p| (prompt_library::content_hash(&p.content), p.id.clone()))
+            .collect();
+        let mut seen: HashMap<String, String> = HashMap::new();
+        let timestamp = get_unix_timestamp()?;
+        let mut import = LibraryImport {
+            directory: dir.display().to_string(),
+            dry_run,
+            items: Vec::new(),
+        };
+
+        for (index, (file, entry)) in read_library(&dir)?.into_iter().enumerate() {
+            let item = |action, prompt_id: Option<&str>, reason: Option<String>| LibraryImportItem {
+                file: file.clone(),
+                action,
+                prompt_id: prompt_id.map(str::to_string),
+                reason,
+            };
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(reason) => {
+                    import.items.push(item(LibraryAction::Error, None, Some(reason)));
+                    continue;
+                }
+            };
+            if let Some(first) = entry.id.as_ref().and_then(|id| seen.get(id)) {
+                import.items.push(item(
+                    LibraryAction::Skip,
+                    entry.id.as_deref(),
+                    Some(format!("Same id as {first}")),
+                ));
+                continue;
+            }
+            if let Some(id) = &entry.id {
+                seen.insert(id.clone(), file.clone());
+            }
+
+            let hash = prompt_library::content_hash(&entry.content);
+            let edited_in_file = entry.content_hash.as_ref().is_some_and(|h| *h != hash);
+            let stem = Path::new(&file)
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            let (action, prompt) = match entry.id.as_ref().and_then(|id| prompts.get(id)) {
+                Some(stored) => {
+                    let updated_at = entry.updated_at;
+                    let mut prompt = stored.clone();
+                    apply_entry(&mut prompt, entry);
+
+                    let unchanged = prompt.content == stored.content
+                        && prompt.name == stored.name
+                        && prompt.description == stored.description
+                        && prompt.tags == stored.tags
+                        && prompt.folder == stored.folder;
+                    let stored_is_newer = !edited_in_file
+                        && matches!(
+                            (updated_at, stored.updated_at),
+                            (Some(file_time), Some(stored_time)) if file_time < stored_time
+                        );
+                    if unchanged {
+                        import.items.push(item(LibraryAction::Skip, Some(&stored.id), Some("Unchanged".into())));
+                        continue;
+                    }
+                    if stored_is_newer {
+                        import.items.push(item(
+                            LibraryAction::Skip,
+                            Some(&stored.id),
+                            Some("The stored prompt was edited after this file was exported".into()),
+                        ));
+                        continue;
+                    }
+                    prompt.updated_at = Some(timestamp);
+                    (LibraryAction::Update, prompt)
+                }
+                None => {
+                    if let Some(existing) = hashes.get(&hash) {
+                        import.items.push(item(
+                            LibraryAction::Skip,
+                            Some(existing),
+                            Some(format!("Same content as prompt {existing}")),
+                        ));
+                        continue;
+                    }
+                    let id = entry
+                        .id
+                        .clone()
+                        .unwrap_or_else(|| format!("imported-{timestamp}-{index}"));
+                    (LibraryAction::Create, prompt_from_entry(id, stem, entry, timestamp))
+                }
+            };
+
+            if !dry_run {
+                if let Err(e) = Self::save(db, &prompt, PromptSource::Import) {
+                    import.items.push(item(LibraryAction::Error, Some(&prompt.id), Some(e.to_string())));
+                    continue;
+                }
+            }
+            hashes.insert(hash, prompt.id.clone());
+            import.items.push(item(action, Some(&prompt.id), None));
+            prompts.insert(prompt.id.clone(), prompt);
+        }
+
+        let changed = import
+            .items
+            .iter()
+            .any(|i| matches!(i.action, LibraryAction::Create | LibraryAction::Update));
+        if !dry_run && changed {
+            log::info!("Imported prompt library from {}", import.directory);
+            Self::commit_to_sync(db, &format!("Import prompts from {}", import.directory));
+        }
+        Ok(import)
+    }
+
+    pub fn get_sync_config(db: &Arc<Database>) -> Result<Option<PromptSyncConfig>, AppError> {
+        let Some(path) = db.get_setting(SYNC_PATH)? else {
+            return Ok(None);
+        };
+        Ok(Some(PromptSyncConfig {
+            path,
+            directory: db.get_setting(SYNC_DIRECTORY)?.unwrap_or_default(),
+        }))
+    }
+
+    fn sync_repo(db: &Arc<Database>) -> Result<Option<(Git, PromptSyncConfig)>, AppError> {
+        match Self::get_sync_config(db)? {
+            Some(config) => Ok(Some((Git::open(Path::new(&config.path))?, config))),
+            None => Ok(None),
+        }
+    }
+
+    /// Points sync at a git working copy, cloning `remote` into `path`
+    /// first when given and the directory is missing or empty. Prompt
+    /// files go in `directory` inside it, `prompts` by default. A `None`
+    /// path turns sync off; the working copy is left as it is.
+    pub fn set_sync(
+        db: &Arc<Database>,
+        path: Option<&str>,
+        remote: Option<&str>,
+        directory: Option<&str>,
+    ) -> Result<Option<PromptSyncStatus>, AppError> {
+        let Some(path) = path.map(str::trim).filter(|p| !p.is_empty()) else {
+            db.set_prompt_sync_state(&IndexMap::new())?;
+            db.delete_setting(SYNC_LAST)?;
+            db.delete_setting(SYNC_PATH)?;
+            db.delete_setting(SYNC_DIRECTORY)?;
+            return Ok(None);
+        };
+
+        let resolved = resolve_file_path(path, Path::new("."));
+        if !resolved.is_absolute() {
+            return Err(AppError::InvalidInput(format!("'{path}' must be an absolute path")));
+        }
+        let directory = match directory {
+            Some(directory) => normalize_folder(directory).unwrap_or_default(),
+            None => "prompts".to_string(),
+        };
+        if directory.split('/').any(|segment| segment == ".." || segment == ".") {
+            return Err(AppError::InvalidInput(format!(
+                "'{directory}' must be a directory inside the working copy"
+            )));
+        }
+
+        let is_empty = std::fs::read_dir(&resolved).map_or(true, |mut entries| entries.next().is_none());
+        let git = match remote.map(str::trim).filter(|r| !r.is_empty()) {
+            Some(remote) if is_empty => Git::clone_into(remote, &resolved)?,
+            _ => Git::open(&resolved)?,
+        };
+
+        // The merge base belongs to the previous working copy.
+        db.set_prompt_sync_state(&IndexMap::new())?;
+        db.delete_setting(SYNC_LAST)?;
+        db.set_setting(SYNC_PATH, &git.root().display().to_string())?;
+        db.set_setting(SYNC_DIRECTORY, &directory)?;
+        log::info!("Syncing prompts with {}", git.root().display());
+        Self::get_sync_status(db)
+    }
+
+    pub fn get_sync_status(db: &Arc<Database>) -> Result<Option<PromptSyncStatus>, AppError> {
+        let Some((git, config)) = Self::sync_repo(db)? else {
+            return Ok(None);
+        };
+        let upstream = git.upstream()?;
+        let (mut ahead, mut behind) = (0, 0);
+        if let (Some(up), true) = (&upstream, git.has_commits()?) {
+            let counts = git.run(&["rev-list", "--left-right", "--count", &format!("HEAD...{up}")])?;
+            let mut counts = counts.split_whitespace().map(|n| n.parse().unwrap_or(0));
+            ahead = counts.next().unwrap_or(0);
+            behind = counts.next().unwrap_or(0);
+        }
+        let plan = Self::sync_plan(db, &git, &config, upstream.as_deref())?;
+
+        Ok(Some(PromptSyncStatus {
+            branch: git.branch().ok(),
+            upstream,
+            ahead,
+            behind,
+            conflicts: plan.conflicts(),
+            last_synced_at: db.get_setting(SYNC_LAST)?.and_then(|t| t.parse().ok()),
+            config,
+        }))
+    }
+
+    /// Compares each prompt here with the repository's version at
+    /// `upstream` and with the last sync. Without an upstream there is
+    /// nothing to merge, so every local prompt is kept.
+    fn sync_plan(
+        db: &Arc<Database>,
+        git: &Git,
+        config: &PromptSyncConfig,
+        upstream: Option<&str>,
+    ) -> Result<SyncPlan, AppError> {
+        let prompts = db.get_prompts()?;
+        let base = db.get_prompt_sync_state()?;
+        let mut plan = SyncPlan {
+            upstream: upstream.unwrap_or_default().to_string(),
+            ..SyncPlan::default()
+        };
+        let Some(upstream) = upstream else {
+            plan.items = prompts
+                .into_values()
+                .map(|prompt| SyncItem {
+                    action: SyncAction::KeepLocal,
+                    local: Some(prompt),
+                    remote: None,
+                    file: None,
+                    failed: false,
+                })
+                .collect();
+            return Ok(plan);
+        };
+
+        let timestamp = get_unix_timestamp()?;
+        let mut remote: IndexMap<String, RemoteFile> = IndexMap::new();
+        let mut unreadable = HashSet::new();
+        let files: Vec<String> = git
+            .list_files(upstream, &config.directory)?
+            .into_iter()
+            .filter(|file| file.to_lowercase().ends_with(".md"))
+            .collect();
+        let texts = git.show_all(upstream, &files)?;
+        for (index, (file, text)) in files.into_iter().zip(texts).enumerate() {
+            let synced_id = base.iter().find(|(_, s)| s.file == file).map(|(id, _)| id.clone());
+            let entry = text
+                .ok_or_else(|| "not found".to_string())
+                .and_then(|text| prompt_library::parse_markdown(&text));
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(reason) => {
+                    plan.errors.push(format!("{file}: {reason}"));
+                    unreadable.extend(synced_id);
+                    continue;
+                }
+            };
+            let id = entry
+                .id
+                .clone()
+                .or(synced_id)
+                .unwrap_or_else(|| format!("imported-{timestamp}-{index}"));
+            if let Some(first) = remote.get(&id) {
+                plan.errors.push(format!("{file}: same id as {}", first.file));
+                continue;
+            }
+
+            let name = Path::new(&file)
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let updated_at = entry.updated_at;
+            let mut prompt = match prompts.get(&id) {
+                Some(local) => {
+                    let mut prompt = local.clone();
+                    apply_entry(&mut prompt, entry);
+                    prompt
+                }
+                None => prompt_from_entry(id.clone(), name, entry, timestamp),
+            };
+            prompt.updated_at = updated_at.or(Some(timestamp));
+            remote.insert(id, RemoteFile { file, prompt });
+        }
+
+        let ids: Vec<String> = prompts
+            .keys()
+            .chain(remote.keys())
+            .chain(base.keys())
+            .filter(|id| !unreadable.contains(*id))
+            .cloned()
+            .collect::<indexmap::IndexSet<_>>()
+            .into_iter()
+            .collect();
+        for id in ids {
+            let local = prompts.get(&id).cloned();
+            let remote = remote.shift_remove(&id);
+            let action = prompt_sync::classify(
+                local.as_ref().map(prompt_library::fingerprint).as_deref(),
+                remote.as_ref().map(|r| prompt_library::fingerprint(&r.prompt)).as_deref(),
+                base.get(&id).map(|s| s.hash.as_str()),
+            );
+            let file = remote
+                .as_ref()
+                .map(|r| r.file.clone())
+                .or_else(|| base.get(&id).map(|s| s.file.clone()));
+            plan.items.push(SyncItem {
+                action,
+                local,
+                remote,
+                file,
+                failed: false,
+            });
+        }
+        Ok(plan)
+    }
+
+    /// Writes every prompt to its file in the working copy and stages the
+    /// directory. Prompts in `plan` that are held back keep the
+    /// repository's version. Returns the file of each prompt written.
+    fn write_sync_files(
+        db: &Arc<Database>,
+        git: &Git,
+        config: &PromptSyncConfig,
+        plan: &SyncPlan,
+    ) -> Result<HashMap<String, String>, AppError> {
+        let dir = git.root().join(&config.directory);
+        let relative = |name: &str| match config.directory.as_str() {
+            "" => name.to_string(),
+            directory => format!("{directory}/{name}"),
+        };
+        let remove = |file: &str| -> Result<(), AppError> {
+            let path = git.root().join(file);
+            if path.exists() {
+                std::fs::remove_file(&path).map_err(|e| AppError::io(&path, e))?;
+            }
+            Ok(())
+        };
+
+        let prompts = db.get_prompts()?;
+        let base = db.get_prompt_sync_state()?;
+        let mut names = LibraryNames::read(&dir)?;
+        let held: HashMap<&str, Option<&RemoteFile>> = plan
+            .items
+            .iter()
+            .filter(|item| item.is_held())
+            .filter_map(|item| Some((item.id()?, item.remote.as_ref())))
+            .collect();
+        let remote_files: HashMap<&str, &str> = plan
+            .items
+            .iter()
+            .filter_map(|item| Some((item.id()?, item.remote.as_ref()?.file.as_str())))
+            .collect();
+
+        // Files of prompts that were deleted, or that moved.
+        let mut current: HashMap<String, Vec<String>> = HashMap::new();
+        for (id, file) in &names.by_id {
+            current.entry(id.clone()).or_default().push(relative(file));
+        }
+        for (id, synced) in &base {
+            current.entry(id.clone()).or_default().push(synced.file.clone());
+        }
+
+        let mut written = HashMap::new();
+        for (id, files) in &current {
+            if !prompts.contains_key(id) && !held.contains_key(id.as_str()) {
+                for file in files {
+                    remove(file)?;
+                }
+            }
+        }
+        for prompt in prompts.values() {
+            if held.contains_key(prompt.id.as_str()) {
+                continue;
+            }
+            let file = match remote_files.get(prompt.id.as_str()) {
+                Some(file) => file.to_string(),
+                None => match (base.get(&prompt.id), names.by_id.get(&prompt.id)) {
+                    (Some(synced), _) => synced.file.clone(),
+                    (None, Some(name)) => relative(name),
+                    (None, None) => relative(&names.claim(prompt)),
+                },
+            };
+            for old in current.get(&prompt.id).into_iter().flatten() {
+                if *old != file {
+                    remove(old)?;
+                }
+            }
+            let path = git.root().join(&file);
+            let text = prompt_library::to_markdown(prompt);
+            if std::fs::read_to_string(&path).ok().as_deref() != Some(text.as_str()) {
+                write_text_file(&path, &text)?;
+            }
+            written.insert(prompt.id.clone(), file);
+        }
+        for (id, remote) in &held {
+            for old in current.get(*id).into_iter().flatten() {
+                if remote.is_none_or(|r| r.file != *old) {
+                    remove(old)?;
+                }
+            }
+            if let Some(remote) = remote {
+                let text = git.show(&plan.upstream, &remote.file)?;
+                write_text_file(&git.root().join(&remote.file), &text)?;
+            }
+        }
+
+        let pathspec = if config.directory.is_empty() { "." } else { config.directory.as_str() };
+        // `git add` rejects a pathspec that matches nothing, as the prompt
+        // directory does until the first prompt is written.
+        if !written.is_empty() || !held.is_empty() || !git.run(&["ls-files", "--", pathspec])?.is_empty() {
+            git.run(&["add", "--all", "--", pathspec])?;
+        }
+        Ok(written)
+    }
+
+    /// Commits what is staged. A merge in progress is concluded even when
+    /// the tree did not change.
+    fn commit_staged(git: &Git, message: &str) -> Result<bool, AppError> {
+        if !git.is_merging()? && git.succeeds(&["diff", "--cached", "--quiet"])? {
+            return Ok(false);
+        }
+        git.commit(message)?;
+        Ok(true)
+    }
+
+    /// Commits the current prompts to the sync working copy, if sync is
+    /// set up. The change is already saved, so a failure is only logged;
+    /// the next sync commits it.
+    fn commit_to_sync(db: &Arc<Database>, message: &str) {
+        let result = Self::sync_repo(db).and_then(|repo| {
+            let Some((git, config)) = repo else {
+                return Ok(());
+            };
+            // A sync is merging; it writes the files itself.
+            if git.is_merging()? {
+                return Ok(());
+            }
+            // Conflicted prompts keep the repository's version.
+            let plan = Self::sync_plan(db, &git, &config, git.upstream()?.as_deref())?;
+            Self::write_sync_files(db, &git, &config, &plan)?;
+            Self::commit_staged(&git, message).map(|_| ())
+        });
+        if let Err(e) = result {
+            log::warn!("Failed to commit prompt change for sync: {e}");
+        }
+    }
+
+    /// Exchanges changes with the sync repository. Local changes are
+    /// committed, the remote is fetched, and each prompt is merged on its
+    /// own: a side that changed since the last sync wins over one that
+    /// did not. Prompts changed on both sides are reported as conflicts
+    /// and left as they are here; the repository keeps its version until
+    /// the conflict is resolved.
+    pub fn sync(db: &Arc<Database>) -> Result<SyncReport, AppError> {
+        let (git, config) = Self::sync_repo(db)?
+            .ok_or_else(|| AppError::InvalidInput("Prompt sync is not set up".to_string()))?;
+
+        let pending = Self::sync_plan(db, &git, &config, git.upstream()?.as_deref())?;
+        Self::write_sync_files(db, &git, &config, &pending)?;
+        Self::commit_staged(&git, "Update prompts")?;
+
+        let remote = git.remote()?;
+        if let Some(remote) = &remote {
+            git.run(&["fetch", "--quiet", remote])?;
+        }
+        let upstream = git.upstream()?;
+        let mut plan = Self::sync_plan(db, &git, &config, upstream.as_deref())?;
+
+        let mut report = SyncReport {
+            pulled: Vec::new(),
+            deleted: Vec::new(),
+            pushed: Vec::new(),
+            conflicts: Vec::new(),
+            errors: std::mem::take(&mut plan.errors),
+            pushed_commits: false,
+        };
+        for item in &mut plan.items {
+            let Some(id) = item.id().map(str::to_string) else {
+                continue;
+            };
+            match (item.action, &item.remote) {
+                (SyncAction::TakeRemote, Some(remote)) => {
+                    match Self::save(db, &remote.prompt, PromptSource::Sync) {
+                        Ok(()) => report.pulled.push(id),
+                        Err(e) => {
+                            report.errors.push(format!("{}: {e}", remote.file));
+                            item.failed = true;
+                        }
+                    }
+                }
+                (SyncAction::TakeRemote, None) => {
+                    let enabled = item.local.as_ref().is_some_and(|p| p.enabled)
+                        || !db.get_prompt_project_ids(&id)?.is_empty();
+                    if enabled {
+                        item.action = SyncAction::Conflict(SyncConflictKind::Enabled);
+                    } else {
+                        db.delete_prompt(&id)?;
+                        report.deleted.push(id);
+                    }
+                }
+                (SyncAction::KeepLocal, _) => report.pushed.push(id),
+                _ => {}
+            }
+        }
+        report.conflicts = plan.conflicts();
+
+        let merged = Self::merge_and_push(db, &git, &config, &plan, remote.as_deref());
+        let written = match merged {
+            Ok((written, pushed)) => {
+                report.pushed_commits = pushed;
+                written
+            }
+            Err(e) => {
+                if git.is_merging()? {
+                    let _ = git.run(&["merge", "--abort"]);
+                }
+                return Err(e);
+            }
+        };
+
+        // What both sides now agree on is the base for the next sync.
+        let prompts = db.get_prompts()?;
+        let mut base = db.get_prompt_sync_state()?;
+        for item in &plan.items {
+            let Some(id) = item.id() else {
+                continue;
+            };
+            if item.is_held() {
+                continue;
+            }
+            match (prompts.get(id), written.get(id)) {
+                (Some(prompt), Some(file)) => {
+                    base.insert(
+                        id.to_string(),
+                        SyncedPrompt {
+                            file: file.clone(),
+                            hash: prompt_library::fingerprint(prompt),
+                        },
+                    );
+                }
+                _ => {
+                    base.shift_remove(id);
+                }
+            }
+        }
+        db.set_prompt_sync_state(&base)?;
+        db.set_setting(SYNC_LAST, &get_unix_timestamp()?.to_string())?;
+
+        log::info!(
+            "Synced prompts: {} pulled, {} deleted, {} pushed, {} conflict(s)",
+            report.pulled.len(),
+            report.deleted.len(),
+            report.pushed.len(),
+            report.conflicts.len()
+        );
+        Ok(report)
+    }
+
+    fn merge_and_push(
+        db: &Arc<Database>,
+        git: &Git,
+        config: &PromptSyncConfig,
+        plan: &SyncPlan,
+        remote: Option<&str>,
+    ) -> Result<(HashMap<String, String>, bool), AppError> {
+        let upstream = Some(plan.upstream.as_str()).filter(|u| !u.is_empty());
+        if let Some(upstream) = upstream {
+            if !git.has_commits()? || git.is_ancestor("HEAD", upstream)? {
+                git.run_as_author(&["merge", "--quiet", "--ff-only", upstream])?;
+            } else if !git.is_ancestor(upstream, "HEAD")? {
+                // Prompt files are rewritten below whatever the merge does
+                // to them; `ours` settles their overlapping edits meanwhile.
+                // Conflicts elsewhere in the repository stop the sync.
+                let merged = git.run_as_author(&[
+                    "merge",
+                    "--quiet",
+                    "--no-ff",
+                    "--no-commit",
+                    "--allow-unrelated-histories",
+                    "-X",
+                    "ours",
+                    upstream,
+                ]);
+                if !git.is_merging()? {
+                    merged?;
+                }
+            }
+        }
+
+        let written = Self::write_sync_files(db, git, config, plan)?;
+        let unmerged = git.run(&["diff", "--name-only", "--diff-filter=U"])?;
+        if !unmerged.is_empty() {
+            return Err(AppError::Message(format!(
+                "Cannot merge changes outside the prompts: {}",
+                unmerged.lines().collect::<Vec<_>>().join(", ")
+            )));
+        }
+        let message = match upstream {
+            Some(upstream) => format!("Sync prompts with {upstream}"),
+            None => "Update prompts".to_string(),
+        };
+        Self::commit_staged(git, &message)?;
+
+        let Some(remote) = remote else {
+            return Ok((written, false));
+        };
+        let unpushed = match upstream {
+            Some(upstream) => !git.is_ancestor("HEAD", upstream)?,
+            None => git.has_commits()?,
+        };
+        if unpushed {
+            git.run(&["push", "--quiet", "--set-upstream", remote, "HEAD"]).map_err(|e| {
+                AppError::Message(format!("{e}\nThe repository may have changed meanwhile; sync again"))
+            })?;
+        }
+        Ok((written, unpushed))
+    }
+
+    /// Settles a conflict reported by sync. The repository is not touched
+    /// until the next sync.
+    pub fn resolve_sync_conflict(
+        db: &Arc<Database>,
+        prompt_id: &str,
+        resolution: SyncResolution,
+    ) -> Result<(), AppError> {
+        let (git, config) = Self::sync_repo(db)?
+            .ok_or_else(|| AppError::InvalidInput("Prompt sync is not set up".to_string()))?;
+        let upstream = git.upstream()?;
+        let plan = Self::sync_plan(db, &git, &config, upstream.as_deref())?;
+        let item = plan
+            .items
+            .into_iter()
+            .find(|item| item.id() == Some(prompt_id) && matches!(item.action, SyncAction::Conflict(_)))
+            .ok_or_else(|| AppError::InvalidInput(format!("Prompt {prompt_id} has no sync conflict")))?;
+
+        if let SyncResolution::Remote = resolution {
+            match &item.remote {
+                Some(remote) => Self::save(db, &remote.prompt, PromptSource::Sync)?,
+                None => Self::delete_prompt(db, prompt_id)?,
+            }
+        }
+
+        // The repository's version becomes the base, so the next sync
+        // sees only this side as changed.
+        let mut base = db.get_prompt_sync_state()?;
+        match &item.remote {
+            Some(remote) => {
+                base.insert(
+                    prompt_id.to_string(),
+                    SyncedPrompt {
+                        file: remote.file.clone(),
+                        hash: prompt_library::fingerprint(&remote.prompt),
+                    },
+                );
+            }
+            None => {
+                base.shift_remove(prompt_id);
+            }
+        }
+        db.set_prompt_sync_state(&base)
+    }
+
+    pub fn get_versions(db: &Arc<Database>, prompt_id: &str) -> Result<Vec<PromptVersion>, AppError> {
+        db.get_prompt_versions(prompt_id)
+    }
+
+    fn find_version(db: &Arc<Database>, id: i64) -> Result<PromptVersion, AppError> {
+        db.get_prompt_version(id)?
+            .ok_or_else(|| AppError::InvalidInput(format!("Prompt version {id} not found")))
+    }
+
+    pub fn diff_versions(
+        db: &Arc<Database>,
+        from_id: i64,
+        to_id: i64,
+    ) -> Result<PromptVersionDiff, AppError> {
+        let from = Self::find_version(db, from_id)?;
+        let to = Self::find_version(db, to_id)?;
+        if from.prompt_id != to.prompt_id {
+            return Err(AppError::InvalidInput(
+                "Cannot diff versions of different prompts".to_string(),
+            ));
+        }
+        let diff = diff::diff_lines(&from.content, &to.content);
+        Ok(PromptVersionDiff { from, to, diff })
+    }
+
+    /// Makes an old revision current again. This adds a new revision rather
+    /// than discarding the newer ones, so a restore can itself be undone.
+    /// A deleted prompt comes back disabled.
+    pub fn restore_version(db: &Arc<Database>, version_id: i64) -> Result<Prompt, AppError> {
+        let version = Self::find_version(db, version_id)?;
+        let mut prompt = db.get_prompts()?.shift_remove(&version.prompt_id).unwrap_or_else(|| Prompt {
+            id: version.prompt_id.clone(),
+            name: String::new(),
+            content: String::new(),
+            description: None,
+            enabled: false,
+            created_at: Some(version.created_at),
+            updated_at: None,
+            position: None,
+            tags: Vec::new(),
+            folder: None,
+        });
+
+        prompt.name = version.name;
+        prompt.content = version.content;
+        prompt.description = version.description;
+        prompt.updated_at = Some(get_unix_timestamp()?);
+        Self::save(db, &prompt, PromptSource::Restore)?;
+        Self::commit_to_sync(
+            db,
+            &format!("Restore prompt '{}' to version {}", prompt.name, version.version),
+        );
+        log::info!("Restored prompt {} to version {}", prompt.id, version.version);
+        Ok(prompt)
+    }
+
+    /// Renders a prompt as it would be written to AGENTS.md. `content`
+    /// previews unsaved edits, including for a prompt not saved yet.
+    pub fn preview(db: &Arc<Database>, id: &str, content: Option<String>) -> Result<String, AppError> {
+        let prompt = match (db.get_prompts()?.shift_remove(id), content) {
+            (Some(prompt), None) => prompt,
+            (Some(prompt), Some(content)) => Prompt { content, ..prompt },
+            (None, Some(content)) => Prompt {
+                id: id.to_string(),
+                name: id.to_string(),
+                content,
+                description: None,
+                enabled: false,
+                created_at: None,
+                updated_at: None,
+                position: None,
+                tags: Vec::new(),
+                folder: None,
+            },
+            (None, None) => return Err(AppError::InvalidInput(format!("Prompt {id} not found"))),
+        };
+        Self::render_with(db, &prompt)
+    }
+
+    pub fn get_variables(db: &Arc<Database>) -> Result<IndexMap<String, String>, AppError> {
+        db.get_prompt_variables()
+    }
+
+    /// Sets a custom variable and re-renders the enabled prompts.
+    pub fn set_variable(db: &Arc<Database>, name: &str, value: &str) -> Result<(), AppError> {
+        prompt_template::validate_variable_name(name)?;
+        let targets = Self::active_targets(db)?;
+        for target in &targets {
+            Self::before_rewrite(db, target)?;
+        }
+        db.set_prompt_variable(name, value)?;
+        for target in &targets {
+            Self::write_enabled(db, target)?;
+        }
+        Ok(())
+    }
+
+    /// Refuses to remove a variable that an enabled prompt still uses.
+    pub fn delete_variable(db: &Arc<Database>, name: &str) -> Result<bool, AppError> {
+        let prompts = db.get_prompts()?;
+        let mut variables = db.get_prompt_variables()?;
+        if variables.shift_remove(name).is_none() {
+            return Ok(false);
+        }
+        let renderer = Renderer::new(&prompts, &variables);
+        let targets = Self::active_targets(db)?;
+        for target in &targets {
+            for prompt in Self::enabled_prompts(db, target)? {
+                renderer.render_prompt(&prompt)?;
+            }
+        }
+
+        for target in &targets {
+            Self::before_rewrite(db, target)?;
+        }
+        db.delete_prompt_variable(name)?;
+        for target in &targets {
+            Self::write_enabled(db, target)?;
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::config::tests::sandbox_home;
+    use crate::diff::DiffKind;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Mutex, MutexGuard};
+
+    fn database() -> Arc<Database> {
+        sandbox_home();
+        Arc::new(Database::open_in_memory().unwrap())
+    }
+
+    /// Held by tests that write the global AGENTS.md, which they share.
+    fn global_agents_md() -> (MutexGuard<'static, ()>, PathBuf) {
+        static GLOBAL: Mutex<()> = Mutex::new(());
+        let guard = GLOBAL.lock().unwrap_or_else(|e| e.into_inner());
+        let path = get_agents_md_path().unwrap();
+        let _ = std::fs::remove_file(&path);
+        (guard, path)
+    }
+
+    fn temp_dir() -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "open-switch-prompts-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A registered project in a fresh temporary directory.
+    fn project(db: &Arc<Database>) -> (Project, PathBuf) {
+        let dir = temp_dir();
+        let project = PromptService::add_project(db, &dir.display().to_string(), None).unwrap();
+        let path = PathBuf::from(&project.path).join("AGENTS.md");
+        (project, path)
+    }
+
+    fn prompt(id: &str, content: &str) -> Prompt {
+        Prompt {
+            id: id.to_string(),
+            name: id.to_string(),
+            content: content.to_string(),
+            description: None,
+            enabled: false,
+            created_at: None,
+            updated_at: None,
+            position: None,
+            tags: Vec::new(),
+            folder: None,
+        }
+    }
+
+    fn read(path: &Path) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn saving_from_the_ui_keeps_the_prompt_enabled() {
+        let db = database();
+        let (_guard, path) = global_agents_md();
+        let style = Prompt {
+            created_at: Some(1_700_000_000),
+            ..prompt("style", "Use tabs")
+        };
+        PromptService::upsert_prompt(&db, style).unwrap();
+        PromptService::upsert_prompt(&db, prompt("tests", "Write tests")).unwrap();
+        PromptService::set_prompt_enabled(&db, "tests", true, None).unwrap();
+        PromptService::set_prompt_enabled(&db, "style", true, None).unwrap();
+        let before = db.get_prompts().unwrap();
+
+        // The UI form always sends `enabled: false`, and no position or
+        // creation time.
+        PromptService::upsert_prompt(&db, prompt("style", "Use spaces")).unwrap();
+
+        let after = db.get_prompts().unwrap();
+        assert!(after["style"].enabled);
+        assert_eq!(after["style"].created_at, Some(1_700_000_000));
+        assert_eq!(after["style"].position, before["style"].position);
+        assert_eq!(
+            db.get_enabled_prompts().unwrap().iter().map(|p| p.id.as_str()).collect::<Vec<_>>(),
+            ["tests", "style"]
+        );
+        let file = read(&path);
+        assert!(file.contains("Use spaces") && file.contains("Write tests"), "{file}");
+    }
+
+    #[test]
+    fn edits_to_a_templated_prompt_are_saved_as_a_new_prompt() {
+        let db = database();
+        let (project, path) = project(&db);
+        db.set_prompt_variable("team", "Platform").unwrap();
+        PromptService::upsert_prompt(&db, prompt("greet", "Hello {{team}}")).unwrap();
+        PromptService::enable_prompt(&db, "greet", Some(&project.id)).unwrap();
+        assert!(read(&path).contains("Hello Platform"));
+
+        std::fs::write(&path, read(&path).replace("Hello Platform", "Hello Infra")).unwrap();
+        let target = PromptService::target(&db, Some(&project.id)).unwrap();
+        let edits = PromptService::edits(&db, &target, AgentsMdMode::WholeFile).unwrap().unwrap();
+        PromptService::backfill(&db, edits).unwrap();
+
+        let prompts = db.get_prompts().unwrap();
+        assert_eq!(prompts["greet"].content, "Hello {{team}}");
+        let copy = prompts.values().find(|p| p.id.starts_with("edited-")).unwrap();
+        assert!(copy.content.contains("Hello Infra"));
+        assert!(!copy.enabled);
+    }
+
+    #[test]
+    fn edits_to_agents_md_wait_for_a_resolution_unless_backfill_is_on() {
+        let db = database();
+        let (project, path) = project(&db);
+        PromptService::upsert_prompt(&db, prompt("style", "Use tabs")).unwrap();
+        PromptService::upsert_prompt(&db, prompt("tests", "Write tests")).unwrap();
+        PromptService::upsert_prompt(&db, prompt("docs", "Write docs")).unwrap();
+        PromptService::enable_prompt(&db, "style", Some(&project.id)).unwrap();
+        assert!(!PromptService::auto_backfill(&db).unwrap());
+
+        let edit = |from: &str, to: &str| {
+            let edited = read(&path).replace(from, to);
+            std::fs::write(&path, &edited).unwrap();
+            edited
+        };
+        let edited = edit("Use tabs", "Use spaces");
+        assert!(PromptService::set_prompt_enabled(&db, "tests", true, Some(&project.id)).is_err());
+        assert_eq!(read(&path), edited);
+        let drift = PromptService::get_drift(&db, Some(&project.id)).unwrap().unwrap();
+        assert_eq!(drift.prompts, ["style"]);
+        assert!(drift.diff.lines.iter().any(|l| l.kind == DiffKind::Insert && l.text == "Use spaces"));
+
+        PromptService::resolve_drift(&db, Some(&project.id), DriftResolution::KeepFile).unwrap();
+        assert_eq!(db.get_prompts().unwrap()["style"].content.trim_end(), "Use spaces");
+        assert!(PromptService::get_drift(&db, Some(&project.id)).unwrap().is_none());
+
+        edit("Use spaces", "Use nothing");
+        PromptService::resolve_drift(&db, Some(&project.id), DriftResolution::KeepStored).unwrap();
+        assert!(read(&path).contains("Use spaces"));
+        assert_eq!(db.get_prompts().unwrap()["style"].content.trim_end(), "Use spaces");
+
+        edit("Use spaces", "Use both");
+        PromptService::resolve_drift(&db, Some(&project.id), DriftResolution::SaveAsNew).unwrap();
+        let prompts = db.get_prompts().unwrap();
+        assert_eq!(prompts["style"].content.trim_end(), "Use spaces");
+        let copy = prompts.values().find(|p| p.id.starts_with("agents-md-")).unwrap();
+        assert!(copy.content.contains("Use both"));
+        assert!(read(&path).contains("Use spaces"));
+
+        PromptService::set_auto_backfill(&db, true).unwrap();
+        assert!(PromptService::auto_backfill(&db).unwrap());
+        edit("Use spaces", "Use tabs again");
+        PromptService::set_prompt_enabled(&db, "docs", true, Some(&project.id)).unwrap();
+        assert_eq!(db.get_prompts().unwrap()["style"].content.trim_end(), "Use tabs again");
+
+        PromptService::set_auto_backfill(&db, false).unwrap();
+        assert_eq!(db.get_setting(AUTO_BACKFILL).unwrap(), None);
+    }
+
+    #[test]
+    fn saving_records_a_version_with_its_source_and_author() {
+        let db = database();
+        PromptService::upsert_prompt(&db, prompt("style", "Use tabs")).unwrap();
+        PromptService::upsert_prompt(&db, prompt("style", "Use tabs")).unwrap();
+        PromptService::upsert_prompt(&db, prompt("style", "Use spaces")).unwrap();
+
+        let versions = PromptService::get_versions(&db, "style").unwrap();
+        assert_eq!(versions.iter().map(|v| v.version).collect::<Vec<_>>(), [2, 1]);
+        assert_eq!(versions[0].content, "Use spaces");
+        assert_eq!(versions[1].content, "Use tabs");
+        assert!(versions.iter().all(|v| v.source == PromptSource::Ui));
+        assert_eq!(versions[0].author, prompt_template::user_name());
+        assert!(PromptService::get_versions(&db, "missing").unwrap().is_empty());
+    }
+
+    #[test]
+    fn restoring_adds_a_revision_after_a_backfill() {
+        let db = database();
+        let (project, path) = project(&db);
+        PromptService::upsert_prompt(&db, prompt("style", "Use tabs")).unwrap();
+        PromptService::enable_prompt(&db, "style", Some(&project.id)).unwrap();
+
+        std::fs::write(&path, read(&path).replace("Use tabs", "Use spaces")).unwrap();
+        PromptService::resolve_drift(&db, Some(&project.id), DriftResolution::KeepFile).unwrap();
+
+        let versions = PromptService::get_versions(&db, "style").unwrap();
+        let sources: Vec<PromptSource> = versions.iter().map(|v| v.source).collect();
+        assert_eq!(sources, [PromptSource::Backfill, PromptSource::Ui]);
+        assert_eq!(versions[0].content.trim_end(), "Use spaces");
+
+        let restored = PromptService::restore_version(&db, versions[1].id).unwrap();
+        assert_eq!(restored.content, "Use tabs");
+        let versions = PromptService::get_versions(&db, "style").unwrap();
+        assert_eq!(versions.len(), 3);
+        assert_eq!((versions[0].version, versions[0].source), (3, PromptSource::Restore));
+        assert_eq!(versions[0].content, "Use tabs");
+        assert!(read(&path).contains("Use tabs"));
+
+        let diff = PromptService::diff_versions(&db, versions[1].id, versions[0].id).unwrap();
+        assert_eq!((diff.from.version, diff.to.version), (2, 3));
+        assert!(PromptService::restore_version(&db, -1).is_err());
+    }
+
+    #[test]
+    fn deleting_a_prompt_keeps_its_history() {
+        let db = database();
+        PromptService::upsert_prompt(&db, prompt("style", "Use tabs")).unwrap();
+        PromptService::upsert_prompt(&db, prompt("style", "Use spaces")).unwrap();
+        PromptService::delete_prompt(&db, "style").unwrap();
+        assert!(!db.get_prompts().unwrap().contains_key("style"));
+
+        let versions = PromptService::get_versions(&db, "style").unwrap();
+        assert_eq!(versions.len(), 2);
+        let restored = PromptService::restore_version(&db, versions[1].id).unwrap();
+        assert_eq!(restored.content, "Use tabs");
+        assert!(!restored.enabled);
+        assert_eq!(db.get_prompts().unwrap()["style"].name, "style");
+        assert_eq!(PromptService::get_versions(&db, "style").unwrap()[0].version, 3);
+    }
+
+    #[test]
+    fn tells_managed_files_from_edited_and_hand_written_ones() {
+        let db = database();
+        let (project, path) = project(&db);
+        let state = || PromptService::get_file_state(&db, Some(&project.id)).unwrap();
+        assert_eq!(state(), AgentsFileState::Missing);
+
+        std::fs::write(&path, "# Notes\n\nKept by hand\n").unwrap();
+        assert_eq!(state(), AgentsFileState::HandWritten);
+
+        PromptService::upsert_prompt(&db, prompt("style", "Use tabs")).unwrap();
+        PromptService::enable_prompt(&db, "style", Some(&project.id)).unwrap();
+        assert_eq!(state(), AgentsFileState::Managed);
+
+        std::fs::write(&path, read(&path).replace("Use tabs", "Use spaces")).unwrap();
+        assert_eq!(state(), AgentsFileState::Modified);
+
+        let projects = PromptService::get_projects(&db).unwrap();
+        assert_eq!(projects[0].agents_md, AgentsFileState::Modified);
+        assert_eq!(projects[0].prompts, ["style"]);
+    }
+
+    #[test]
+    fn projects_need_an_absolute_path_registered_once() {
+        let db = database();
+        let dir = temp_dir();
+        assert!(PromptService::add_project(&db, "relative/dir", None).is_err());
+        assert!(PromptService::add_project(&db, ".", None).is_err());
+        let missing = dir.join("missing").display().to_string();
+        assert!(PromptService::add_project(&db, &missing, None).is_err());
+
+        let added = PromptService::add_project(&db, &format!(" {} ", dir.display()), Some(" ".to_string())).unwrap();
+        assert_eq!(PathBuf::from(&added.path), dir.canonicalize().unwrap());
+        assert_eq!(Some(added.name.as_str()), dir.file_name().and_then(|n| n.to_str()));
+
+        let same = dir.join("sub").join("..");
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        for path in [dir.display().to_string(), same.display().to_string()] {
+            let error = PromptService::add_project(&db, &path, None).unwrap_err();
+            assert!(error.to_string().contains("already registered"), "{error}");
+        }
+        assert_eq!(db.get_projects().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn quotes_search_words_and_matches_the_last_as_a_prefix() {
+        assert_eq!(fts_query(""), None);
+        assert_eq!(fts_query("  -- * "), None);
+        assert_eq!(fts_query("rust").as_deref(), Some("\"rust\"*"));
+        assert_eq!(fts_query(" rust  iter ").as_deref(), Some("\"rust\" \"iter\"*"));
+        assert_eq!(
+            fts_query("NOT a\"b OR name:x (y)").as_deref(),
+            Some("\"NOT\" \"a\"\"b\" \"OR\" \"name:x\" \"(y)\"*")
+        );
+        assert_eq!(fts_query("rust -").as_deref(), Some("\"rust\"*"));
+    }
+
+    #[test]
+    fn searches_by_words_tags_and_folders() {
+        let db = database();
+        let save = |id: &str, name: &str, content: &str, tags: &[&str], folder: &str| {
+            let prompt = Prompt {
+                name: name.to_string(),
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                folder: Some(folder.to_string()),
+                ..prompt(id, content)
+            };
+            PromptService::upsert_prompt(&db, prompt).unwrap();
+        };
+        save("style", "Rust style", "Prefer iterators", &["lang", "style"], "team/backend");
+        save("review", "Review", "Check tests AND docs", &["style"], "team");
+        save("notes", "Notes", "Something rusty", &[], "teammates");
+        let search = |query: &str, tags: &[&str], folder: Option<&str>| -> Vec<String> {
+            let tags = tags.iter().map(|t| t.to_string()).collect();
+            let mut ids: Vec<String> = PromptService::search(&db, query, tags, folder, None)
+                .unwrap()
+                .into_iter()
+                .map(|hit| hit.prompt.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(search("rus", &[], None), ["notes", "style"]);
+        assert_eq!(search("rust iter", &[], None), ["style"]);
+        assert_eq!(search("rusty iter", &[], None), Vec::<String>::new());
+        // Operators and stray quotes are searched for as words.
+        assert_eq!(search("tests AND", &[], None), ["review"]);
+        assert_eq!(search("NOT \"docs", &[], None), Vec::<String>::new());
+        assert_eq!(search("docs OR", &[], None), Vec::<String>::new());
+
+        assert_eq!(search("", &["style"], None), ["review", "style"]);
+        assert_eq!(search("", &["style", "lang"], None), ["style"]);
+        assert_eq!(search("", &["style", "other"], None), Vec::<String>::new());
+        assert_eq!(search("prefer", &[" lang "], None), ["style"]);
+
+        assert_eq!(search("", &[], Some("team")), ["review", "style"]);
+        assert_eq!(search("", &[], Some("/team/backend/")), ["style"]);
+        assert_eq!(search("", &[], Some("teammates")), ["notes"]);
+        assert_eq!(search("", &[], None), ["notes", "review", "style"]);
+
+        let hits = PromptService::search(&db, "rust", Vec::new(), None, None).unwrap();
+        assert_eq!(hits[0].prompt.id, "style");
+        assert!(hits[0].name.iter().any(|part| part.highlight && part.text == "Rust"));
+    }
+
+    #[test]
+    fn search_follows_updates_and_deletes() {
+        let db = database();
+        PromptService::upsert_prompt(&db, prompt("review", "Check the docs")).unwrap();
+        PromptService::upsert_prompt(&db, prompt("notes", "Rusty notes")).unwrap();
+        let search = |query: &str| -> Vec<String> {
+            PromptService::search(&db, query, Vec::new(), None, None)
+                .unwrap()
+                .into_iter()
+                .map(|hit| hit.prompt.id)
+                .collect()
+        };
+        assert_eq!(search("docs"), ["review"]);
+
+        PromptService::upsert_prompt(&db, prompt("review", "Check the lint")).unwrap();
+        assert!(search("docs").is_empty());
+        assert_eq!(search("lint"), ["review"]);
+
+        PromptService::delete_prompt(&db, "notes").unwrap();
+        assert!(search("rusty").is_empty());
+        assert_eq!(search("check"), ["review"]);
+    }
+
+    #[test]
+    fn switching_to_region_mode_keeps_text_around_the_prompts() {
+        let db = database();
+        let (project, path) = project(&db);
+        PromptService::upsert_prompt(&db, prompt("style", "Use tabs")).unwrap();
+        PromptService::upsert_prompt(&db, prompt("tests", "Write tests")).unwrap();
+        PromptService::set_prompt_enabled(&db, "style", true, Some(&project.id)).unwrap();
+        PromptService::set_prompt_enabled(&db, "tests", true, Some(&project.id)).unwrap();
+        let written = read(&path);
+        std::fs::write(&path, format!("# Notes\n\n{written}\nKeep me\n")).unwrap();
+
+        PromptService::set_agents_md_mode(&db, AgentsMdMode::Region).unwrap();
+
+        let file = read(&path);
+        assert!(file.starts_with("# Notes\n\n<!-- open-switch:begin -->\n"), "{file}");
+        assert!(file.ends_with("<!-- open-switch:end -->\n\nKeep me\n"), "{file}");
+        assert_eq!(prompt_fragments::split_region(&file).unwrap().inner, written.trim_end());
+        // Nothing was backed up as a new prompt.
+        assert_eq!(db.get_prompts().unwrap().len(), 2);
+
+        // Later writes only touch the region.
+        PromptService::set_prompt_enabled(&db, "tests", false, Some(&project.id)).unwrap();
+        assert_eq!(
+            read(&path),
+            "# Notes\n\n<!-- open-switch:begin -->\nUse tabs\n<!-- open-switch:end -->\n\nKeep me\n"
+        );
+    }
+
+    #[test]
+    fn switching_to_region_mode_backs_up_an_edited_file() {
+        let db = database();
+        let (project, path) = project(&db);
+        PromptService::upsert_prompt(&db, prompt("style", "Use tabs")).unwrap();
+        PromptService::enable_prompt(&db, "style", Some(&project.id)).unwrap();
+        std::fs::write(&path, "Use spaces\n").unwrap();
+
+        assert!(PromptService::set_agents_md_mode(&db, AgentsMdMode::Region).is_err());
+        assert_eq!(read(&path), "Use spaces\n");
+        assert_eq!(PromptService::agents_md_mode(&db).unwrap(), AgentsMdMode::WholeFile);
+
+        PromptService::set_auto_backfill(&db, true).unwrap();
+        PromptService::set_agents_md_mode(&db, AgentsMdMode::Region).unwrap();
+
+        assert_eq!(db.get_prompts().unwrap()["style"].content.trim_end(), "Use spaces");
+        assert_eq!(
+            read(&path),
+            "<!-- open-switch:begin -->\nUse spaces\n<!-- open-switch:end -->\n"
+        );
+    }
+
+    #[test]
+    fn region_mode_refuses_unpaired_markers() {
+        let db = database();
+        let (project, path) = project(&db);
+        PromptService::upsert_prompt(&db, prompt("style", "Use tabs")).unwrap();
+        PromptService::upsert_prompt(&db, prompt("tests", "Write tests")).unwrap();
+        PromptService::enable_prompt(&db, "style", Some(&project.id)).unwrap();
+        PromptService::set_agents_md_mode(&db, AgentsMdMode::Region).unwrap();
+
+        let broken = "Mine\n<!-- open-switch:begin -->\nUse tabs\n";
+        std::fs::write(&path, broken).unwrap();
+        assert!(PromptService::set_prompt_enabled(&db, "tests", true, Some(&project.id)).is_err());
+        assert_eq!(read(&path), broken);
+    }
+
+    /// A database syncing with a new clone of `remote`.
+    fn synced_database(remote: &Path) -> Arc<Database> {
+        let db = database();
+        let work = temp_dir().join("work");
+        PromptService::set_sync(
+            &db,
+            Some(&work.display().to_string()),
+            Some(&remote.display().to_string()),
+            None,
+        )
+        .unwrap();
+        db
+    }
+
+    #[test]
+    fn syncs_prompts_through_a_shared_repository() {
+        let remote = temp_dir().join("prompts.git");
+        let output = std::process::Command::new("git")
+            .args(["init", "--quiet", "--bare"])
+            .arg(&remote)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let laptop = synced_database(&remote);
+        let desktop = synced_database(&remote);
+
+        PromptService::upsert_prompt(&laptop, prompt("style", "Use tabs")).unwrap();
+        let report = PromptService::sync(&laptop).unwrap();
+        assert_eq!(report.pushed, ["style"]);
+        assert!(report.pushed_commits);
+
+        let report = PromptService::sync(&desktop).unwrap();
+        assert_eq!(report.pulled, ["style"]);
+        assert_eq!(desktop.get_prompts().unwrap()["style"].content, "Use tabs");
+
+        // Edited on both machines since the last sync.
+        PromptService::upsert_prompt(&laptop, prompt("style", "Use spaces")).unwrap();
+        PromptService::sync(&laptop).unwrap();
+        PromptService::upsert_prompt(&desktop, prompt("style", "Use both")).unwrap();
+        let report = PromptService::sync(&desktop).unwrap();
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].kind, SyncConflictKind::BothChanged);
+        assert_eq!(desktop.get_prompts().unwrap()["style"].content, "Use both");
+
+        PromptService::resolve_sync_conflict(&desktop, "style", SyncResolution::Remote).unwrap();
+        assert_eq!(desktop.get_prompts().unwrap()["style"].content, "Use spaces");
+        let report = PromptService::sync(&desktop).unwrap();
+        assert!(report.conflicts.is_empty(), "{:?}", report.conflicts);
+
+        // Deleted on one machine, deleted on the other at the next sync.
+        PromptService::delete_prompt(&desktop, "style").unwrap();
+        PromptService::sync(&desktop).unwrap();
+        let report = PromptService::sync(&laptop).unwrap();
+        assert_eq!(report.deleted, ["style"]);
+        assert!(laptop.get_prompts().unwrap().is_empty());
+    }
 }
@@ -0,0 +1,412 @@
+use crate::error::AppError;
+use crate::prompt_template::user_name;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// Runs the `git` CLI in a working copy.
+pub struct Git {
+    root: PathBuf,
+}
+
+impl Git {
+    /// Opens the working copy containing `path`.
+    pub fn open(path: &Path) -> Result<Self, AppError> {
+        let git = Git { root: path.to_path_buf() };
+        let root = git.run(&["rev-parse", "--show-toplevel"]).map_err(|_| {
+            AppError::InvalidInput(format!("'{}' is not a git working copy", path.display()))
+        })?;
+        Ok(Git { root: PathBuf::from(root) })
+    }
+
+    pub fn clone_into(remote: &str, path: &Path) -> Result<Self, AppError> {
+        let parent = path.parent().unwrap_or(Path::new("."));
+        std::fs::create_dir_all(parent).map_err(|e| AppError::io(parent, e))?;
+        let output = Self::command(parent)
+            .args(["clone", "--"])
+            .arg(remote)
+            .arg(path)
+            .output()
+            .map_err(|e| AppError::Message(format!("Failed to run git: {e}")))?;
+        check("clone", output)?;
+        Self::open(path)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn command(dir: &Path) -> Command {
+        let mut cmd = Command::new("git");
+        cmd.current_dir(dir)
+            // Never wait for credentials on a terminal nobody sees.
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .env("GIT_ASKPASS", "")
+            .args(["-c", "core.quotePath=false"]);
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+            cmd.creation_flags(CREATE_NO_WINDOW);
+        }
+        cmd
+    }
+
+    fn output(&self, args: &[&str]) -> Result<Output, AppError> {
+        Self::command(&self.root)
+            .args(args)
+            .output()
+            .map_err(|e| AppError::Message(format!("Failed to run git: {e}")))
+    }
+
+    /// Runs a command and returns its trimmed stdout.
+    pub fn run(&self, args: &[&str]) -> Result<String, AppError> {
+        let stdout = check(args.first().copied().unwrap_or(""), self.output(args)?)?;
+        Ok(stdout.trim_end().to_string())
+    }
+
+    /// Runs a command whose exit status is the answer, e.g. `diff --quiet`.
+    pub fn succeeds(&self, args: &[&str]) -> Result<bool, AppError> {
+        Ok(self.output(args)?.status.success())
+    }
+
+    pub fn branch(&self) -> Result<String, AppError> {
+        self.run(&["symbolic-ref", "--short", "HEAD"])
+    }
+
+    pub fn has_commits(&self) -> Result<bool, AppError> {
+        self.succeeds(&["rev-parse", "--verify", "--quiet", "HEAD"])
+    }
+
+    /// The remote that branches are pushed to, preferring `origin`.
+    pub fn remote(&self) -> Result<Option<String>, AppError> {
+        let remotes = self.run(&["remote"])?;
+        let remotes: Vec<&str> = remotes.lines().collect();
+        Ok(remotes
+            .iter()
+            .find(|r| **r == "origin")
+            .or(remotes.first())
+            .map(|r| r.to_string()))
+    }
+
+    /// The remote-tracking branch to merge with: the configured upstream,
+    /// or the same branch name on the remote.
+    pub fn upstream(&self) -> Result<Option<String>, AppError> {
+        if let Ok(upstream) = self.run(&["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{u}"]) {
+            return Ok(Some(upstream));
+        }
+        let (Some(remote), Ok(branch)) = (self.remote()?, self.branch()) else {
+            return Ok(None);
+        };
+        let candidate = format!("{remote}/{branch}");
+        let exists = self.succeeds(&["rev-parse", "--verify", "--quiet", &format!("refs/remotes/{candidate}")])?;
+        Ok(exists.then_some(candidate))
+    }
+
+    pub fn is_ancestor(&self, ancestor: &str, of: &str) -> Result<bool, AppError> {
+        self.succeeds(&["merge-base", "--is-ancestor", ancestor, of])
+    }
+
+    /// Files directly inside `dir` at `rev`, as paths from the root.
+    pub fn list_files(&self, rev: &str, dir: &str) -> Result<Vec<String>, AppError> {
+        let prefix = if dir.is_empty() { String::new() } else { format!("{dir}/") };
+        let out = self.run(&["ls-tree", "-z", "--name-only", &format!("{rev}:{dir}")]);
+        let Ok(out) = out else {
+            // The directory does not exist at that revision yet.
+            return Ok(Vec::new());
+        };
+        Ok(out
+            .split('\0')
+            .filter(|name| !name.is_empty())
+            .map(|name| format!("{prefix}{name}"))
+            .collect())
+    }
+
+    pub fn show(&self, rev: &str, path: &str) -> Result<String, AppError> {
+        let output = self.output(&["show", &format!("{rev}:{path}")])?;
+        check("show", output)
+    }
+
+    /// Reads many files at `rev` with a single `git cat-file --batch`.
+    /// Files that do not exist there are `None`.
+    pub fn show_all(&self, rev: &str, paths: &[String]) -> Result<Vec<Option<String>>, AppError> {
+        if paths.is_empty() {
+            return Ok(Vec::new());
+        }
+        let failed = |e: std::io::Error| AppError::Message(format!("Failed to run git: {e}"));
+        let mut child = Self::command(&self.root)
+            .args(["cat-file", "--batch"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(failed)?;
+
+        // Written from another thread, since git answers while it reads.
+        let input: String = paths.iter().map(|path| format!("{rev}:{path}\n")).collect();
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+        let output = child.wait_with_output().map_err(failed)?;
+        let _ = writer.join();
+        if !output.status.success() {
+            return Err(failure("cat-file", &output));
+        }
+        parse_batch(&output.stdout, paths.len())
+            .ok_or_else(|| AppError::Message("git cat-file returned unexpected output".to_string()))
+    }
+
+    /// Runs a command that records commits, falling back to a local
+    /// identity when git has none configured.
+    pub fn run_as_author(&self, args: &[&str]) -> Result<String, AppError> {
+        let identity = if self.succeeds(&["config", "user.email"])? {
+            Vec::new()
+        } else {
+            let name = user_name().unwrap_or_else(|| "Open Switch".to_string());
+            vec![
+                "-c".to_string(),
+                format!("user.name={name}"),
+                "-c".to_string(),
+                "user.email=open-switch@localhost".to_string(),
+            ]
+        };
+        let mut full: Vec<&str> = identity.iter().map(String::as_str).collect();
+        full.extend(args);
+        self.run(&full)
+    }
+
+    pub fn commit(&self, message: &str) -> Result<(), AppError> {
+        self.run_as_author(&["commit", "--quiet", "--no-verify", "-m", message])
+            .map(|_| ())
+    }
+
+    pub fn is_merging(&self) -> Result<bool, AppError> {
+        self.succeeds(&["rev-parse", "--quiet", "--verify", "MERGE_HEAD"])
+    }
+}
+
+/// Splits `cat-file --batch` output: per object a `<oid> <type> <size>`
+/// line followed by its content and a newline, or a `<name> missing` line.
+fn parse_batch(mut out: &[u8], count: usize) -> Option<Vec<Option<String>>> {
+    let mut files = Vec::with_capacity(count);
+    for _ in 0..count {
+        let end = out.iter().position(|b| *b == b'\n')?;
+        let header = std::str::from_utf8(&out[..end]).ok()?;
+        out = &out[end + 1..];
+        if header.ends_with(" missing") || header.ends_with(" ambiguous") {
+            files.push(None);
+            continue;
+        }
+        let size: usize = header.rsplit(' ').next()?.parse().ok()?;
+        let content = out.get(..size)?;
+        files.push(Some(String::from_utf8_lossy(content).into_owned()));
+        out = out.get(size + 1..)?;
+    }
+    Some(files)
+}
+
+fn check(command: &str, output: Output) -> Result<String, AppError> {
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+    }
+    Err(failure(command, &output))
+}
+
+fn failure(command: &str, output: &Output) -> AppError {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let detail = if stderr.trim().is_empty() { stdout.trim() } else { stderr.trim() };
+    AppError::Message(format!("git {command} failed: {detail}"))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptSyncConfig {
+    /// Root of the working copy.
+    pub path: String,
+    /// Directory inside the working copy holding the prompt files; empty
+    /// for the root.
+    pub directory: String,
+}
+
+/// A prompt as of the last sync: the merge base for the next one.
+#[derive(Debug, Clone)]
+pub struct SyncedPrompt {
+    /// Path from the working copy root.
+    pub file: String,
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncConflictKind {
+    /// Edited here and in the repository since the last sync.
+    BothChanged,
+    /// Deleted here but edited in the repository.
+    DeletedHere,
+    /// Edited here but deleted in the repository.
+    DeletedThere,
+    /// Deleted in the repository, but still enabled here.
+    Enabled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncConflict {
+    #[serde(rename = "promptId")]
+    pub prompt_id: String,
+    pub name: String,
+    pub file: String,
+    pub kind: SyncConflictKind,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncResolution {
+    /// Keep this machine's version; the next sync pushes it.
+    Local,
+    /// Take the repository's version.
+    Remote,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncReport {
+    /// Prompts created or updated from the repository.
+    pub pulled: Vec<String>,
+    /// Prompts deleted because they were deleted in the repository.
+    pub deleted: Vec<String>,
+    /// Prompts changed here that were sent to the repository.
+    pub pushed: Vec<String>,
+    pub conflicts: Vec<SyncConflict>,
+    /// Files in the repository that could not be read.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    /// Whether commits were pushed; false without a remote.
+    #[serde(rename = "pushedCommits")]
+    pub pushed_commits: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptSyncStatus {
+    #[serde(flatten)]
+    pub config: PromptSyncConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream: Option<String>,
+    /// Local commits not pushed yet.
+    pub ahead: u32,
+    /// Fetched commits not merged yet.
+    pub behind: u32,
+    /// Conflicts as of the last fetch.
+    pub conflicts: Vec<SyncConflict>,
+    #[serde(rename = "lastSyncedAt", skip_serializing_if = "Option::is_none")]
+    pub last_synced_at: Option<i64>,
+}
+
+/// What to do with one prompt, from its fingerprint here, in the
+/// repository and at the last sync. `None` means absent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncAction {
+    InSync,
+    TakeRemote,
+    KeepLocal,
+    Conflict(SyncConflictKind),
+}
+
+pub fn classify(local: Option<&str>, remote: Option<&str>, base: Option<&str>) -> SyncAction {
+    if local == remote {
+        SyncAction::InSync
+    } else if local == base {
+        SyncAction::TakeRemote
+    } else if remote == base {
+        SyncAction::KeepLocal
+    } else if local.is_none() {
+        SyncAction::Conflict(SyncConflictKind::DeletedHere)
+    } else if remote.is_none() {
+        SyncAction::Conflict(SyncConflictKind::DeletedThere)
+    } else {
+        SyncAction::Conflict(SyncConflictKind::BothChanged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "open-switch-sync-{name}-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn init(dir: &Path) -> Git {
+        let output = Git::command(dir).args(["init", "--quiet"]).output().unwrap();
+        check("init", output).unwrap();
+        Git::open(dir).unwrap()
+    }
+
+    #[test]
+    fn classifies_against_the_last_sync() {
+        use SyncAction::*;
+        use SyncConflictKind::*;
+        let cases = [
+            ((Some("a"), Some("a"), Some("a")), InSync),
+            ((Some("b"), Some("b"), Some("a")), InSync),
+            ((Some("a"), Some("b"), Some("a")), TakeRemote),
+            ((None, Some("b"), None), TakeRemote),
+            ((Some("a"), None, Some("a")), TakeRemote),
+            ((Some("b"), Some("a"), Some("a")), KeepLocal),
+            ((Some("b"), None, None), KeepLocal),
+            ((Some("b"), Some("c"), Some("a")), Conflict(BothChanged)),
+            ((Some("b"), Some("c"), None), Conflict(BothChanged)),
+            ((None, Some("b"), Some("a")), Conflict(DeletedHere)),
+            ((Some("b"), None, Some("a")), Conflict(DeletedThere)),
+        ];
+        for ((local, remote, base), expected) in cases {
+            assert_eq!(classify(local, remote, base), expected, "{local:?} {remote:?} {base:?}");
+        }
+    }
+
+    #[test]
+    fn reads_many_files_in_one_batch() {
+        let dir = temp_dir("batch");
+        let git = init(&dir);
+        std::fs::create_dir_all(dir.join("prompts")).unwrap();
+        std::fs::write(dir.join("prompts/a.md"), "first\nline two\n").unwrap();
+        std::fs::write(dir.join("prompts/b c.md"), "").unwrap();
+        std::fs::write(dir.join("prompts/ü.md"), "unicode ✓").unwrap();
+        git.run(&["add", "--all"]).unwrap();
+        git.commit("Add prompts").unwrap();
+
+        let files = git.list_files("HEAD", "prompts").unwrap();
+        assert_eq!(files.len(), 3);
+        let mut paths = files.clone();
+        paths.push("prompts/missing.md".to_string());
+        let texts = git.show_all("HEAD", &paths).unwrap();
+        for (file, text) in files.iter().zip(&texts) {
+            assert_eq!(text.as_deref(), Some(git.show("HEAD", file).unwrap().as_str()), "{file}");
+        }
+        assert_eq!(texts[3], None);
+        assert!(git.show_all("HEAD", &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn clones_a_remote_that_looks_like_an_option() {
+        let dir = temp_dir("clone");
+        let output = Git::command(&dir)
+            .args(["init", "--quiet", "--bare", "--", "-bare.git"])
+            .output()
+            .unwrap();
+        check("init", output).unwrap();
+
+        let git = Git::clone_into("-bare.git", &dir.join("work")).unwrap();
+        assert_eq!(git.root().canonicalize().unwrap(), dir.join("work").canonicalize().unwrap());
+        assert_eq!(git.remote().unwrap().as_deref(), Some("origin"));
+    }
+}
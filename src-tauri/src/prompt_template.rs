@@ -0,0 +1,228 @@
+use crate::error::AppError;
+use crate::prompt::Prompt;
+use indexmap::IndexMap;
+
+/// Variables every prompt can use without defining them.
+pub const BUILTIN_VARIABLES: &[&str] = &["user.name", "user.home", "os", "arch", "date"];
+
+/// The local account name.
+pub fn user_name() -> Option<String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+        .filter(|name| !name.is_empty())
+}
+
+fn builtin(name: &str) -> Option<String> {
+    match name {
+        "user.name" => user_name(),
+        "user.home" => dirs::home_dir().map(|home| home.display().to_string()),
+        "os" => Some(
+            match std::env::consts::OS {
+                "macos" => "macOS",
+                "windows" => "Windows",
+                "linux" => "Linux",
+                other => other,
+            }
+            .to_string(),
+        ),
+        "arch" => Some(std::env::consts::ARCH.to_string()),
+        "date" => Some(chrono::Local::now().format("%Y-%m-%d").to_string()),
+        _ => None,
+    }
+}
+
+pub fn validate_variable_name(name: &str) -> Result<(), AppError> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if !valid {
+        return Err(AppError::InvalidInput(format!(
+            "Variable name '{name}' may only contain letters, digits, '_', '-' and '.'"
+        )));
+    }
+    if BUILTIN_VARIABLES.contains(&name) {
+        return Err(AppError::InvalidInput(format!("'{name}' is a built-in variable")));
+    }
+    Ok(())
+}
+
+/// Renders `{{name}}` variables and `{{> prompt-id}}` includes. `\{{` is a
+/// literal `{{`.
+pub struct Renderer<'a> {
+    prompts: &'a IndexMap<String, Prompt>,
+    variables: &'a IndexMap<String, String>,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(
+        prompts: &'a IndexMap<String, Prompt>,
+        variables: &'a IndexMap<String, String>,
+    ) -> Self {
+        Self { prompts, variables }
+    }
+
+    pub fn render_prompt(&self, prompt: &Prompt) -> Result<String, AppError> {
+        self.render(&prompt.id, &prompt.content, &mut Vec::new())
+    }
+
+    fn render(&self, id: &str, content: &str, stack: &mut Vec<String>) -> Result<String, AppError> {
+        stack.push(id.to_string());
+        let mut out = String::with_capacity(content.len());
+        let mut rest = content;
+
+        while let Some(start) = rest.find("{{") {
+            if rest[..start].ends_with('\\') {
+                out.push_str(&rest[..start - 1]);
+                out.push_str("{{");
+                rest = &rest[start + 2..];
+                continue;
+            }
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let end = after.find("}}").ok_or_else(|| {
+                AppError::InvalidInput(format!("Unclosed '{{{{' in prompt '{id}'"))
+            })?;
+            let tag = after[..end].trim();
+            rest = &after[end + 2..];
+
+            if let Some(include) = tag.strip_prefix('>') {
+                out.push_str(&self.include(id, include.trim(), stack)?);
+            } else {
+                out.push_str(&self.variable(id, tag)?);
+            }
+        }
+        out.push_str(rest);
+
+        stack.pop();
+        Ok(out)
+    }
+
+    fn include(&self, id: &str, target: &str, stack: &mut Vec<String>) -> Result<String, AppError> {
+        if stack.iter().any(|seen| seen == target) {
+            let mut cycle = stack.clone();
+            cycle.push(target.to_string());
+            return Err(AppError::InvalidInput(format!(
+                "Include cycle: {}",
+                cycle.join(" -> ")
+            )));
+        }
+        let prompt = self.prompts.get(target).ok_or_else(|| {
+            AppError::InvalidInput(format!("Prompt '{id}' includes unknown prompt '{target}'"))
+        })?;
+        let rendered = self.render(target, &prompt.content, stack)?;
+        Ok(rendered.trim_end_matches('\n').to_string())
+    }
+
+    fn variable(&self, id: &str, name: &str) -> Result<String, AppError> {
+        if name.is_empty() {
+            return Err(AppError::InvalidInput(format!("Empty '{{{{}}}}' in prompt '{id}'")));
+        }
+        self.variables
+            .get(name)
+            .cloned()
+            .or_else(|| builtin(name))
+            .ok_or_else(|| {
+                AppError::InvalidInput(format!("Undefined variable '{name}' in prompt '{id}'"))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompts(entries: &[(&str, &str)]) -> IndexMap<String, Prompt> {
+        entries
+            .iter()
+            .map(|(id, content)| {
+                let prompt = Prompt {
+                    id: id.to_string(),
+                    name: id.to_string(),
+                    content: content.to_string(),
+                    description: None,
+                    enabled: false,
+                    created_at: None,
+                    updated_at: None,
+                    position: None,
+                    tags: Vec::new(),
+                    folder: None,
+                };
+                (id.to_string(), prompt)
+            })
+            .collect()
+    }
+
+    fn render(prompts: &IndexMap<String, Prompt>, id: &str) -> Result<String, String> {
+        let variables = IndexMap::from([("team".to_string(), "Platform".to_string())]);
+        Renderer::new(prompts, &variables)
+            .render_prompt(&prompts[id])
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn renders_variables_and_includes() {
+        let prompts = prompts(&[
+            ("main", "# {{ team }}\n\n{{> style}}\n\nRuns on {{os}}."),
+            ("style", "Be brief.\n\n"),
+        ]);
+        let rendered = render(&prompts, "main").unwrap();
+        assert!(rendered.starts_with("# Platform\n\nBe brief.\n\nRuns on "));
+        assert!(!rendered.contains("{{"));
+    }
+
+    #[test]
+    fn keeps_escaped_braces_literal() {
+        let prompts = prompts(&[("main", r"Write \{{team}} for {{team}}.")]);
+        assert_eq!(render(&prompts, "main").unwrap(), "Write {{team}} for Platform.");
+    }
+
+    #[test]
+    fn rejects_undefined_and_malformed_tags() {
+        let prompts = prompts(&[
+            ("undefined", "Hello {{nobody}}"),
+            ("empty", "Hello {{ }}"),
+            ("unclosed", "Hello {{team"),
+            ("unknown", "{{> missing}}"),
+        ]);
+        assert!(render(&prompts, "undefined")
+            .unwrap_err()
+            .contains("Undefined variable 'nobody' in prompt 'undefined'"));
+        assert!(render(&prompts, "empty").unwrap_err().contains("Empty"));
+        assert!(render(&prompts, "unclosed").unwrap_err().contains("Unclosed"));
+        assert!(render(&prompts, "unknown")
+            .unwrap_err()
+            .contains("includes unknown prompt 'missing'"));
+    }
+
+    #[test]
+    fn reports_include_cycles() {
+        let prompts = prompts(&[
+            ("a", "{{> b}}"),
+            ("b", "{{> c}}"),
+            ("c", "{{> a}}"),
+            ("self", "{{> self}}"),
+        ]);
+        assert!(render(&prompts, "a")
+            .unwrap_err()
+            .contains("Include cycle: a -> b -> c -> a"));
+        assert!(render(&prompts, "self")
+            .unwrap_err()
+            .contains("Include cycle: self -> self"));
+    }
+
+    #[test]
+    fn allows_including_the_same_prompt_twice() {
+        let prompts = prompts(&[("main", "{{> part}} and {{> part}}"), ("part", "x")]);
+        assert_eq!(render(&prompts, "main").unwrap(), "x and x");
+    }
+
+    #[test]
+    fn validates_variable_names() {
+        assert!(validate_variable_name("team.lead-name_2").is_ok());
+        assert!(validate_variable_name("").is_err());
+        assert!(validate_variable_name("has space").is_err());
+        assert!(validate_variable_name("os").is_err());
+    }
+}
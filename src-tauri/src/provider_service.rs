@@ -0,0 +1,295 @@
+use crate::config::{
+    get_auth_config, get_opencode_config, insert_after, rename_key, reorder_keys,
+    save_auth_config, save_opencode_config, AuthConfig, OpenCodeConfig,
+};
+use crate::credential_service::CredentialService;
+use crate::database::Database;
+use crate::error::AppError;
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RenameResult {
+    /// Config paths whose references were rewritten, e.g. `agent.build.model`.
+    pub references: Vec<String>,
+    #[serde(rename = "credentialMoved")]
+    pub credential_moved: bool,
+}
+
+pub fn validate_new_name(kind: &str, old: &str, new: &str) -> Result<(), AppError> {
+    if new.trim().is_empty() || new.trim() != new {
+        return Err(AppError::InvalidInput(format!(
+            "{kind} name must be non-empty without surrounding spaces"
+        )));
+    }
+    if new == old {
+        return Err(AppError::InvalidInput(format!("{kind} is already named '{new}'")));
+    }
+    Ok(())
+}
+
+/// Writes auth.json first, then opencode.json. If the second write fails
+/// the first is undone, so the two files never disagree about a provider.
+fn save_both(
+    auth: Option<&AuthConfig>,
+    original_auth: &AuthConfig,
+    config: &OpenCodeConfig,
+) -> Result<(), AppError> {
+    if let Some(auth) = auth {
+        save_auth_config(auth)?;
+    }
+    if let Err(e) = save_opencode_config(config) {
+        if auth.is_some() {
+            if let Err(rollback) = save_auth_config(original_auth) {
+                log::error!("Failed to roll back auth.json after a failed config write: {rollback}");
+            }
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+pub struct ProviderService;
+
+impl ProviderService {
+    fn check_ids(config: &OpenCodeConfig, old: &str, new: &str) -> Result<(), AppError> {
+        validate_new_name("Provider", old, new)?;
+        if new.contains('/') || new.chars().any(char::is_whitespace) {
+            return Err(AppError::InvalidInput(
+                "Provider id cannot contain '/' or whitespace".to_string(),
+            ));
+        }
+        if !config.provider.contains_key(old) {
+            return Err(AppError::InvalidInput(format!("Provider '{old}' not found")));
+        }
+        if config.provider.contains_key(new) {
+            return Err(AppError::InvalidInput(format!("Provider '{new}' already exists")));
+        }
+        Ok(())
+    }
+
+    /// Renames a provider together with its auth.json credential, its
+    /// credential metadata and every `provider/model` reference.
+    pub fn rename(db: &Arc<Database>, old: &str, new: &str) -> Result<RenameResult, AppError> {
+        let mut config = get_opencode_config()?;
+        Self::check_ids(&config, old, new)?;
+
+        let original_auth = get_auth_config()?;
+        if original_auth.contains_key(old) && original_auth.contains_key(new) {
+            return Err(AppError::InvalidInput(format!(
+                "auth.json already has a credential for '{new}'"
+            )));
+        }
+
+        rename_key(&mut config.provider, old, new);
+        let references = config.rename_provider_references(old, new);
+
+        let mut auth = original_auth.clone();
+        let credential_moved = rename_key(&mut auth, old, new);
+
+        // The records move first so a clash fails before any file changes,
+        // and move back if the files cannot be written.
+        let records_moved = db.rename_credential_records(old, new)?;
+        if let Err(e) = save_both(credential_moved.then_some(&auth), &original_auth, &config) {
+            if records_moved {
+                if let Err(rollback) = db.rename_credential_records(new, old) {
+                    log::error!("Failed to move credential records back to {old}: {rollback}");
+                }
+            }
+            return Err(e);
+        }
+
+        log::info!("Renamed provider {old} -> {new}");
+        Ok(RenameResult {
+            references,
+            credential_moved,
+        })
+    }
+
+    /// Copies a provider, and its credential if it has one, under a new id.
+    /// Model references keep pointing at the original.
+    pub fn duplicate(db: &Arc<Database>, source: &str, new: &str) -> Result<bool, AppError> {
+        let mut config = get_opencode_config()?;
+        Self::check_ids(&config, source, new)?;
+
+        let original_auth = get_auth_config()?;
+        if original_auth.contains_key(new) {
+            return Err(AppError::InvalidInput(format!(
+                "auth.json already has a credential for '{new}'"
+            )));
+        }
+
+        if let Some(provider) = config.provider.get(source).cloned() {
+            insert_after(&mut config.provider, source, new.to_string(), provider);
+        }
+        let mut auth = original_auth.clone();
+        let credential = original_auth.get(source).cloned();
+        let copied = credential.is_some();
+        if let Some(credential) = credential {
+            insert_after(&mut auth, source, new.to_string(), credential);
+        }
+
+        save_both(copied.then_some(&auth), &original_auth, &config)?;
+        if copied {
+            CredentialService::record_set(db, new)?;
+        }
+
+        log::info!("Duplicated provider {source} -> {new}");
+        Ok(copied)
+    }
+
+    pub fn reorder(order: &[String]) -> Result<(), AppError> {
+        let mut config = get_opencode_config()?;
+        reorder_keys(&mut config.provider, order)?;
+        save_opencode_config(&config)
+    }
+
+    pub fn reorder_models(provider_id: &str, order: &[String]) -> Result<(), AppError> {
+        let mut config = get_opencode_config()?;
+        let provider = config.provider.get_mut(provider_id).ok_or_else(|| {
+            AppError::InvalidInput(format!("Provider '{provider_id}' not found"))
+        })?;
+        reorder_keys(&mut provider.models, order)?;
+        save_opencode_config(&config)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::config::tests::config_files;
+    use crate::config::Credential;
+    use crate::credential_service::CredentialMetadata;
+    use serde_json::{json, Map};
+
+    fn api_key(key: &str) -> Credential {
+        Credential::Api {
+            key: key.to_string(),
+            extra: Map::new(),
+        }
+    }
+
+    fn metadata(id: &str, note: &str) -> CredentialMetadata {
+        CredentialMetadata {
+            id: id.to_string(),
+            created_at: Some(100),
+            last_rotated_at: None,
+            expires_at: None,
+            owner_note: Some(note.to_string()),
+        }
+    }
+
+    /// An opencode.json with an `openai` provider referenced from every
+    /// place a model can be named, and a database that knows its key.
+    fn setup() -> Arc<Database> {
+        let config: OpenCodeConfig = serde_json::from_value(json!({
+            "provider": {
+                "openai": {
+                    "npm": "@ai-sdk/openai",
+                    "name": "OpenAI",
+                    "options": { "baseURL": "https://api.openai.com/v1" },
+                    "models": { "gpt-4o": { "name": "GPT-4o" } }
+                },
+                "local": {
+                    "npm": "@ai-sdk/openai-compatible",
+                    "name": "Local",
+                    "options": { "baseURL": "http://localhost:11434/v1" },
+                    "models": {}
+                }
+            },
+            "model": "openai/gpt-4o",
+            "small_model": "local/llama",
+            "agent": { "build": { "model": "openai/gpt-4o" } },
+            "enabled_providers": ["openai", "local"]
+        }))
+        .unwrap();
+        save_opencode_config(&config).unwrap();
+        save_auth_config(&AuthConfig::from([("openai".to_string(), api_key("sk-openai-1234567890"))])).unwrap();
+
+        let db = Arc::new(Database::open_in_memory().unwrap());
+        db.save_credential_metadata(&metadata("openai", "team")).unwrap();
+        db.add_credential_history("openai", "{\"type\":\"api\",\"key\":\"sk-old\"}", 100, 200).unwrap();
+        db
+    }
+
+    #[test]
+    fn rename_moves_references_credential_and_records() {
+        let _files = config_files();
+        let db = setup();
+        // Left behind by a provider that was deleted outside the app.
+        db.save_credential_metadata(&metadata("work", "stale")).unwrap();
+        db.add_credential_history("work", "{}", 50, 60).unwrap();
+
+        let result = ProviderService::rename(&db, "openai", "work").unwrap();
+        assert!(result.credential_moved);
+        assert_eq!(result.references, ["model", "agent.build.model", "enabled_providers"]);
+
+        let config = get_opencode_config().unwrap();
+        assert_eq!(config.provider.keys().collect::<Vec<_>>(), ["work", "local"]);
+        assert_eq!(config.model.as_deref(), Some("work/gpt-4o"));
+        assert_eq!(config.small_model.as_deref(), Some("local/llama"));
+        assert_eq!(config.agent.unwrap()["build"]["model"], "work/gpt-4o");
+        assert_eq!(config.extra["enabled_providers"], json!(["work", "local"]));
+
+        let auth = get_auth_config().unwrap();
+        assert!(!auth.contains_key("openai"));
+        assert!(matches!(&auth["work"], Credential::Api { key, .. } if key == "sk-openai-1234567890"));
+
+        let metadata = db.get_credential_metadata().unwrap();
+        assert_eq!(metadata.len(), 1);
+        assert_eq!(metadata[0].id, "work");
+        assert_eq!(metadata[0].owner_note.as_deref(), Some("team"));
+        let history = db.get_credential_history("work").unwrap();
+        assert_eq!(history.len(), 1);
+        assert!(history[0].credential_json.contains("sk-old"));
+        assert!(db.get_credential_history("openai").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rename_refuses_taken_ids_without_touching_anything() {
+        let _files = config_files();
+        let db = setup();
+        let mut auth = get_auth_config().unwrap();
+        auth.insert("work".to_string(), api_key("sk-work-1234567890"));
+        save_auth_config(&auth).unwrap();
+        let before = (
+            std::fs::read_to_string(crate::config::get_config_path().unwrap()).unwrap(),
+            std::fs::read_to_string(crate::config::get_auth_path().unwrap()).unwrap(),
+        );
+
+        assert!(ProviderService::rename(&db, "openai", "local").is_err());
+        assert!(ProviderService::rename(&db, "openai", "work").is_err());
+        assert!(ProviderService::rename(&db, "openai", "a/b").is_err());
+        assert!(ProviderService::rename(&db, "missing", "other").is_err());
+
+        let after = (
+            std::fs::read_to_string(crate::config::get_config_path().unwrap()).unwrap(),
+            std::fs::read_to_string(crate::config::get_auth_path().unwrap()).unwrap(),
+        );
+        assert_eq!(before, after);
+        assert_eq!(db.get_credential_metadata().unwrap()[0].id, "openai");
+    }
+
+    #[test]
+    fn duplicate_copies_the_credential_but_not_references() {
+        let _files = config_files();
+        let db = setup();
+
+        assert!(ProviderService::duplicate(&db, "openai", "openai-copy").unwrap());
+        let config = get_opencode_config().unwrap();
+        assert_eq!(config.provider.keys().collect::<Vec<_>>(), ["openai", "openai-copy", "local"]);
+        assert_eq!(config.model.as_deref(), Some("openai/gpt-4o"));
+        assert_eq!(config.extra["enabled_providers"], json!(["openai", "local"]));
+
+        let auth = get_auth_config().unwrap();
+        assert!(matches!(&auth["openai-copy"], Credential::Api { key, .. } if key == "sk-openai-1234567890"));
+        assert!(auth.contains_key("openai"));
+        let ids: Vec<String> = db.get_credential_metadata().unwrap().into_iter().map(|m| m.id).collect();
+        assert_eq!(ids, ["openai", "openai-copy"]);
+        assert!(db.get_credential_history("openai-copy").unwrap().is_empty());
+
+        // Without a credential only the provider is copied.
+        assert!(!ProviderService::duplicate(&db, "local", "local-copy").unwrap());
+        assert!(!get_auth_config().unwrap().contains_key("local-copy"));
+    }
+}
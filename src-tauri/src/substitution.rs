@@ -0,0 +1,490 @@
+use crate::config::{get_config_path, get_opencode_config, save_opencode_config, OpenCodeConfig};
+use crate::error::AppError;
+use crate::permissions;
+use crate::secrets::{mask_secret, SecretTarget};
+use serde::{Deserialize, Serialize};
+use indexmap::IndexMap;
+use std::path::{Path, PathBuf};
+
+// ============== Placeholder parsing ==============
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferenceKind {
+    Env,
+    File,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub kind: ReferenceKind,
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+pub fn find_references(value: &str) -> Vec<Reference> {
+    let mut references = Vec::new();
+    let mut offset = 0;
+
+    while let Some(pos) = value[offset..].find('{') {
+        let start = offset + pos;
+        let rest = &value[start + 1..];
+        let parsed = [("env:", ReferenceKind::Env), ("file:", ReferenceKind::File)]
+            .into_iter()
+            .find(|(prefix, _)| rest.starts_with(prefix))
+            .and_then(|(prefix, kind)| {
+                let body = &rest[prefix.len()..];
+                body.find('}').map(|close| (kind, prefix.len(), &body[..close]))
+            });
+
+        match parsed {
+            Some((kind, prefix_len, name)) if !name.is_empty() => {
+                let end = start + 1 + prefix_len + name.len() + 1;
+                references.push(Reference {
+                    kind,
+                    name: name.trim().to_string(),
+                    start,
+                    end,
+                });
+                offset = end;
+            }
+            _ => offset = start + 1,
+        }
+    }
+
+    references
+}
+
+pub fn has_references(value: &str) -> bool {
+    !find_references(value).is_empty()
+}
+
+// ============== Resolution ==============
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum ReferenceStatus {
+    Resolved,
+    UnsetVariable,
+    MissingFile,
+    Unreadable { error: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReferenceReport {
+    pub target: SecretTarget,
+    pub kind: ReferenceKind,
+    pub reference: String,
+    #[serde(rename = "resolvedPath", skip_serializing_if = "Option::is_none")]
+    pub resolved_path: Option<String>,
+    #[serde(flatten)]
+    pub status: ReferenceStatus,
+    /// Masked preview of the whole value after substitution, present only
+    /// when every placeholder in it resolved.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<String>,
+}
+
+pub fn config_dir() -> Result<PathBuf, AppError> {
+    let path = get_config_path()?;
+    path.parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| AppError::Config("Config path has no parent directory".into()))
+}
+
+pub fn resolve_file_path(path: &str, base_dir: &Path) -> PathBuf {
+    if let Some(rest) = path.strip_prefix("~/") {
+        if let Some(home) = dirs::home_dir() {
+            return home.join(rest);
+        }
+    }
+    let path = Path::new(path);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        base_dir.join(path)
+    }
+}
+
+fn resolve_reference(
+    reference: &Reference,
+    base_dir: &Path,
+) -> (Result<String, ReferenceStatus>, Option<PathBuf>) {
+    match reference.kind {
+        ReferenceKind::Env => match std::env::var(&reference.name) {
+            Ok(value) => (Ok(value), None),
+            Err(_) => (Err(ReferenceStatus::UnsetVariable), None),
+        },
+        ReferenceKind::File => {
+            let path = resolve_file_path(&reference.name, base_dir);
+            let result = if !path.exists() {
+                Err(ReferenceStatus::MissingFile)
+            } else {
+                std::fs::read_to_string(&path)
+                    .map(|content| content.trim().to_string())
+                    .map_err(|e| ReferenceStatus::Unreadable {
+                        error: e.to_string(),
+                    })
+            };
+            (result, Some(path))
+        }
+    }
+}
+
+/// Substitutes every placeholder in `value`, returning `None` if any of them
+/// could not be resolved.
+pub fn resolve_value(value: &str, base_dir: &Path) -> Option<String> {
+    let mut resolved = String::with_capacity(value.len());
+    let mut last = 0;
+    for reference in find_references(value) {
+        resolved.push_str(&value[last..reference.start]);
+        resolved.push_str(&resolve_reference(&reference, base_dir).0.ok()?);
+        last = reference.end;
+    }
+    resolved.push_str(&value[last..]);
+    Some(resolved)
+}
+
+fn report_value(
+    target: SecretTarget,
+    value: &str,
+    base_dir: &Path,
+    reports: &mut Vec<ReferenceReport>,
+) {
+    let references = find_references(value);
+    if references.is_empty() {
+        return;
+    }
+
+    let preview = resolve_value(value, base_dir).map(|v| mask_secret(&v));
+    for reference in references {
+        let (result, path) = resolve_reference(&reference, base_dir);
+        reports.push(ReferenceReport {
+            target: target.clone(),
+            kind: reference.kind,
+            reference: reference.name,
+            resolved_path: path.map(|p| p.display().to_string()),
+            status: result.err().unwrap_or(ReferenceStatus::Resolved),
+            preview: preview.clone(),
+        });
+    }
+}
+
+fn report_map(
+    map: Option<&IndexMap<String, String>>,
+    target: impl Fn(&str) -> SecretTarget,
+    base_dir: &Path,
+    reports: &mut Vec<ReferenceReport>,
+) {
+    for (name, value) in map.into_iter().flatten() {
+        report_value(target(name), value, base_dir, reports);
+    }
+}
+
+pub fn scan_config(config: &OpenCodeConfig, base_dir: &Path) -> Vec<ReferenceReport> {
+    let mut reports = Vec::new();
+
+    for (id, provider) in &config.provider {
+        if let Some(ref key) = provider.options.api_key {
+            let target = SecretTarget::ProviderApiKey {
+                provider: id.clone(),
+            };
+            report_value(target, key, base_dir, &mut reports);
+        }
+        let target = |name: &str| SecretTarget::ProviderHeader {
+            provider: id.clone(),
+            name: name.to_string(),
+        };
+        report_map(provider.options.headers.as_ref(), target, base_dir, &mut reports);
+    }
+
+    for (server_name, server) in config.mcp.iter().flatten() {
+        let env_target = |name: &str| SecretTarget::McpEnvironment {
+            server: server_name.clone(),
+            name: name.to_string(),
+        };
+        report_map(server.environment.as_ref(), env_target, base_dir, &mut reports);
+
+        let header_target = |name: &str| SecretTarget::McpHeader {
+            server: server_name.clone(),
+            name: name.to_string(),
+        };
+        report_map(server.headers.as_ref(), header_target, base_dir, &mut reports);
+
+        if let Some(ref url) = server.url {
+            let target = SecretTarget::McpUrl {
+                server: server_name.clone(),
+            };
+            report_value(target, url, base_dir, &mut reports);
+        }
+        for (index, argument) in server.command.iter().flatten().enumerate() {
+            let target = SecretTarget::McpArgument {
+                server: server_name.clone(),
+                index,
+            };
+            report_value(target, argument, base_dir, &mut reports);
+        }
+    }
+
+    reports
+}
+
+pub fn get_reference_reports() -> Result<Vec<ReferenceReport>, AppError> {
+    let config = get_opencode_config()?;
+    Ok(scan_config(&config, &config_dir()?))
+}
+
+// ============== Converting inline secrets ==============
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ReferenceRequest {
+    Env { name: String },
+    File { path: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversionResult {
+    pub placeholder: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+/// Replaces an inline secret with a placeholder. File references get the
+/// secret written to an owner-only file; env references only rewrite the
+/// config, so the caller is told when the variable still has to be exported.
+pub fn convert_to_reference(
+    target: &SecretTarget,
+    request: &ReferenceRequest,
+) -> Result<ConversionResult, AppError> {
+    let mut config = get_opencode_config()?;
+    let base_dir = config_dir()?;
+    let description = target.describe();
+
+    let value = target.value_mut(&mut config).ok_or_else(|| {
+        AppError::InvalidInput(format!("Secret '{description}' not found in opencode.json"))
+    })?;
+    if has_references(value) {
+        return Err(AppError::InvalidInput(format!(
+            "Secret '{description}' already uses a placeholder"
+        )));
+    }
+
+    let (placeholder, warning) = match request {
+        ReferenceRequest::Env { name } => {
+            let name = name.trim();
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(AppError::InvalidInput(format!(
+                    "Invalid environment variable name: '{name}'"
+                )));
+            }
+            let warning = match std::env::var(name) {
+                Ok(current) if current == *value => None,
+                Ok(_) => Some(format!("{name} is set but holds a different value")),
+                Err(_) => Some(format!("Export {name} before starting OpenCode")),
+            };
+            (format!("{{env:{name}}}"), warning)
+        }
+        ReferenceRequest::File { path } => {
+            let resolved = resolve_file_path(path, &base_dir);
+            if resolved.exists() {
+                return Err(AppError::InvalidInput(format!(
+                    "Refusing to overwrite existing file: {}",
+                    resolved.display()
+                )));
+            }
+            if let Some(parent) = resolved.parent() {
+                if !parent.exists() {
+                    permissions::create_private_dir(parent)?;
+                }
+            }
+            permissions::write_private_file(&resolved, value)?;
+            (format!("{{file:{path}}}"), None)
+        }
+    };
+
+    *value = placeholder.clone();
+    save_opencode_config(&config)?;
+    log::info!("Converted {description} to {placeholder}");
+
+    Ok(ConversionResult {
+        placeholder,
+        warning,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn names(value: &str) -> Vec<(ReferenceKind, String)> {
+        find_references(value)
+            .into_iter()
+            .map(|r| (r.kind, r.name))
+            .collect()
+    }
+
+    #[test]
+    fn finds_placeholders_in_text() {
+        assert_eq!(
+            names("Bearer {env:TOKEN} and {file:~/key}"),
+            [
+                (ReferenceKind::Env, "TOKEN".to_string()),
+                (ReferenceKind::File, "~/key".to_string())
+            ]
+        );
+        let reference = &find_references("x{env:A}y")[0];
+        assert_eq!((reference.start, reference.end), (1, 8));
+    }
+
+    #[test]
+    fn reads_nested_and_malformed_braces_like_opencode() {
+        // OpenCode matches `{env:[^}]+}`, so the name ends at the first `}`.
+        assert_eq!(names("{env:{A}}"), [(ReferenceKind::Env, "{A".to_string())]);
+        assert_eq!(names("{{env:A}}"), [(ReferenceKind::Env, "A".to_string())]);
+        assert!(names("{env:}").is_empty());
+        assert!(names("{env:A").is_empty());
+        assert!(names("{secret:A} {ENV:A} env:A}").is_empty());
+        assert!(!has_references("plain {text}"));
+    }
+
+    #[test]
+    fn resolves_environment_variables() {
+        std::env::set_var("OPEN_SWITCH_TEST_SET", "sk-from-env");
+        std::env::remove_var("OPEN_SWITCH_TEST_UNSET");
+        let base = Path::new("/nonexistent");
+
+        assert_eq!(
+            resolve_value("Bearer {env:OPEN_SWITCH_TEST_SET}", base).as_deref(),
+            Some("Bearer sk-from-env")
+        );
+        assert_eq!(resolve_value("{env:OPEN_SWITCH_TEST_UNSET}", base), None);
+        assert_eq!(resolve_value("no placeholders", base).as_deref(), Some("no placeholders"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolves_files_from_home_and_the_config_directory() {
+        crate::config::tests::sandbox_home();
+        let home = dirs::home_dir().unwrap();
+        std::fs::write(home.join("substitution-key"), "sk-from-file\n").unwrap();
+        let base = home.join("config");
+        std::fs::create_dir_all(&base).unwrap();
+        std::fs::write(base.join("relative-key"), "sk-relative").unwrap();
+
+        assert_eq!(
+            resolve_value("{file:~/substitution-key}", &base).as_deref(),
+            Some("sk-from-file")
+        );
+        assert_eq!(
+            resolve_value("{file:relative-key}", &base).as_deref(),
+            Some("sk-relative")
+        );
+        assert_eq!(resolve_value("{file:~/no-such-key}", &base), None);
+        assert_eq!(
+            resolve_file_path("~/no-such-key", &base),
+            home.join("no-such-key")
+        );
+    }
+
+    #[test]
+    fn scans_every_place_opencode_substitutes() {
+        std::env::remove_var("OPEN_SWITCH_TEST_UNSET");
+        let config: OpenCodeConfig = serde_json::from_value(json!({
+            "provider": {
+                "acme": {
+                    "npm": "@ai-sdk/openai-compatible",
+                    "name": "Acme",
+                    "models": {},
+                    "options": { "baseURL": "https://acme.example", "apiKey": "{env:OPEN_SWITCH_TEST_UNSET}" }
+                }
+            },
+            "mcp": {
+                "db": {
+                    "type": "local",
+                    "command": ["db-mcp", "--url", "postgres://u:{file:missing-pass}@host/db"],
+                    "environment": { "PLAIN": "value" }
+                },
+                "remote": {
+                    "type": "remote",
+                    "url": "https://mcp.example/{env:OPEN_SWITCH_TEST_UNSET}",
+                    "headers": { "X-Key": "{env:OPEN_SWITCH_TEST_UNSET}" }
+                }
+            }
+        }))
+        .unwrap();
+
+        let reports = scan_config(&config, Path::new("/nonexistent"));
+        let targets: Vec<String> = reports.iter().map(|r| r.target.describe()).collect();
+        assert_eq!(
+            targets,
+            [
+                "provider:acme:apiKey",
+                "mcp:db:command:2",
+                "mcp:remote:header:X-Key",
+                "mcp:remote:url"
+            ]
+        );
+        assert!(matches!(reports[0].status, ReferenceStatus::UnsetVariable));
+        assert!(matches!(reports[1].status, ReferenceStatus::MissingFile));
+        let missing = Path::new("/nonexistent").join("missing-pass");
+        assert_eq!(reports[1].resolved_path, Some(missing.display().to_string()));
+        assert!(reports.iter().all(|r| r.preview.is_none()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn converts_inline_secrets_to_placeholders() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let _files = crate::config::tests::config_files();
+        let config: OpenCodeConfig = serde_json::from_value(json!({
+            "mcp": {
+                "db": {
+                    "type": "local",
+                    "command": ["db-mcp"],
+                    "environment": { "API_KEY": "sk-inline-secret", "TOKEN": "tok-inline" }
+                }
+            }
+        }))
+        .unwrap();
+        save_opencode_config(&config).unwrap();
+        let key = SecretTarget::McpEnvironment {
+            server: "db".to_string(),
+            name: "API_KEY".to_string(),
+        };
+
+        let path = format!("secrets/db-key-{}", std::process::id());
+        let result = convert_to_reference(&key, &ReferenceRequest::File { path: path.clone() }).unwrap();
+
+        assert_eq!(result.placeholder, format!("{{file:{path}}}"));
+        let written = config_dir().unwrap().join(&path);
+        assert_eq!(std::fs::read_to_string(&written).unwrap(), "sk-inline-secret");
+        let mode = std::fs::metadata(&written).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600);
+        let environment = get_opencode_config().unwrap().mcp.unwrap()["db"].environment.clone().unwrap();
+        assert_eq!(environment["API_KEY"], result.placeholder);
+        assert_eq!(get_reference_reports().unwrap().len(), 1);
+
+        // Already a placeholder, and the file is not overwritten.
+        assert!(convert_to_reference(&key, &ReferenceRequest::File { path }).is_err());
+
+        let token = SecretTarget::McpEnvironment {
+            server: "db".to_string(),
+            name: "TOKEN".to_string(),
+        };
+        std::env::remove_var("OPEN_SWITCH_TEST_TOKEN");
+        let env = ReferenceRequest::Env {
+            name: "OPEN_SWITCH_TEST_TOKEN".to_string(),
+        };
+        let result = convert_to_reference(&token, &env).unwrap();
+        assert_eq!(result.placeholder, "{env:OPEN_SWITCH_TEST_TOKEN}");
+        assert!(result.warning.unwrap().contains("Export OPEN_SWITCH_TEST_TOKEN"));
+        let bad = ReferenceRequest::Env {
+            name: "NOT VALID".to_string(),
+        };
+        assert!(convert_to_reference(&key, &bad).is_err());
+    }
+}
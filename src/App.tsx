@@ -1,11 +1,12 @@
-import { useState } from "react";
+import { useEffect, useRef, useState } from "react";
 import { useTranslation } from "react-i18next";
 import { useQuery, useMutation, useQueryClient } from "@tanstack/react-query";
 import { toast } from "sonner";
 import { getCurrentWindow } from "@tauri-apps/api/window";
+import { listen } from "@tauri-apps/api/event";
 import { Plus, Trash2, Edit, AlertTriangle, Box, Sparkles, Eye, EyeOff, Settings, Globe, Server, FileText, Download, Check, Github, ExternalLink, Minus, Square, X } from "lucide-react";
 import { configApi, authApi, mcpApi, promptsApi } from "@/lib/api";
-import type { ProviderConfig, OpenCodeModel, SdkType, McpServer, McpServerType, Prompt } from "@/types";
+import type { ProviderConfig, OpenCodeModel, SdkType, McpServer, McpServerType, Prompt, ExpiringCredential, DriftResolution } from "@/types";
 import { SDK_OPTIONS } from "@/types";
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
@@ -94,9 +95,10 @@ function App() {
 
   const [isPromptAddOpen, setIsPromptAddOpen] = useState(false);
   const [editingPrompt, setEditingPrompt] = useState<string | null>(null);
-  const [promptFormData, setPromptFormData] = useState({ name: "", description: "", content: "" });
+  const [promptFormData, setPromptFormData] = useState({ name: "", description: "", content: "", tags: "", folder: "" });
   const [promptDeleteConfirmOpen, setPromptDeleteConfirmOpen] = useState(false);
   const [promptToDelete, setPromptToDelete] = useState<string | null>(null);
+  const [driftOpen, setDriftOpen] = useState(false);
 
   const { data: config, isLoading } = useQuery({
     queryKey: ["config"],
@@ -118,6 +120,47 @@ function App() {
     queryFn: promptsApi.getAll,
   });
 
+  const { data: promptDrift } = useQuery({
+    queryKey: ["promptDrift"],
+    queryFn: () => promptsApi.getDrift(),
+    enabled: activeTab === "prompts",
+  });
+
+  const { data: autoBackfill } = useQuery({
+    queryKey: ["promptAutoBackfill"],
+    queryFn: promptsApi.getAutoBackfill,
+  });
+
+  // A rewrite refused because AGENTS.md was edited by hand is settled in the
+  // drift dialog.
+  const reviewDrift = () => {
+    queryClient
+      .fetchQuery({ queryKey: ["promptDrift"], queryFn: () => promptsApi.getDrift() })
+      .then((drift) => drift && setDriftOpen(true))
+      .catch(() => {});
+  };
+
+  // Startup warnings arrive as events once the page has loaded; ones sent
+  // before the listener was ready are fetched instead.
+  const warnedCredentials = useRef(new Set<string>());
+  useEffect(() => {
+    const warn = (credential: ExpiringCredential) => {
+      if (warnedCredentials.current.has(credential.id)) return;
+      warnedCredentials.current.add(credential.id);
+      toast.warning(
+        credential.expired
+          ? t("key.expired", { id: credential.id })
+          : t("key.expiresIn", { id: credential.id, count: credential.daysLeft }),
+        { description: credential.ownerNote },
+      );
+    };
+    const unlisten = listen<ExpiringCredential>("credential-expiring", (event) => warn(event.payload));
+    authApi.getStartupExpiring().then((expiring) => expiring.forEach(warn)).catch(() => {});
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, [t]);
+
   const addMutation = useMutation({
     mutationFn: ({ id, provider }: { id: string; provider: ProviderConfig }) =>
       configApi.addProvider(id, provider),
@@ -225,6 +268,7 @@ function App() {
     },
     onError: (error) => {
       toast.error(`${editingPrompt ? t("prompts.updateFailed") : t("prompts.addFailed")}: ${error}`);
+      reviewDrift();
     },
   });
 
@@ -241,13 +285,37 @@ function App() {
   });
 
   const enablePromptMutation = useMutation({
-    mutationFn: promptsApi.enable,
+    mutationFn: (id: string) => promptsApi.enable(id),
     onSuccess: () => {
       queryClient.invalidateQueries({ queryKey: ["prompts"] });
       toast.success(t("prompts.enabledSuccess"));
     },
     onError: (error) => {
       toast.error(`${t("prompts.enableFailed")}: ${error}`);
+      reviewDrift();
+    },
+  });
+
+  const resolveDriftMutation = useMutation({
+    mutationFn: (resolution: DriftResolution) => promptsApi.resolveDrift(resolution),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: ["prompts"] });
+      queryClient.invalidateQueries({ queryKey: ["promptDrift"] });
+      toast.success(t("prompts.driftResolved"));
+      setDriftOpen(false);
+    },
+    onError: (error) => {
+      toast.error(`${t("prompts.driftResolveFailed")}: ${error}`);
+    },
+  });
+
+  const autoBackfillMutation = useMutation({
+    mutationFn: promptsApi.setAutoBackfill,
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: ["promptAutoBackfill"] });
+    },
+    onError: (error) => {
+      toast.error(`${t("settings.saveFailed")}: ${error}`);
     },
   });
 
@@ -293,7 +361,7 @@ function App() {
   const closePromptDialog = () => {
     setIsPromptAddOpen(false);
     setEditingPrompt(null);
-    setPromptFormData({ name: "", description: "", content: "" });
+    setPromptFormData({ name: "", description: "", content: "", tags: "", folder: "" });
   };
 
   const closePromptDeleteDialog = () => {
@@ -306,6 +374,8 @@ function App() {
       name: prompt.name,
       description: prompt.description ?? "",
       content: prompt.content,
+      tags: (prompt.tags ?? []).join(", "),
+      folder: prompt.folder ?? "",
     });
     setEditingPrompt(id);
   };
@@ -331,6 +401,8 @@ function App() {
       name: promptFormData.name.trim(),
       content: promptFormData.content,
       description: promptFormData.description.trim() || undefined,
+      tags: promptFormData.tags.split(",").map((tag) => tag.trim()).filter(Boolean),
+      folder: promptFormData.folder.trim() || undefined,
       enabled: false,
       createdAt: editingPrompt ? undefined : now,
       updatedAt: now,
@@ -1333,6 +1405,18 @@ function App() {
 
         {activeTab === "prompts" && (
           <>
+            {promptDrift && (
+              <div className="mb-4 flex items-center justify-between gap-4 rounded-lg border border-amber-500/30 bg-amber-500/10 px-4 py-3 text-sm">
+                <div className="flex items-center gap-2 text-amber-600 dark:text-amber-400">
+                  <AlertTriangle className="h-4 w-4 shrink-0" />
+                  <span>{t("prompts.driftBanner")}</span>
+                </div>
+                <Button variant="outline" size="sm" onClick={() => setDriftOpen(true)}>
+                  {t("prompts.driftReview")}
+                </Button>
+              </div>
+            )}
+
             {isPromptsLoading ? (
               <div className="grid gap-4">
                 {[1, 2].map((i) => (
@@ -1483,6 +1567,33 @@ function App() {
                     />
                   </div>
 
+                  <div className="grid grid-cols-2 gap-4">
+                    <div className="space-y-2">
+                      <Label htmlFor="promptTags" className="flex items-center gap-2">
+                        {t("prompts.tags")}
+                        <span className="text-xs text-muted-foreground">({t("form.headersOptional")})</span>
+                      </Label>
+                      <Input
+                        id="promptTags"
+                        placeholder={t("prompts.tagsPlaceholder")}
+                        value={promptFormData.tags}
+                        onChange={(e) => setPromptFormData((prev) => ({ ...prev, tags: e.target.value }))}
+                      />
+                    </div>
+                    <div className="space-y-2">
+                      <Label htmlFor="promptFolder" className="flex items-center gap-2">
+                        {t("prompts.folder")}
+                        <span className="text-xs text-muted-foreground">({t("form.headersOptional")})</span>
+                      </Label>
+                      <Input
+                        id="promptFolder"
+                        placeholder={t("prompts.folderPlaceholder")}
+                        value={promptFormData.folder}
+                        onChange={(e) => setPromptFormData((prev) => ({ ...prev, folder: e.target.value }))}
+                      />
+                    </div>
+                  </div>
+
                   <div className="space-y-2">
                     <Label htmlFor="promptContent">{t("prompts.content")}</Label>
                     <textarea
@@ -1537,6 +1648,79 @@ function App() {
                 </DialogFooter>
               </DialogContent>
             </Dialog>
+
+            <Dialog open={driftOpen && !!promptDrift} onOpenChange={setDriftOpen}>
+              <DialogContent className="max-h-[90vh] overflow-y-auto sm:max-w-2xl border-border/80 bg-card/95 backdrop-blur-xl">
+                <DialogHeader>
+                  <div className="flex items-center gap-2">
+                    <div className="flex h-10 w-10 items-center justify-center rounded-full bg-amber-500/10 text-amber-500">
+                      <AlertTriangle className="h-5 w-5" />
+                    </div>
+                    <DialogTitle>{t("prompts.driftTitle")}</DialogTitle>
+                  </div>
+                  <DialogDescription className="pt-2">
+                    {t("prompts.driftDescription")}
+                    <span className="mt-1 block font-mono text-xs">{promptDrift?.path}</span>
+                  </DialogDescription>
+                </DialogHeader>
+                {promptDrift && (
+                  <div className="space-y-3">
+                    {promptDrift.prompts.length > 0 && (
+                      <div className="text-sm">
+                        <span className="text-muted-foreground">{t("prompts.driftPrompts")}: </span>
+                        <span className="font-medium text-foreground">
+                          {promptDrift.prompts.map((id) => prompts?.[id]?.name ?? id).join(", ")}
+                        </span>
+                      </div>
+                    )}
+                    {promptDrift.unassigned && (
+                      <p className="text-sm text-muted-foreground">{t("prompts.driftUnassigned")}</p>
+                    )}
+                    <div className="max-h-80 overflow-auto rounded-lg bg-black/20 p-3 ring-1 ring-white/5">
+                      <pre className="font-mono text-xs whitespace-pre-wrap break-all">
+                        {promptDrift.lines.map((line, i) => (
+                          <div
+                            key={i}
+                            className={
+                              line.kind === "insert"
+                                ? "bg-green-500/10 text-green-500"
+                                : line.kind === "delete"
+                                  ? "bg-red-500/10 text-red-500"
+                                  : "text-muted-foreground"
+                            }
+                          >
+                            {line.kind === "insert" ? "+ " : line.kind === "delete" ? "- " : "  "}
+                            {line.text}
+                          </div>
+                        ))}
+                      </pre>
+                    </div>
+                  </div>
+                )}
+                <DialogFooter className="mt-4 gap-2 sm:gap-0">
+                  <Button
+                    variant="ghost"
+                    onClick={() => resolveDriftMutation.mutate("keep-stored")}
+                    disabled={resolveDriftMutation.isPending}
+                  >
+                    {t("prompts.driftKeepStored")}
+                  </Button>
+                  <Button
+                    variant="outline"
+                    onClick={() => resolveDriftMutation.mutate("save-as-new")}
+                    disabled={resolveDriftMutation.isPending}
+                  >
+                    {t("prompts.driftSaveAsNew")}
+                  </Button>
+                  <Button
+                    onClick={() => resolveDriftMutation.mutate("keep-file")}
+                    disabled={resolveDriftMutation.isPending}
+                  >
+                    {t("prompts.driftKeepFile")}
+                  </Button>
+                </DialogFooter>
+              </DialogContent>
+            </Dialog>
           </>
         )}
 
@@ -1568,6 +1752,22 @@ function App() {
                 </Select>
               </div>
 
+              <div className="flex items-center justify-between gap-4">
+                <div className="space-y-0.5">
+                  <div className="flex items-center gap-2">
+                    <FileText className="h-4 w-4 text-muted-foreground" />
+                    <Label htmlFor="auto-backfill">{t("settings.autoBackfill")}</Label>
+                  </div>
+                  <p className="text-xs text-muted-foreground">{t("settings.autoBackfillHint")}</p>
+                </div>
+                <Switch
+                  id="auto-backfill"
+                  checked={autoBackfill ?? false}
+                  onCheckedChange={(enabled) => autoBackfillMutation.mutate(enabled)}
+                  disabled={autoBackfillMutation.isPending}
+                />
+              </div>
+
               <div className="border-t border-border/50 pt-4">
                 <div className="flex items-center gap-2 mb-3">
                   <Sparkles className="h-4 w-4 text-muted-foreground" />
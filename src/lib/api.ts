@@ -1,5 +1,51 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { OpenCodeConfig, ProviderConfig, AuthConfig, McpServer, Prompt } from "@/types";
+import type {
+  OpenCodeConfig,
+  ProviderConfig,
+  AuthConfig,
+  CredentialStatus,
+  CredentialMetadata,
+  PreviousCredential,
+  ExpiringCredential,
+  McpServer,
+  McpCheckResult,
+  McpInventory,
+  McpClient,
+  McpSource,
+  ImportPreview,
+  ImportSummary,
+  McpExport,
+  McpTemplate,
+  ResolvedCommand,
+  McpLogEntry,
+  SupervisedServer,
+  McpSelection,
+  McpBulkAction,
+  McpBulkResult,
+  McpTemplateValues,
+  Prompt,
+  PromptVersion,
+  PromptSearchHit,
+  LibraryExport,
+  LibraryImport,
+  PromptSyncStatus,
+  SyncReport,
+  SyncResolution,
+  Project,
+  ProjectStatus,
+  AgentsFileState,
+  AgentsMdMode,
+  PromptDrift,
+  DriftResolution,
+  PromptVersionDiff,
+  SecretTarget,
+  SecretAccessEntry,
+  PermissionDiagnostic,
+  ReferenceReport,
+  ReferenceRequest,
+  ConversionResult,
+  RenameResult,
+} from "@/types";
 
 export const configApi = {
   getConfig: () => invoke<OpenCodeConfig>("get_config"),
@@ -15,6 +61,18 @@ export const configApi = {
 
   deleteProvider: (id: string) => invoke<boolean>("delete_provider", { id }),
 
+  renameProvider: (oldId: string, newId: string) =>
+    invoke<RenameResult>("rename_provider", { oldId, newId }),
+
+  duplicateProvider: (sourceId: string, newId: string) =>
+    invoke<boolean>("duplicate_provider", { sourceId, newId }),
+
+  reorderProviders: (order: string[]) =>
+    invoke<boolean>("reorder_providers", { order }),
+
+  reorderModels: (providerId: string, order: string[]) =>
+    invoke<boolean>("reorder_models", { providerId, order }),
+
   getConfigPath: () => invoke<string>("get_config_path"),
 };
 
@@ -28,6 +86,42 @@ export const authApi = {
     invoke<boolean>("delete_credential", { id }),
 
   hasCredential: (id: string) => invoke<boolean>("has_credential", { id }),
+
+  getStatuses: () => invoke<Record<string, CredentialStatus>>("get_credential_statuses"),
+
+  getMetadata: () => invoke<Record<string, CredentialMetadata>>("get_credential_metadata"),
+
+  updateMetadata: (metadata: CredentialMetadata) =>
+    invoke<boolean>("update_credential_metadata", { metadata }),
+
+  rotate: (id: string, apiKey: string, graceDays?: number, validDays?: number) =>
+    invoke<CredentialMetadata>("rotate_credential", { id, apiKey, graceDays, validDays }),
+
+  getPrevious: (id: string) => invoke<PreviousCredential[]>("get_previous_credentials", { id }),
+
+  restorePrevious: (historyId: number) =>
+    invoke<boolean>("restore_previous_credential", { historyId }),
+
+  getExpiring: (withinDays?: number) =>
+    invoke<ExpiringCredential[]>("get_expiring_credentials", { withinDays }),
+
+  getStartupExpiring: () =>
+    invoke<ExpiringCredential[]>("get_startup_expiring_credentials"),
+};
+
+export const secretsApi = {
+  reveal: (target: SecretTarget) => invoke<string>("reveal_secret", { target }),
+
+  getAccessLog: (limit?: number) =>
+    invoke<SecretAccessEntry[]>("get_secret_access_log", { limit }),
+
+  getPermissionDiagnostics: () =>
+    invoke<PermissionDiagnostic[]>("get_permission_diagnostics"),
+
+  getReferences: () => invoke<ReferenceReport[]>("get_secret_references"),
+
+  convertToReference: (target: SecretTarget, reference: ReferenceRequest) =>
+    invoke<ConversionResult>("convert_secret_to_reference", { target, reference }),
 };
 
 export const mcpApi = {
@@ -43,6 +137,61 @@ export const mcpApi = {
 
   toggleServer: (name: string, enabled: boolean) =>
     invoke<boolean>("toggle_mcp_server", { name, enabled }),
+
+  renameServer: (oldName: string, newName: string) =>
+    invoke<string[]>("rename_mcp_server", { oldName, newName }),
+
+  duplicateServer: (sourceName: string, newName: string) =>
+    invoke<boolean>("duplicate_mcp_server", { sourceName, newName }),
+
+  reorderServers: (order: string[]) =>
+    invoke<boolean>("reorder_mcp_servers", { order }),
+
+  getTags: () => invoke<Record<string, string[]>>("get_mcp_tags"),
+
+  setTags: (name: string, tags: string[]) =>
+    invoke<string[]>("set_mcp_server_tags", { name, tags }),
+
+  bulkUpdate: (selection: McpSelection, action: McpBulkAction) =>
+    invoke<McpBulkResult>("bulk_update_mcp_servers", { selection, action }),
+
+  checkServer: (name: string, server?: McpServer) =>
+    invoke<McpCheckResult>("check_mcp_server", { name, server }),
+
+  inspectServer: (name: string) => invoke<McpInventory>("inspect_mcp_server", { name }),
+
+  discoverSources: () => invoke<McpSource[]>("discover_mcp_sources"),
+
+  previewImport: (path: string, client?: McpClient) =>
+    invoke<ImportPreview>("preview_mcp_import", { path, client }),
+
+  importServers: (path: string, names: string[], overwrite: boolean, client?: McpClient) =>
+    invoke<ImportSummary>("import_mcp_servers", { path, client, names, overwrite }),
+
+  exportServers: (client: McpClient, names?: string[], targetPath?: string) =>
+    invoke<McpExport>("export_mcp_servers", { client, names, targetPath }),
+
+  resolveCommand: (command: string[], environment?: Record<string, string>) =>
+    invoke<ResolvedCommand>("resolve_mcp_command", { command, environment }),
+
+  startServer: (name: string) => invoke<SupervisedServer>("start_mcp_server", { name }),
+
+  stopServer: (name: string) => invoke<boolean>("stop_mcp_server", { name }),
+
+  sendRequest: (name: string, method: string, params?: unknown, timeoutMs?: number) =>
+    invoke<unknown>("send_mcp_request", { name, method, params, timeoutMs }),
+
+  getSupervised: () => invoke<SupervisedServer[]>("get_supervised_mcp_servers"),
+
+  getLogs: (name: string, limit?: number, beforeId?: number) =>
+    invoke<McpLogEntry[]>("get_mcp_logs", { name, limit, beforeId }),
+
+  clearLogs: (name?: string) => invoke<number>("clear_mcp_logs", { name }),
+
+  getTemplates: () => invoke<McpTemplate[]>("get_mcp_templates"),
+
+  addFromTemplate: (templateId: string, name: string, params: McpTemplateValues) =>
+    invoke<McpServer>("add_mcp_from_template", { templateId, name, params }),
 };
 
 export const instructionsApi = {
@@ -62,9 +211,80 @@ export const promptsApi = {
 
   delete: (id: string) => invoke<boolean>("delete_prompt", { id }),
 
-  enable: (id: string) => invoke<boolean>("enable_prompt", { id }),
+  enable: (id: string, projectId?: string) =>
+    invoke<boolean>("enable_prompt", { id, projectId }),
+
+  setEnabled: (id: string, enabled: boolean, projectId?: string) =>
+    invoke<boolean>("set_prompt_enabled", { id, enabled, projectId }),
+
+  reorderEnabled: (order: string[], projectId?: string) =>
+    invoke<boolean>("reorder_enabled_prompts", { order, projectId }),
 
   importFromFile: () => invoke<string>("import_prompt_from_file"),
 
-  getCurrentFileContent: () => invoke<string | null>("get_current_prompt_file_content"),
+  getCurrentFileContent: (projectId?: string) =>
+    invoke<string | null>("get_current_prompt_file_content", { projectId }),
+
+  getFileState: (projectId?: string) =>
+    invoke<AgentsFileState>("get_agents_md_state", { projectId }),
+
+  getDrift: (projectId?: string) =>
+    invoke<PromptDrift | null>("get_prompt_drift", { projectId }),
+
+  resolveDrift: (resolution: DriftResolution, projectId?: string) =>
+    invoke<boolean>("resolve_prompt_drift", { resolution, projectId }),
+
+  getAutoBackfill: () => invoke<boolean>("get_prompt_auto_backfill"),
+
+  setAutoBackfill: (enabled: boolean) =>
+    invoke<boolean>("set_prompt_auto_backfill", { enabled }),
+
+  getAgentsMdMode: () => invoke<AgentsMdMode>("get_agents_md_mode"),
+
+  setAgentsMdMode: (mode: AgentsMdMode) => invoke<boolean>("set_agents_md_mode", { mode }),
+
+  getProjects: () => invoke<ProjectStatus[]>("get_projects"),
+
+  addProject: (path: string, name?: string) =>
+    invoke<Project>("add_project", { path, name }),
+
+  removeProject: (id: string) => invoke<boolean>("remove_project", { id }),
+
+  search: (query: string, options?: { tags?: string[]; folder?: string; limit?: number }) =>
+    invoke<PromptSearchHit[]>("search_prompts", { query, ...options }),
+
+  exportLibrary: (directory: string, ids?: string[]) =>
+    invoke<LibraryExport>("export_prompt_library", { directory, ids }),
+
+  importLibrary: (directory: string, dryRun?: boolean) =>
+    invoke<LibraryImport>("import_prompt_library", { directory, dryRun }),
+
+  getSyncStatus: () => invoke<PromptSyncStatus | null>("get_prompt_sync_status"),
+
+  setSync: (path: string | null, options?: { remote?: string; directory?: string }) =>
+    invoke<PromptSyncStatus | null>("set_prompt_sync", { path, ...options }),
+
+  sync: () => invoke<SyncReport>("sync_prompts"),
+
+  resolveSyncConflict: (promptId: string, resolution: SyncResolution) =>
+    invoke<boolean>("resolve_prompt_sync_conflict", { promptId, resolution }),
+
+  getVersions: (promptId: string) =>
+    invoke<PromptVersion[]>("get_prompt_versions", { promptId }),
+
+  diffVersions: (fromId: number, toId: number) =>
+    invoke<PromptVersionDiff>("diff_prompt_versions", { fromId, toId }),
+
+  restoreVersion: (versionId: number) =>
+    invoke<Prompt>("restore_prompt_version", { versionId }),
+
+  preview: (id: string, content?: string) =>
+    invoke<string>("preview_prompt", { id, content }),
+
+  getVariables: () => invoke<Record<string, string>>("get_prompt_variables"),
+
+  setVariable: (name: string, value: string) =>
+    invoke<boolean>("set_prompt_variable", { name, value }),
+
+  deleteVariable: (name: string) => invoke<boolean>("delete_prompt_variable", { name }),
 };
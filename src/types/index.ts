@@ -29,21 +29,251 @@ export interface McpServer {
   headers?: Record<string, string>;
 }
 
+export interface McpServerInfo {
+  protocolVersion?: string | null;
+  name?: string | null;
+  version?: string | null;
+  capabilities: Record<string, unknown>;
+  instructions?: string;
+}
+
+export type McpTransportKind = "stdio" | "streamableHttp" | "sse";
+
+export interface McpCheckResult {
+  ok: boolean;
+  transport?: McpTransportKind;
+  server?: McpServerInfo;
+  error?: string;
+  authFailed: boolean;
+  httpStatus?: number;
+  stderr: string;
+  elapsedMs: number;
+}
+
+export interface RiskyTool {
+  name: string;
+  reasons: string[];
+}
+
+export interface McpInventory {
+  server: McpServerInfo;
+  transport: McpTransportKind;
+  tools: Record<string, unknown>[];
+  resources: Record<string, unknown>[];
+  prompts: Record<string, unknown>[];
+  riskyTools: RiskyTool[];
+  warnings: string[];
+  stderr: string;
+}
+
+export type McpClient = "claudeDesktop" | "cursor" | "vsCode" | "windsurf";
+
+export interface McpSource {
+  client: McpClient;
+  path: string;
+  serverCount: number;
+}
+
+export interface ImportCandidate {
+  name: string;
+  server?: McpServer;
+  conflict: boolean;
+  warnings?: string[];
+  error?: string;
+}
+
+export interface ImportPreview {
+  client: McpClient;
+  path: string;
+  candidates: ImportCandidate[];
+}
+
+export interface ImportSummary {
+  imported: string[];
+  skipped: string[];
+}
+
+export interface ExportSkip {
+  name: string;
+  reason: string;
+}
+
+export interface McpExport {
+  client: McpClient;
+  document: Record<string, unknown>;
+  path?: string;
+  exported: string[];
+  skipped?: ExportSkip[];
+  warnings?: string[];
+}
+
+export type McpTemplateParamKind = "path" | "pathList" | "text" | "secret" | "flag";
+
+export type McpTemplateParamTarget =
+  | { kind: "arg" }
+  | { kind: "option"; name: string }
+  | { kind: "flag"; name: string }
+  | { kind: "env"; name: string };
+
+export interface McpTemplateParam {
+  key: string;
+  label: string;
+  kind: McpTemplateParamKind;
+  target: McpTemplateParamTarget;
+  required: boolean;
+  description?: string;
+  choices?: string[];
+  prefixes?: string[];
+}
+
+export interface McpTemplate {
+  id: string;
+  name: string;
+  description: string;
+  command: string[];
+  params: McpTemplateParam[];
+}
+
+export type McpTemplateValues = Record<string, string | string[] | boolean | null>;
+
+export interface ResolvedCommand {
+  command: string[];
+  executable?: string;
+  source?: string;
+  shellSyntax?: string[];
+  warnings?: string[];
+}
+
+export type McpTraffic = "sent" | "received" | "stdout" | "stderr" | "exited" | "lifecycle";
+
+export interface McpLogEntry {
+  id?: number;
+  server: string;
+  stream: McpTraffic;
+  message: string;
+  timestamp: number;
+}
+
+export interface SupervisedServer {
+  name: string;
+  pid: number;
+  command: string[];
+  startedAt: number;
+  running: boolean;
+  server?: McpServerInfo;
+}
+
+export interface McpSelection {
+  names?: string[];
+  tags?: string[];
+}
+
+export type McpBulkAction = "enable" | "disable" | "delete";
+
+export interface McpBulkResult {
+  affected: string[];
+  missing?: string[];
+}
+
+export interface RenameResult {
+  references: string[];
+  credentialMoved: boolean;
+}
+
 export interface OpenCodeConfig {
   $schema?: string;
   plugin?: string[];
   provider: Record<string, ProviderConfig>;
   mcp?: Record<string, McpServer>;
   instructions?: string[];
+  model?: string;
+  small_model?: string;
+  agent?: Record<string, { model?: string; [key: string]: unknown }>;
+  [key: string]: unknown;
 }
 
-export interface Credential {
-  type: string;
-  key: string;
+export type Credential =
+  | { type: "api"; key: string; [extra: string]: unknown }
+  | { type: "oauth"; refresh: string; access: string; expires: number; [extra: string]: unknown }
+  | { type: "wellknown"; key: string; token: string; [extra: string]: unknown }
+  | { type: string; [extra: string]: unknown };
+
+export interface CredentialStatus {
+  kind: string;
+  expiresAt?: number;
+  expiresInSecs?: number;
+  expired: boolean;
 }
 
 export type AuthConfig = Record<string, Credential>;
 
+export interface CredentialMetadata {
+  id: string;
+  createdAt?: number | null;
+  lastRotatedAt?: number | null;
+  expiresAt?: number | null;
+  ownerNote?: string | null;
+}
+
+export interface PreviousCredential {
+  historyId: number;
+  credentialId: string;
+  rotatedAt: number;
+  recoverableUntil: number;
+  credential: Credential;
+}
+
+export interface ExpiringCredential {
+  id: string;
+  expiresAt: number;
+  daysLeft: number;
+  expired: boolean;
+  ownerNote?: string;
+}
+
+export type SecretTarget =
+  | { kind: "credential"; id: string; field?: "key" | "access" | "refresh" | "token" }
+  | { kind: "providerApiKey"; provider: string }
+  | { kind: "providerHeader"; provider: string; name: string }
+  | { kind: "mcpEnvironment"; server: string; name: string }
+  | { kind: "mcpHeader"; server: string; name: string }
+  | { kind: "mcpUrl"; server: string }
+  | { kind: "mcpArgument"; server: string; index: number };
+
+export interface PermissionDiagnostic {
+  level: "warning";
+  path: string;
+  mode: string;
+  message: string;
+  repaired: boolean;
+}
+
+export type ReferenceStatus =
+  | { status: "resolved" }
+  | { status: "unsetVariable" }
+  | { status: "missingFile" }
+  | { status: "unreadable"; error: string };
+
+export type ReferenceReport = {
+  target: SecretTarget;
+  kind: "env" | "file";
+  reference: string;
+  resolvedPath?: string;
+  preview?: string;
+} & ReferenceStatus;
+
+export type ReferenceRequest = { kind: "env"; name: string } | { kind: "file"; path: string };
+
+export interface ConversionResult {
+  placeholder: string;
+  warning?: string;
+}
+
+export interface SecretAccessEntry {
+  target: string;
+  accessedAt: number;
+}
+
 export interface Prompt {
   id: string;
   name: string;
@@ -52,6 +282,129 @@ export interface Prompt {
   enabled: boolean;
   createdAt?: number;
   updatedAt?: number;
+  position?: number;
+  tags?: string[];
+  folder?: string;
+}
+
+export interface TextPart {
+  text: string;
+  highlight: boolean;
+}
+
+export interface PromptSearchHit {
+  prompt: Prompt;
+  name: TextPart[];
+  snippet?: TextPart[];
+  score?: number;
+}
+
+export interface LibraryExport {
+  directory: string;
+  exported: { id: string; file: string }[];
+  missing?: string[];
+}
+
+export type LibraryAction = "create" | "update" | "skip" | "error";
+
+export interface LibraryImportItem {
+  file: string;
+  action: LibraryAction;
+  promptId?: string;
+  reason?: string;
+}
+
+export interface LibraryImport {
+  directory: string;
+  dryRun: boolean;
+  items: LibraryImportItem[];
+}
+
+export type SyncConflictKind = "bothChanged" | "deletedHere" | "deletedThere" | "enabled";
+
+export interface SyncConflict {
+  promptId: string;
+  name: string;
+  file: string;
+  kind: SyncConflictKind;
+}
+
+export interface SyncReport {
+  pulled: string[];
+  deleted: string[];
+  pushed: string[];
+  conflicts: SyncConflict[];
+  errors?: string[];
+  pushedCommits: boolean;
+}
+
+export interface PromptSyncStatus {
+  path: string;
+  directory: string;
+  branch?: string;
+  upstream?: string;
+  ahead: number;
+  behind: number;
+  conflicts: SyncConflict[];
+  lastSyncedAt?: number;
+}
+
+export type SyncResolution = "local" | "remote";
+
+export interface Project {
+  id: string;
+  name: string;
+  path: string;
+  createdAt: number;
+}
+
+export type AgentsFileState = "missing" | "managed" | "modified" | "handWritten";
+
+export type AgentsMdMode = "wholeFile" | "region";
+
+export interface ProjectStatus extends Project {
+  agentsMd: AgentsFileState;
+  prompts: string[];
+}
+
+export interface PromptDrift extends TextDiff {
+  path: string;
+  prompts: string[];
+  unassigned?: string;
+}
+
+export type DriftResolution = "keep-file" | "keep-stored" | "save-as-new";
+
+export type PromptSource = "initial" | "ui" | "backfill" | "import" | "restore";
+
+export interface PromptVersion {
+  id: number;
+  promptId: string;
+  version: number;
+  name: string;
+  content: string;
+  description?: string;
+  source: PromptSource;
+  author?: string;
+  createdAt: number;
+}
+
+export interface DiffLine {
+  kind: "equal" | "insert" | "delete";
+  text: string;
+  oldLine?: number;
+  newLine?: number;
+}
+
+export interface TextDiff {
+  lines: DiffLine[];
+  added: number;
+  removed: number;
+}
+
+export interface PromptVersionDiff extends TextDiff {
+  from: PromptVersion;
+  to: PromptVersion;
 }
 
 export type SdkType =